@@ -0,0 +1,248 @@
+//! Headless batch front-end for the VibeDither pipeline.
+//!
+//! `vibedither-cli assets/*.png --mode bayer --set dither_scale=2 -o out/`
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use vibedither::color::parse_hex_color;
+use vibedither::image_io::{self, ExportFormat};
+use vibedither::pipeline::{self, diffusion, ColorSettings};
+use vibedither::threshold::matrix;
+use vibedither::{palette, preset, Backend, CurvePoint, GradientStop, Look, Renderer};
+
+#[derive(Parser)]
+#[command(name = "vibedither-cli", version, about = "Dither images in batch with the VibeDither pipeline")]
+struct Args {
+    /// Image files, directories or glob patterns (e.g. "assets/**/*.png")
+    #[arg(required = true)]
+    inputs: Vec<String>,
+    /// Output directory. Defaults to writing next to each input
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Output format (png, jpg, webp, svg, tif). Defaults to the input's own format
+    #[arg(short, long, value_parser = parse_format)]
+    format: Option<ExportFormat>,
+    /// PNG compression or JPG quality, 0..1
+    #[arg(short, long, default_value_t = 0.8)]
+    quality: f32,
+    /// Bits per channel, 8 or 16. 16 applies to PNG and TIFF output and needs dithering off
+    #[arg(long, default_value_t = 8, value_parser = parse_depth)]
+    depth: u8,
+    /// Background transparent pixels are flattened onto. JPG and SVG are always flattened, onto white by default; giving a matte flattens every format
+    #[arg(long, value_name = "#RRGGBB", value_parser = parse_matte)]
+    matte: Option<[u8; 3]>,
+    /// Look preset (.vdpreset) to start from
+    #[arg(short, long)]
+    preset: Option<PathBuf>,
+    /// Write the final look (preset plus overrides) to this .vdpreset file
+    #[arg(long, value_name = "FILE")]
+    save_preset: Option<PathBuf>,
+    /// Dither algorithm by name or index (e.g. "bayer", "blue-noise", "3"), or an error-diffusion kernel (e.g. "floyd-steinberg", "atkinson")
+    #[arg(short, long)]
+    mode: Option<String>,
+    /// Override one ColorSettings field, e.g. --set dither_scale=2 (repeatable)
+    #[arg(long = "set", value_name = "FIELD=VALUE")]
+    sets: Vec<String>,
+    /// Curve points for one channel, e.g. --curve "master:0,0;0.4,0.6;1,1" (repeatable)
+    #[arg(long = "curve", value_name = "CHANNEL:X,Y;...")]
+    curves: Vec<String>,
+    /// Ordered-dither threshold matrix from a CSV or grayscale image. Selects Bayer mode with the Custom matrix
+    #[arg(long, value_name = "FILE")]
+    matrix: Option<PathBuf>,
+    /// Gradient remap stops, e.g. --gradient "0:#000000,1:#ffcc00". Enables the remap
+    #[arg(long, value_name = "POS:#RRGGBB,...")]
+    gradient: Option<String>,
+    /// Dither palette: a built-in name (e.g. "pico-8", "gameboy", "c64"), a palette file (.gpl, .txt, .pal, .hex, .ase, .aco) or colors like "#000000,#ff0000,#ffffff", up to 256. Enables palette dithering
+    #[arg(long, value_name = "NAME|FILE|#RRGGBB,...")]
+    palette: Option<String>,
+    /// Descend into subdirectories of directory inputs
+    #[arg(short, long)]
+    recursive: bool,
+    /// Pipeline implementation: auto (GPU when available), gpu or cpu
+    #[arg(short, long, default_value = "auto")]
+    backend: Backend,
+    /// Appended to each output file stem
+    #[arg(long, default_value = "_dithered")]
+    suffix: String,
+}
+
+fn main() {
+    env_logger::init();
+    if let Err(e) = run(Args::parse()) {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<()> {
+    let mut look = match &args.preset { Some(path) => preset::load(path)?, None => Look::default() };
+    if let Some(mode) = &args.mode {
+        let (idx, kernel) = parse_mode(mode)?;
+        look.settings.dither_type = idx as f32; look.settings.dither_enabled = if idx > 0 { 1.0 } else { 0.0 };
+        if let Some(k) = kernel { look.settings.diffusion_kernel = k as f32; }
+    }
+    for kv in &args.sets { apply_setting(&mut look.settings, kv)?; }
+    for spec in &args.curves { let (idx, pts) = parse_curve(spec)?; look.curves[idx] = pts; }
+    if let Some(path) = &args.matrix {
+        look.custom_matrix = Some(Arc::new(matrix::load(path)?));
+        look.settings.dither_type = 3.0; look.settings.dither_enabled = 1.0; look.settings.ordered_matrix = matrix::CUSTOM as f32;
+    }
+    if let Some(spec) = &args.gradient { look.gradient = parse_gradient(spec)?; look.settings.grad_enabled = 1.0; }
+    if let Some(spec) = &args.palette {
+        if palette::builtin::find(spec).is_some() { look.set_builtin_palette(spec)?; } else { look.palette = parse_palette(spec)?; look.palette_name = None; }
+        look.settings.palette_enabled = 1.0;
+    }
+    look.gradient.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+    for pts in look.curves.iter_mut() { pts.sort_by(|a, b| a.x.total_cmp(&b.x)); }
+    if let Some(path) = &args.save_preset { preset::save(path, &look)?; }
+    if args.depth == 16 && !look.settings.allows_deep_output() { bail!("--depth 16 needs dithering off"); }
+    if args.depth == 16 && args.format.is_some_and(|f| !f.supports_16bit()) { bail!("--depth 16 needs png or tif output"); }
+
+    let files = collect_inputs(&args.inputs, args.recursive)?;
+    if files.is_empty() { bail!("no input images matched"); }
+    if let Some(dir) = &args.output { std::fs::create_dir_all(dir).with_context(|| format!("creating output directory {:?}", dir))?; }
+
+    let renderer = Renderer::with_backend(args.backend)?;
+    log::info!("Rendering on the {:?} backend", renderer.backend());
+
+    let mut failed = 0;
+    for input in &files {
+        let out_format = args.format.or_else(|| input.extension().and_then(|e| e.to_str()).and_then(ExportFormat::from_extension)).unwrap_or(ExportFormat::Png);
+        let out_path = output_path(input, args.output.as_deref(), &args.suffix, out_format);
+        let result = (|| -> Result<()> {
+            if out_path == *input { bail!("output would overwrite the input; pass --output or a non-empty --suffix"); }
+            let img = image_io::load_from_path(input)?;
+            let out = if args.depth == 16 && out_format.supports_16bit() {
+                image::DynamicImage::ImageRgba16(image_io::to_srgb16(&renderer.render_linear(&img, &look)?))
+            } else {
+                if args.depth == 16 { log::warn!("{}: {} output is written with 8 bits per channel", input.display(), out_format.extension().1); }
+                image::DynamicImage::ImageRgba8(renderer.render(&img, &look)?)
+            };
+            let out = if args.matte.is_some() || !out_format.supports_alpha() { image_io::flatten(&out, args.matte.unwrap_or([255; 3]), look.settings.alpha_premultiply > 0.5) } else { out };
+            image_io::save_image(&out_path, &out, out_format, args.quality)
+        })();
+        match result {
+            Ok(()) => println!("{} -> {}", input.display(), out_path.display()),
+            Err(e) => { eprintln!("{}: {:#}", input.display(), e); failed += 1; }
+        }
+    }
+    if failed > 0 { bail!("{} of {} images failed", failed, files.len()); }
+    Ok(())
+}
+
+fn parse_format(s: &str) -> Result<ExportFormat, String> {
+    ExportFormat::from_extension(s).ok_or_else(|| format!("unknown format '{}' (expected png, jpg, webp, svg or tif)", s))
+}
+
+fn parse_depth(s: &str) -> Result<u8, String> {
+    match s { "8" => Ok(8), "16" => Ok(16), _ => Err(format!("unsupported bit depth '{}' (expected 8 or 16)", s)) }
+}
+
+fn parse_matte(s: &str) -> Result<[u8; 3], String> {
+    parse_hex_color(s).map_err(|e| e.to_string())
+}
+
+fn normalize_name(s: &str) -> String {
+    s.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_ascii_lowercase()
+}
+
+/// Returns the dither type and, for kernel names, the error-diffusion kernel to use.
+fn parse_mode(s: &str) -> Result<(usize, Option<usize>)> {
+    if let Ok(idx) = s.parse::<usize>() {
+        if idx < pipeline::DITHER_NAMES.len() { return Ok((idx, None)); }
+        bail!("dither mode index {} out of range (0-{})", idx, pipeline::DITHER_NAMES.len() - 1);
+    }
+    let wanted = normalize_name(s);
+    if let Some(idx) = pipeline::DITHER_NAMES.iter().position(|n| normalize_name(n) == wanted) { return Ok((idx, None)); }
+    if let Some(k) = diffusion::KERNELS.iter().position(|k| normalize_name(k.name) == wanted) { return Ok((pipeline::DITHER_ERROR_DIFFUSION, Some(k))); }
+    let kernels = diffusion::KERNELS.iter().map(|k| k.name).collect::<Vec<_>>().join(", ");
+    bail!("unknown dither mode '{}' (expected one of: {}, or a kernel: {})", s, pipeline::DITHER_NAMES.join(", "), kernels)
+}
+
+/// Applies `field=value` to `settings` through its serde representation so every public field is reachable by name.
+fn apply_setting(settings: &mut ColorSettings, kv: &str) -> Result<()> {
+    let (key, value) = kv.split_once('=').with_context(|| format!("expected FIELD=VALUE, got '{}'", kv))?;
+    let value = parse_finite(value, kv)?;
+    let mut json = serde_json::to_value(*settings)?;
+    let obj = json.as_object_mut().unwrap();
+    let key = key.trim();
+    if !obj.contains_key(key) { bail!("unknown setting '{}'", key); }
+    obj.insert(key.to_string(), serde_json::json!(value));
+    *settings = serde_json::from_value(json)?;
+    Ok(())
+}
+
+/// A finite number out of `value`, the part of `spec` being parsed. `f32::from_str` takes "nan"
+/// and "inf", which clamping does not fix.
+fn parse_finite(value: &str, spec: &str) -> Result<f32> {
+    let v: f32 = value.trim().parse().with_context(|| format!("invalid number in '{}'", spec))?;
+    if !v.is_finite() { bail!("'{}' is not a finite number in '{}'", value.trim(), spec); }
+    Ok(v)
+}
+
+fn parse_curve(spec: &str) -> Result<(usize, Vec<CurvePoint>)> {
+    let (channel, points) = spec.split_once(':').with_context(|| format!("expected CHANNEL:X,Y;..., got '{}'", spec))?;
+    let idx = match channel.trim().to_ascii_lowercase().as_str() {
+        "a" | "all" | "master" => 0, "r" | "red" => 1, "g" | "green" => 2, "b" | "blue" => 3,
+        other => bail!("unknown curve channel '{}' (expected master, red, green or blue)", other),
+    };
+    let pts = points.split(';').filter(|p| !p.trim().is_empty()).map(|p| {
+        let (x, y) = p.split_once(',').with_context(|| format!("expected X,Y, got '{}'", p))?;
+        Ok(CurvePoint::new(parse_finite(x, p)?.clamp(0.0, 1.0), parse_finite(y, p)?.clamp(0.0, 1.0)))
+    }).collect::<Result<Vec<_>>>().with_context(|| format!("invalid curve '{}'", spec))?;
+    if pts.is_empty() { bail!("curve '{}' has no points", spec); }
+    Ok((idx, pts))
+}
+
+fn parse_gradient(spec: &str) -> Result<Vec<GradientStop>> {
+    let stops = spec.split(',').filter(|s| !s.trim().is_empty()).enumerate().map(|(i, s)| {
+        let (pos, color) = s.split_once(':').with_context(|| format!("expected POS:#RRGGBB, got '{}'", s))?;
+        Ok(GradientStop::new(i as u64, parse_finite(pos, s)?.clamp(0.0, 1.0), parse_hex_color(color)?))
+    }).collect::<Result<Vec<_>>>()?;
+    if stops.is_empty() { bail!("gradient '{}' has no stops", spec); }
+    Ok(stops)
+}
+
+fn parse_palette(spec: &str) -> Result<Vec<[u8; 3]>> {
+    if Path::new(spec).is_file() { return palette::import::load(Path::new(spec)); }
+    let builtins = palette::builtin::BUILTINS.iter().map(|b| b.id).collect::<Vec<_>>().join(", ");
+    palette::import::parse_hex_lines(spec).with_context(|| format!("invalid palette '{}' (expected a file, hex colors or a built-in: {})", spec, builtins))
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).map(|e| image_io::SUPPORTED_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str())).unwrap_or(false)
+}
+
+fn collect_dir(dir: &Path, recursive: bool, out: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir).with_context(|| format!("reading directory {:?}", dir))?.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() { if recursive { collect_dir(&path, recursive, out)?; } } else if is_image(&path) { out.push(path); }
+    }
+    Ok(())
+}
+
+/// Expands each input as a file, a directory of images, or a glob pattern.
+fn collect_inputs(inputs: &[String], recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        if path.is_dir() { collect_dir(path, recursive, &mut files)?; continue; }
+        if path.is_file() { files.push(path.to_path_buf()); continue; }
+        let matches = glob::glob(input).with_context(|| format!("invalid glob pattern '{}'", input))?.filter_map(|p| p.ok()).collect::<Vec<_>>();
+        if matches.is_empty() { log::warn!("'{}' matched no files", input); }
+        for m in matches {
+            if m.is_dir() { collect_dir(&m, recursive, &mut files)?; } else if is_image(&m) { files.push(m); }
+        }
+    }
+    files.dedup();
+    Ok(files)
+}
+
+fn output_path(input: &Path, out_dir: Option<&Path>, suffix: &str, format: ExportFormat) -> PathBuf {
+    let stem = input.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "image".to_string());
+    let dir = out_dir.map(Path::to_path_buf).unwrap_or_else(|| input.parent().map(Path::to_path_buf).unwrap_or_default());
+    dir.join(format!("{}{}.{}", stem, suffix, format.extension().0))
+}
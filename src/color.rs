@@ -0,0 +1,28 @@
+use crate::pipeline::cpu::{linear_to_oklab, linear_to_srgb, srgb_to_linear};
+use anyhow::{bail, Context, Result};
+
+/// Parses `#RRGGBB` (the leading `#` is optional).
+pub fn parse_hex_color(s: &str) -> Result<[u8; 3]> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 { bail!("invalid color '{}' (expected #RRGGBB)", s); }
+    let v = u32::from_str_radix(hex, 16).with_context(|| format!("invalid color '{}'", s))?;
+    Ok([(v >> 16) as u8, (v >> 8) as u8, v as u8])
+}
+
+pub fn format_hex_color(c: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2])
+}
+
+/// 8-bit sRGB to OKLab (L in 0..1), where Euclidean distance follows perceived difference.
+pub fn srgb_to_oklab(c: [u8; 3]) -> [f32; 3] {
+    linear_to_oklab(c.map(|v| srgb_to_linear(v as f32 / 255.0)))
+}
+
+/// Inverse of [`srgb_to_oklab`], clamped into the sRGB gamut.
+pub fn oklab_to_srgb(c: [f32; 3]) -> [u8; 3] {
+    let l = (c[0] + 0.39633778 * c[1] + 0.21580376 * c[2]).powi(3);
+    let m = (c[0] - 0.105561346 * c[1] - 0.06385417 * c[2]).powi(3);
+    let s = (c[0] - 0.08948418 * c[1] - 1.2914855 * c[2]).powi(3);
+    let rgb = [4.0767417 * l - 3.3077116 * m + 0.23096993 * s, -1.268438 * l + 2.6097574 * m - 0.3413194 * s, -0.0041960863 * l - 0.7034186 * m + 1.7076147 * s];
+    rgb.map(|v| (linear_to_srgb(v.clamp(0.0, 1.0)) * 255.0).round() as u8)
+}
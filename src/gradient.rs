@@ -0,0 +1,44 @@
+use crate::pipeline::{Lut, LUT_SIZE};
+
+/// A color stop of the gradient remap ramp. `id` gives editors a stable handle while stops get re-sorted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStop { pub id: u64, pub pos: f32, pub color: [u8; 3] }
+
+impl GradientStop {
+    pub const fn new(id: u64, pos: f32, color: [u8; 3]) -> Self { Self { id, pos, color } }
+}
+
+/// Default black-to-white ramp used on startup and after a reset.
+pub fn default_stops() -> Vec<GradientStop> {
+    vec![GradientStop::new(0, 0.0, [0, 0, 0]), GradientStop::new(1, 1.0, [255, 255, 255])]
+}
+
+/// Stops for `colors` spread evenly from 0 to 1, with ids from 0; a single color fills the ramp.
+pub fn stops_from_colors(colors: &[[u8; 3]]) -> Vec<GradientStop> {
+    let last = colors.len().saturating_sub(1).max(1) as f32;
+    colors.iter().enumerate().map(|(i, &c)| GradientStop::new(i as u64, i as f32 / last, c)).collect()
+}
+
+/// Bakes the stops into the [`LUT_SIZE`]x1 float LUT uploaded as `gradient_texture`.
+pub fn generate_gradient_data(stops: &[GradientStop], data: &mut [[f32; 4]]) {
+    if stops.is_empty() { return; }
+    let last = (data.len().max(2) - 1) as f32;
+    for (i, texel) in data.iter_mut().enumerate() {
+        let t = i as f32 / last;
+        let mut lower = &stops[0]; let mut upper = &stops[stops.len() - 1];
+        for stop in stops {
+            if stop.pos <= t && stop.pos >= lower.pos { lower = stop; }
+            if stop.pos >= t && stop.pos <= upper.pos { upper = stop; }
+        }
+        let f = if (upper.pos - lower.pos).abs() < 0.0001 { 0.0 } else { (t - lower.pos) / (upper.pos - lower.pos) };
+        let color: [f32; 3] = std::array::from_fn(|c| (lower.color[c] as f32 * (1.0 - f) + upper.color[c] as f32 * f) / 255.0);
+        *texel = [color[0], color[1], color[2], 1.0];
+    }
+}
+
+/// The gradient LUT for `stops`, [`LUT_SIZE`] texels long.
+pub fn gradient_lut(stops: &[GradientStop]) -> Lut {
+    let mut data = vec![[0.0, 0.0, 0.0, 1.0]; LUT_SIZE];
+    generate_gradient_data(stops, &mut data);
+    data
+}
@@ -0,0 +1,55 @@
+//! Undo/redo over [`Look`]s. Each step keeps the whole look it replaced rather than a command
+//! that reverses one edit: the app edits looks in place from many widgets and shortcuts, and a
+//! snapshot covers all of them alike without each needing an inverse.
+//!
+//! The cost is one look per step: the 176-byte settings, the curve points and gradient stops (a
+//! few hundred bytes) and the palette (up to 768 bytes), so about 1.5 KiB at most and 300 KiB for
+//! a full [`MAX_STEPS`] history. An imported custom matrix is behind an `Arc` and shared by every
+//! step that has it, not copied.
+
+use crate::look::Look;
+
+/// Steps kept before the oldest are dropped.
+pub const MAX_STEPS: usize = 200;
+
+pub struct History {
+    undo: Vec<Look>,
+    redo: Vec<Look>,
+    /// The look as of the last recorded step, what [`History::undo`] steps back from.
+    current: Look,
+}
+
+impl History {
+    pub fn new(look: &Look) -> Self {
+        Self { undo: Vec::new(), redo: Vec::new(), current: look.clone() }
+    }
+
+    /// Records `look` as one step if it differs from the last recorded look, and drops the redo
+    /// steps. Call it once an edit settles rather than every frame of a drag, so the whole drag
+    /// undoes at once. Returns whether a step was added.
+    pub fn record(&mut self, look: &Look) -> bool {
+        if *look == self.current { return false; }
+        self.undo.push(std::mem::replace(&mut self.current, look.clone()));
+        if self.undo.len() > MAX_STEPS { self.undo.remove(0); }
+        self.redo.clear();
+        true
+    }
+
+    /// The look before the last step, if any.
+    pub fn undo(&mut self) -> Option<Look> {
+        let prev = self.undo.pop()?;
+        self.redo.push(std::mem::replace(&mut self.current, prev.clone()));
+        Some(prev)
+    }
+
+    /// The look the last undo stepped back from, if nothing was recorded since.
+    pub fn redo(&mut self) -> Option<Look> {
+        let next = self.redo.pop()?;
+        self.undo.push(std::mem::replace(&mut self.current, next.clone()));
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool { !self.undo.is_empty() }
+
+    pub fn can_redo(&self) -> bool { !self.redo.is_empty() }
+}
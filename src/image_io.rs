@@ -1,19 +1,162 @@
 use anyhow::Result;
-use image::DynamicImage;
+use crate::pipeline::cpu::{linear_to_srgb, srgb_to_linear};
+use image::{DynamicImage, GenericImageView, ImageEncoder, Rgba32FImage, RgbaImage};
+use std::collections::BTreeMap;
 use std::path::Path;
 
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ExportFormat { Png, Jpg, Webp, Svg, Tiff }
+
+impl ExportFormat {
+    /// File extension and file-dialog filter name.
+    pub fn extension(self) -> (&'static str, &'static str) {
+        match self { ExportFormat::Png => ("png", "PNG"), ExportFormat::Jpg => ("jpg", "JPEG"), ExportFormat::Webp => ("webp", "WEBP"), ExportFormat::Svg => ("svg", "SVG"), ExportFormat::Tiff => ("tif", "TIFF") }
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() { "png" => Some(ExportFormat::Png), "jpg" | "jpeg" => Some(ExportFormat::Jpg), "webp" => Some(ExportFormat::Webp), "svg" => Some(ExportFormat::Svg), "tif" | "tiff" => Some(ExportFormat::Tiff), _ => None }
+    }
+
+    /// True for the formats that can store 16 bits per channel.
+    pub fn supports_16bit(self) -> bool {
+        matches!(self, ExportFormat::Png | ExportFormat::Tiff)
+    }
+
+    /// True for the formats that keep an alpha channel; the others are flattened onto a matte.
+    pub fn supports_alpha(self) -> bool {
+        matches!(self, ExportFormat::Png | ExportFormat::Webp | ExportFormat::Tiff)
+    }
+}
+
+/// Extensions offered by the Load dialog and picked up from directories by the CLI.
+pub const SUPPORTED_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "webp", "bmp", "tiff", "tif", "gif"];
+
 pub fn load_from_path(path: &Path) -> Result<DynamicImage> {
     let img = image::open(path)?;
     Ok(img)
 }
 
+/// Encodes `img` to `path`. `compression` is the 0..1 slider from the export window:
+/// PNG compression level, or JPG quality. SVG is written at the image's own size. 16-bit images
+/// stay 16-bit in PNG and TIFF and are reduced to 8 bits for the other formats. JPG and SVG drop
+/// alpha as is; [`flatten`] first to composite onto a matte.
+pub fn save_image(path: &Path, img: &DynamicImage, format: ExportFormat, compression: f32) -> Result<()> {
+    match format {
+        ExportFormat::Png => {
+            let mut f = std::fs::File::create(path)?;
+            let level = if compression > 0.8 { image::codecs::png::CompressionType::Best } else if compression > 0.3 { image::codecs::png::CompressionType::Default } else { image::codecs::png::CompressionType::Fast };
+            let encoder = image::codecs::png::PngEncoder::new_with_quality(&mut f, level, image::codecs::png::FilterType::Adaptive);
+            let (w, h) = img.dimensions();
+            encoder.write_image(img.as_bytes(), w, h, img.color())?;
+        },
+        ExportFormat::Jpg => {
+            let mut f = std::fs::File::create(path)?;
+            let quality = (compression * 100.0).clamp(1.0, 100.0) as u8;
+            let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut f, quality);
+            encoder.encode_image(&DynamicImage::ImageRgb8(img.to_rgb8()))?;
+        },
+        ExportFormat::Webp if img.color().bytes_per_pixel() > img.color().channel_count() => DynamicImage::ImageRgba8(img.to_rgba8()).save_with_format(path, image::ImageFormat::WebP)?,
+        ExportFormat::Webp => img.save(path)?,
+        ExportFormat::Tiff => img.save_with_format(path, image::ImageFormat::Tiff)?,
+        ExportFormat::Svg => { let (w, h) = img.dimensions(); std::fs::write(path, encode_svg(&img.to_rgba8(), w, h))?; },
+    }
+    Ok(())
+}
+
+/// Scales a render to the export size, nearest-neighbour so dither cells stay crisp. SVG is left
+/// at render size: [`encode_svg`] traces the rendered cells and scales them through its view box.
+pub fn resize_for_export(img: DynamicImage, format: ExportFormat, width: u32, height: u32) -> DynamicImage {
+    if format == ExportFormat::Svg || img.dimensions() == (width, height) { return img; }
+    img.resize_exact(width, height, image::imageops::FilterType::Nearest)
+}
+
+/// Composites `img` onto an opaque sRGB `matte` in linear light, for formats or exports without
+/// alpha. `premultiplied` says the color already carries its alpha (see
+/// `ColorSettings::alpha_premultiply`). 16-bit images stay 16-bit.
+pub fn flatten(img: &DynamicImage, matte: [u8; 3], premultiplied: bool) -> DynamicImage {
+    let matte = matte.map(|m| srgb_to_linear(m as f32 / 255.0));
+    let rgba = img.to_rgba32f();
+    let blend = |p: [f32; 4], c: usize| {
+        let color = srgb_to_linear(p[c]) * if premultiplied { 1.0 } else { p[3] };
+        linear_to_srgb((color + matte[c] * (1.0 - p[3])).clamp(0.0, 1.0))
+    };
+    if img.color().bytes_per_pixel() > img.color().channel_count() {
+        DynamicImage::ImageRgb16(image::ImageBuffer::from_fn(img.width(), img.height(), |x, y| { let p = rgba.get_pixel(x, y).0; image::Rgb([0, 1, 2].map(|c| (blend(p, c) * 65535.0 + 0.5) as u16)) }))
+    } else {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(img.width(), img.height(), |x, y| { let p = rgba.get_pixel(x, y).0; image::Rgb([0, 1, 2].map(|c| (blend(p, c) * 255.0 + 0.5) as u8)) }))
+    }
+}
+
+/// Encodes the linear output of [`crate::Renderer::render_linear`] as 16-bit sRGB.
+pub fn to_srgb16(img: &Rgba32FImage) -> image::ImageBuffer<image::Rgba<u16>, Vec<u16>> {
+    let encode = |c: f32| (linear_to_srgb(c.clamp(0.0, 1.0)) * 65535.0 + 0.5) as u16;
+    image::ImageBuffer::from_fn(img.width(), img.height(), |x, y| { let p = img.get_pixel(x, y).0; image::Rgba([encode(p[0]), encode(p[1]), encode(p[2]), (p[3].clamp(0.0, 1.0) * 65535.0 + 0.5) as u16]) })
+}
+
+/// Traces a flat-colored image as rectangles: each row is split into runs of one color, and a run
+/// repeated exactly on the rows below grows into a taller rectangle, so every pixel-scaled dither
+/// cell ends up in at most one rectangle. The rectangles are grouped into one path per color, and
+/// the most common color is drawn once as the background instead. Alpha is ignored.
+///
+/// The view box stays in source pixels while `width` x `height` sets the drawn size, so the cells
+/// scale exactly to any export size.
+pub fn encode_svg(img: &RgbaImage, width: u32, height: u32) -> String {
+    use std::fmt::Write;
+    // Rectangles `(x, y, w, h)` per color, in the order they close.
+    let mut rects: BTreeMap<[u8; 3], Vec<[u32; 4]>> = BTreeMap::new();
+    let close = |rects: &mut BTreeMap<[u8; 3], Vec<[u32; 4]>>, (x0, x1, c, y0): (u32, u32, [u8; 3], u32), y: u32| rects.entry(c).or_default().push([x0, y0, x1 - x0, y - y0]);
+    // Runs still open from the row above as `(x0, x1, color, first row)`, ordered by `x0`.
+    let mut open: Vec<(u32, u32, [u8; 3], u32)> = Vec::new();
+    for y in 0..img.height() {
+        let mut next = Vec::with_capacity(open.len());
+        let mut above = open.drain(..).peekable();
+        let mut x0 = 0;
+        while x0 < img.width() {
+            let c = { let p = img.get_pixel(x0, y).0; [p[0], p[1], p[2]] };
+            let mut x1 = x0 + 1;
+            while x1 < img.width() && img.get_pixel(x1, y).0[..3] == c { x1 += 1; }
+            // Runs above that start left of this one can no longer continue.
+            while let Some(r) = above.next_if(|r| r.0 < x0) { close(&mut rects, r, y); }
+            match above.next_if(|r| r.0 == x0) {
+                Some(r) if r.1 == x1 && r.2 == c => next.push(r),
+                Some(r) => { close(&mut rects, r, y); next.push((x0, x1, c, y)); },
+                None => next.push((x0, x1, c, y)),
+            }
+            x0 = x1;
+        }
+        for r in above { close(&mut rects, r, y); }
+        open = next;
+    }
+    for r in open { close(&mut rects, r, img.height()); }
+
+    let area = |rs: &[[u32; 4]]| rs.iter().map(|r| r[2] as u64 * r[3] as u64).sum::<u64>();
+    let background = rects.iter().max_by_key(|(_, rs)| area(rs)).map(|(c, _)| *c);
+    let hex = |c: [u8; 3]| format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2]);
+    let mut out = String::new();
+    let _ = writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" shape-rendering="crispEdges">"#, width, height, img.width(), img.height());
+    if let Some(c) = background { let _ = writeln!(out, r#"<rect width="{}" height="{}" fill="{}"/>"#, img.width(), img.height(), hex(c)); }
+    for (c, rs) in rects.iter().filter(|(c, _)| Some(**c) != background) {
+        let _ = write!(out, r#"<g fill="{}"><path d=""#, hex(*c));
+        for r in rs { let _ = write!(out, "M{} {}h{}v{}h-{}z", r[0], r[1], r[2], r[3], r[2]); }
+        out.push_str("\"/></g>\n");
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(feature = "gui")]
+pub fn get_clipboard_text() -> Option<String> {
+    arboard::Clipboard::new().ok()?.get_text().ok()
+}
+
+#[cfg(feature = "gui")]
 pub fn get_clipboard_image() -> Option<DynamicImage> {
     let mut clipboard = arboard::Clipboard::new().ok()?;
     let image_data = match clipboard.get_image() {
         Ok(img) => img,
         Err(_) => return None, // Silent fail if no image
     };
-    
+
     // Convert arboard image to image crate DynamicImage
     let img = image::RgbaImage::from_raw(
         image_data.width as u32,
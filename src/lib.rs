@@ -0,0 +1,29 @@
+//! VibeDither's processing core: adjustments, tone curves, dithering and gradient remap,
+//! usable without the egui front-end.
+//!
+//! ```no_run
+//! let img = image::open("in.png").unwrap();
+//! let mut look = vibedither::Look::default();
+//! look.settings.dither_enabled = 1.0;
+//! look.settings.dither_type = 3.0; // Bayer
+//! vibedither::render(&img, &look).save("out.png").unwrap();
+//! ```
+
+pub mod pipeline;
+pub mod image_io;
+pub mod spline;
+pub mod gradient;
+pub mod look;
+pub mod renderer;
+pub mod color;
+pub mod preset;
+pub mod palette;
+pub mod threshold;
+pub mod history;
+pub mod scopes;
+
+pub use gradient::GradientStop;
+pub use look::Look;
+pub use pipeline::ColorSettings;
+pub use renderer::{render, Backend, Renderer};
+pub use spline::CurvePoint;
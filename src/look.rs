@@ -0,0 +1,63 @@
+use crate::gradient::{self, GradientStop};
+use crate::palette;
+use crate::pipeline::{ColorSettings, Lut};
+use crate::spline::{self, CurvePoint};
+use crate::threshold::{self, ThresholdMap};
+use std::sync::Arc;
+
+/// Everything that defines an edit: the uniform settings, the Master/R/G/B tone curves,
+/// the gradient remap stops, the dither palette and an optional imported ordered-dither matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Look {
+    pub settings: ColorSettings,
+    pub curves: [Vec<CurvePoint>; 4],
+    pub gradient: Vec<GradientStop>,
+    /// Colors `palette_enabled` dithers to, at most [`palette::MAX_COLORS`].
+    pub palette: Vec<[u8; 3]>,
+    /// Id of the [`palette::builtin`] palette `palette` was taken from, `None` once it is edited.
+    pub palette_name: Option<String>,
+    pub custom_matrix: Option<Arc<ThresholdMap>>,
+}
+
+impl Default for Look {
+    fn default() -> Self {
+        Self { settings: ColorSettings::default(), curves: spline::default_curves(), gradient: gradient::default_stops(), palette: palette::default_colors(), palette_name: None, custom_matrix: None }
+    }
+}
+
+impl Look {
+    /// Float curves LUT as uploaded to `curves_texture`.
+    pub fn curves_lut(&self) -> Lut {
+        spline::build_curves_lut(&self.curves)
+    }
+
+    /// Float gradient LUT as uploaded to `gradient_texture`.
+    pub fn gradient_lut(&self) -> Lut {
+        gradient::gradient_lut(&self.gradient)
+    }
+
+    /// 256x1 RGBA palette LUT as uploaded to `palette_texture`.
+    pub fn palette_lut(&self) -> [u8; 1024] {
+        let mut data = [0u8; 1024];
+        palette::generate_palette_data(&self.palette, &mut data);
+        data
+    }
+
+    /// Switches to the built-in palette with `id`, keeping the reference so presets store the name.
+    pub fn set_builtin_palette(&mut self, id: &str) -> anyhow::Result<()> {
+        let builtin = palette::builtin::find(id).ok_or_else(|| anyhow::anyhow!("unknown built-in palette '{}'", id))?;
+        self.palette = builtin.colors.to_vec();
+        self.palette_name = Some(builtin.id.to_string());
+        Ok(())
+    }
+
+    /// Threshold map as uploaded to `threshold_texture`.
+    pub fn threshold_map(&self) -> Arc<ThresholdMap> {
+        threshold::for_settings(&self.settings, self.custom_matrix.as_ref())
+    }
+
+    /// Whether [`Look::threshold_map`] returns without waiting, see [`threshold::is_ready`].
+    pub fn threshold_map_ready(&self) -> bool {
+        threshold::is_ready(&self.settings)
+    }
+}
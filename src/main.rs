@@ -1,10 +1,14 @@
-mod pipeline;
-mod image_io;
-mod spline;
-
 use eframe::{egui, egui_wgpu};
-use pipeline::{Pipeline, ColorSettings};
-use image::{DynamicImage, GenericImageView, ImageEncoder};
+use vibedither::{image_io, palette, preset, spline, Look};
+use vibedither::history::History;
+use vibedither::scopes::{Histogram, Waveform};
+use vibedither::image_io::ExportFormat;
+use vibedither::gradient::{self, GradientStop};
+use vibedither::palette::extract;
+use vibedither::pipeline::{self, diffusion, stipple, Pipeline, ColorSettings, Lut};
+use vibedither::spline::CurvePoint;
+use vibedither::threshold::{blue_noise, halftone, matrix};
+use image::DynamicImage;
 use std::sync::Arc;
 
 fn main() -> eframe::Result<()> {
@@ -20,36 +24,72 @@ fn main() -> eframe::Result<()> {
 #[derive(PartialEq)]
 enum Tab { Adjust, Dither }
 
-#[derive(Clone, Copy)]
-struct GradientStop { id: u64, pos: f32, color: egui::Color32 }
+/// Longest side of snapshot thumbnails, in pixels.
+const THUMB_SIZE: u32 = 160;
+/// Snapshots get the keyboard slots 1-9.
+const MAX_SNAPSHOTS: usize = 9;
+
+/// A look variant kept for the session, with its thumbnail rendered from the reduced source.
+struct Snapshot { name: String, look: Look, thumb: Option<(wgpu::Texture, egui::TextureId)> }
+
+/// How long edits have to pause before a serial CPU pass starts on the full image.
+const CPU_PASS_DELAY: std::time::Duration = std::time::Duration::from_millis(250);
 
+/// A viewport render that needs a serial CPU pass (error diffusion, stippling), which can take
+/// seconds at full size. It waits for edits to settle and then runs on a worker thread.
+struct CpuPass { look: Look, luts: (Lut, Lut, [u8; 1024]), requested: std::time::Instant }
+
+/// Longest side of the reduced source the scopes are measured on.
+const SCOPE_SIZE: u32 = 256;
+/// Column bands of the waveform scope.
+const WAVEFORM_COLUMNS: usize = 128;
+
+/// How the viewport shows the original next to the output.
 #[derive(PartialEq, Clone, Copy)]
-enum ExportFormat { Png, Jpg, Webp }
+enum Compare { Off, Split, SideBySide }
+
+/// Where [`VibeDitherApp::render_other_look`] draws: the viewport, the unedited original the
+/// compare views show, or a snapshot's thumbnail.
+enum RenderTarget { Viewport, Original, Thumb(usize) }
 
 struct ExportSettings {
-    format: ExportFormat, compression: f32, transparency: bool,
+    format: ExportFormat, compression: f32, transparency: bool, deep: bool, matte: [u8; 3],
     use_percentage: bool, percentage: f32, width_px: u32, height_px: u32, link_aspect: bool,
+    cmyk_plates: bool,
 }
 
 impl Default for ExportSettings {
     fn default() -> Self {
-        Self { format: ExportFormat::Png, compression: 0.8, transparency: true, use_percentage: true, percentage: 1.0, width_px: 1920, height_px: 1080, link_aspect: true }
+        Self { format: ExportFormat::Png, compression: 0.8, transparency: true, deep: false, matte: [255; 3], use_percentage: true, percentage: 1.0, width_px: 1920, height_px: 1080, link_aspect: true, cmyk_plates: false }
     }
 }
 
 #[derive(PartialEq, Clone, Copy, Debug)]
-enum KeyboardFocus { Main, Adjust, Light, Color, Dither, Editing(&'static str), ModeSelection, PosterizeMenu, BayerSizeMenu, GradientMapMenu, GradientPointEdit, Export }
+enum KeyboardFocus { Main, Adjust, Light, Color, Dither, Editing(&'static str), ModeSelection, PosterizeMenu, BayerSizeMenu, NoiseSizeMenu, KernelMenu, GradientMapMenu, GradientPointEdit, PaletteMenu, PaletteLibraryMenu, Export, PresetMenu, Snapshots }
 
 struct VibeDitherApp {
     pipeline: Pipeline, current_image: Option<DynamicImage>,
     device: Option<Arc<wgpu::Device>>, queue: Option<Arc<wgpu::Queue>>, renderer: Option<Arc<egui::mutex::RwLock<egui_wgpu::Renderer>>>,
     target_format: wgpu::TextureFormat, input_texture: Option<wgpu::Texture>, output_texture: Option<wgpu::Texture>,
-    egui_texture_id: Option<egui::TextureId>, settings: ColorSettings,
-    curves_data: [u8; 1024], gradient_data: [u8; 1024], gradient_stops: Vec<GradientStop>,
-    selected_stop_id: Option<u64>, next_stop_id: u64, curve_points: [Vec<egui::Pos2>; 4],
+    egui_texture_id: Option<egui::TextureId>, original_texture: Option<(wgpu::Texture, egui::TextureId)>, look: Look,
+    curves_data: Lut, gradient_data: Lut, palette_data: [u8; 1024],
+    selected_stop_id: Option<u64>, next_stop_id: u64,
     selected_curve_idx: usize, dragging_point_idx: Option<usize>, active_tab: Tab, zoom_factor: f32, fit_to_screen: bool, pan_offset: egui::Vec2,
     focus: KeyboardFocus, last_edit_time: f64, show_export_window: bool, export_settings: ExportSettings,
     export_row: usize, export_col: usize,
+    palette_locked: Vec<bool>, extract_method: extract::Method, extract_count: usize, extract_adjusted: bool,
+    history: History,
+    compare: Compare, split_vertical: bool, split_pos: f32, dragging_split: bool,
+    snapshots: Vec<Snapshot>, previewing: Option<usize>, show_snapshots: bool, thumb_source: Option<(DynamicImage, wgpu::Texture)>,
+    /// Scopes of the adjusted image (before the dither stage) and of the output, measured on `scope_source`.
+    scopes: Option<[(Histogram, Waveform); 2]>, scope_source: Option<DynamicImage>, scopes_dirty: bool, last_scope_time: f64, scope_output: bool, show_waveform: bool,
+    /// Pixel inspector: the decoded source it probes, built while it is on, and the last CPU-pass
+    /// output, since serial passes cannot be probed one pixel at a time.
+    inspector: bool, probe_source: Option<pipeline::cpu::SourceImage>, cpu_output: Option<image::RgbaImage>,
+    /// A render is waiting on a blue-noise map generating in the background.
+    noise_pending: bool,
+    /// The newest CPU pass waiting to start, and the one running, which reports on the channel.
+    cpu_pending: Option<CpuPass>, cpu_worker: Option<std::sync::mpsc::Receiver<image::RgbaImage>>,
 }
 
 impl VibeDitherApp {
@@ -61,92 +101,237 @@ impl VibeDitherApp {
             device = Some(wgpu_render_state.device.clone()); queue = Some(wgpu_render_state.queue.clone()); renderer = Some(wgpu_render_state.renderer.clone());
             pipeline.init(&wgpu_render_state.device, target_format);
         }
-        let mut curves_data = [0u8; 1024];
-        let curve_points = [
-            vec![egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)],
-            vec![egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)],
-            vec![egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)],
-            vec![egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)],
-        ];
-        let lut = spline::interpolate_spline(&curve_points[0]);
-        for i in 0..256 { curves_data[i * 4] = lut[i]; curves_data[i * 4 + 1] = lut[i]; curves_data[i * 4 + 2] = lut[i]; curves_data[i * 4 + 3] = 255; }
-        let gradient_stops = vec![GradientStop { id: 0, pos: 0.0, color: egui::Color32::BLACK }, GradientStop { id: 1, pos: 1.0, color: egui::Color32::WHITE }];
-        let mut gradient_data = [0u8; 1024]; Self::generate_gradient_data(&gradient_stops, &mut gradient_data);
+        let look = Look::default();
+        let curves_data = look.curves_lut();
+        let gradient_data = look.gradient_lut();
+        let palette_data = look.palette_lut();
+        let history = History::new(&look);
         Self {
-            pipeline, current_image: None, device, queue, renderer, target_format, input_texture: None, output_texture: None, egui_texture_id: None,
-            settings: ColorSettings::default(), curves_data, gradient_data, gradient_stops, selected_stop_id: Some(0), next_stop_id: 2, curve_points, selected_curve_idx: 0, dragging_point_idx: None,
+            pipeline, current_image: None, device, queue, renderer, target_format, input_texture: None, output_texture: None, egui_texture_id: None, original_texture: None,
+            look, curves_data, gradient_data, palette_data, selected_stop_id: Some(0), next_stop_id: 2, selected_curve_idx: 0, dragging_point_idx: None,
             active_tab: Tab::Adjust, zoom_factor: 1.0, fit_to_screen: false, pan_offset: egui::Vec2::ZERO, focus: KeyboardFocus::Main, last_edit_time: 0.0, show_export_window: false, export_settings: ExportSettings::default(),
             export_row: 0, export_col: 0,
+            palette_locked: Vec::new(), extract_method: extract::Method::KMeans, extract_count: 8, extract_adjusted: false,
+            history,
+            compare: Compare::Off, split_vertical: true, split_pos: 0.5, dragging_split: false,
+            snapshots: Vec::new(), previewing: None, show_snapshots: false, thumb_source: None,
+            scopes: None, scope_source: None, scopes_dirty: false, last_scope_time: 0.0, scope_output: false, show_waveform: false,
+            inspector: false, probe_source: None, cpu_output: None, noise_pending: false, cpu_pending: None, cpu_worker: None,
         }
     }
 
     fn load_content(&mut self, _ctx: &egui::Context, path: std::path::PathBuf) {
         log::debug!("load_content called for path: {:?}", path);
-        // Assume it's an image
+        if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv")) { self.load_matrix(&path); return; }
+        if path.extension().and_then(|e| e.to_str()).is_some_and(|e| palette::import::EXTENSIONS.contains(&e.to_ascii_lowercase().as_str())) { self.load_palette(&path); return; }
+        if path.extension().is_some_and(|e| e.eq_ignore_ascii_case(preset::PRESET_EXTENSION)) {
+            match preset::load(&path) { Ok(look) => self.set_look(look), Err(e) => log::error!("Failed to load preset: {:#}", e) }
+            return;
+        }
+        // Otherwise assume it's an image
         match image_io::load_from_path(&path) {
             Ok(img) => self.load_image_to_gpu(_ctx, img),
             Err(e) => log::error!("Failed to load image from path {:?}: {}", path, e),
         }
     }
 
-    fn read_back_image(&self, output_tex: &wgpu::Texture) -> Option<image::RgbaImage> {
-        let (Some(device), Some(queue), Some(current_img)) = (&self.device, &self.queue, &self.current_image) else { return None; };
-        let width = output_tex.width(); let height = output_tex.height();
-        let bytes_per_pixel = 4; let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT; let unpadded = width * bytes_per_pixel; let padded = unpadded + (align - unpadded % align) % align;
-        let staging = device.create_buffer(&wgpu::BufferDescriptor { label: Some("readback_staging"), size: (padded * height) as u64, usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false });
-        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("readback_enc") });
-        encoder.copy_texture_to_buffer(wgpu::ImageCopyTexture { texture: output_tex, mip_level: 0, origin: wgpu::Origin3d::ZERO, aspect: wgpu::TextureAspect::All }, wgpu::ImageCopyBuffer { buffer: &staging, layout: wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(padded), rows_per_image: Some(height) } }, wgpu::Extent3d { width, height, depth_or_array_layers: 1 });
-        queue.submit(Some(encoder.finish()));
-        let slice = staging.slice(..); let (tx, rx) = std::sync::mpsc::channel(); slice.map_async(wgpu::MapMode::Read, move |v| tx.send(v).unwrap()); device.poll(wgpu::Maintain::Wait);
-        if let Ok(Ok(())) = rx.recv() {
-            let data = slice.get_mapped_range(); let mut pixels = Vec::with_capacity((width * height * 4) as usize);
-            for row in 0..height { 
-                let start = (row * padded) as usize; 
-                pixels.extend_from_slice(&data[start..start+(width*4) as usize]);
-            }
-            drop(data); staging.unmap();
-            return image::RgbaImage::from_raw(width, height, pixels);
+    fn reset_adjustments(&mut self) {
+        self.selected_curve_idx = 0;
+        self.set_look(Look::default());
+    }
+
+    /// Replaces the whole edit state, re-uploads the LUTs and re-renders.
+    fn set_look(&mut self, look: Look) {
+        self.look = look;
+        self.curves_data = self.look.curves_lut(); self.gradient_data = self.look.gradient_lut(); self.palette_data = self.look.palette_lut();
+        self.selected_stop_id = self.look.gradient.first().map(|s| s.id); self.next_stop_id = self.look.gradient.iter().map(|s| s.id + 1).max().unwrap_or(0);
+        if let Some(q) = &self.queue { self.pipeline.update_curves(q, &self.curves_data); self.pipeline.update_gradient(q, &self.gradient_data); self.pipeline.update_palette(q, &self.palette_data); }
+        self.render_output();
+    }
+
+    /// Steps the look back (or forward again), keeping the selected gradient stop when it survives.
+    fn step_history(&mut self, redo: bool) {
+        self.history.record(&self.look);
+        let Some(look) = (if redo { self.history.redo() } else { self.history.undo() }) else { return };
+        let selected = self.selected_stop_id;
+        self.set_look(look);
+        if selected.is_some_and(|id| self.look.gradient.iter().any(|s| s.id == id)) { self.selected_stop_id = selected; }
+    }
+
+    /// Redraws the output texture. A look that needs a pass the shader cannot do is queued as a
+    /// [`CpuPass`] instead. Also ends a snapshot preview, so edits always show on the current look.
+    fn render_output(&mut self) {
+        if !self.look.threshold_map_ready() { self.noise_pending = true; return; }
+        self.previewing = None; self.scopes_dirty = true;
+        if self.look.settings.needs_cpu_pass() { self.queue_cpu_pass(self.look.clone(), (self.curves_data.clone(), self.gradient_data.clone(), self.palette_data)); return; }
+        self.cancel_cpu_pass();
+        let (Some(device), Some(queue)) = (self.device.clone(), self.queue.clone()) else { return };
+        self.pipeline.update_threshold_map(&device, &queue, &self.look.threshold_map());
+        let (Some(img), Some(input), Some(output)) = (&self.current_image, &self.input_texture, &self.output_texture) else { return };
+        self.render_look(&self.look, (&self.curves_data, &self.gradient_data, &self.palette_data), img, input, output);
+    }
+
+    /// Renders `look` from `img`, already uploaded as `input`, into the display texture `output`.
+    /// The GPU path reads the LUTs and threshold map the pipeline holds, which have to be `look`'s.
+    /// CPU passes run right here, so only small images should come through with one.
+    fn render_look(&self, look: &Look, luts: (&Lut, &Lut, &[u8; 1024]), img: &DynamicImage, input: &wgpu::Texture, output: &wgpu::Texture) {
+        let (Some(device), Some(queue)) = (&self.device, &self.queue) else { return };
+        let settings = look.settings.with_premultiplied_alpha();
+        if settings.needs_cpu_pass() {
+            self.pipeline.upload_image(queue, output, &pipeline::cpu::render(img, &settings, luts.0, luts.1, luts.2, &look.threshold_map()));
+        } else {
+            self.pipeline.render(device, queue, &input.create_view(&wgpu::TextureViewDescriptor::default()), &output.create_view(&wgpu::TextureViewDescriptor::default()), &settings);
         }
-        None
-    }
-
-    fn generate_gradient_data(stops: &[GradientStop], data: &mut [u8; 1024]) {
-        if stops.is_empty() { return; }
-        for i in 0..256 {
-            let t = i as f32 / 255.0;
-            let mut lower = &stops[0]; let mut upper = &stops[stops.len() - 1];
-            for stop in stops { if stop.pos <= t && stop.pos >= lower.pos { lower = stop; } if stop.pos >= t && stop.pos <= upper.pos { upper = stop; } }
-            let color = if (upper.pos - lower.pos).abs() < 0.0001 { lower.color } else {
-                let f = (t - lower.pos) / (upper.pos - lower.pos);
-                egui::Color32::from_rgba_unmultiplied(
-                    (lower.color.r() as f32 * (1.0 - f) + upper.color.r() as f32 * f) as u8,
-                    (lower.color.g() as f32 * (1.0 - f) + upper.color.g() as f32 * f) as u8,
-                    (lower.color.b() as f32 * (1.0 - f) + upper.color.b() as f32 * f) as u8, 255,
-                )
-            };
-            data[i * 4] = color.r(); data[i * 4 + 1] = color.g(); data[i * 4 + 2] = color.b(); data[i * 4 + 3] = 255;
+    }
+
+    /// Makes `look` the next CPU pass for the viewport, replacing any that has not started.
+    fn queue_cpu_pass(&mut self, look: Look, luts: (Lut, Lut, [u8; 1024])) {
+        self.cpu_pending = Some(CpuPass { look, luts, requested: std::time::Instant::now() });
+    }
+
+    /// Drops the queued CPU pass and the result of the running one, for when the viewport moves
+    /// on to a GPU render or another image.
+    fn cancel_cpu_pass(&mut self) {
+        self.cpu_pending = None; self.cpu_worker = None; self.cpu_output = None;
+    }
+
+    /// Runs the queued CPU pass on a worker thread, which wakes the UI when it is done.
+    fn start_cpu_pass(&mut self, ctx: &egui::Context) {
+        let (Some(pass), Some(img)) = (self.cpu_pending.take(), self.current_image.clone()) else { return };
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (settings, threshold, ctx) = (pass.look.settings.with_premultiplied_alpha(), pass.look.threshold_map(), ctx.clone());
+        std::thread::spawn(move || {
+            let (curves, gradient, palette) = &pass.luts;
+            if tx.send(pipeline::cpu::render(&img, &settings, curves, gradient, palette, &threshold)).is_ok() { ctx.request_repaint(); }
+        });
+        self.cpu_worker = Some(rx);
+    }
+
+    /// Shows a finished CPU pass, unless the image changed under it.
+    fn show_cpu_output(&mut self, out: image::RgbaImage) {
+        let (Some(queue), Some(output), Some(img)) = (&self.queue, &self.output_texture, &self.current_image) else { return };
+        if out.dimensions() != (img.width(), img.height()) { return; }
+        self.pipeline.upload_image(queue, output, &out);
+        self.cpu_output = Some(out); self.scopes_dirty = true;
+    }
+
+    /// What the current look does to source pixel `(x, y)`. CPU passes report the pixel they
+    /// rendered as the output, back to straight alpha unless the look premultiplies. Also returns
+    /// the probed look's working space, which the adjusted color is still in.
+    fn probe_pixel(&self, x: u32, y: u32) -> Option<(pipeline::cpu::PixelProbe, f32)> {
+        let source = self.probe_source.as_ref()?;
+        if x >= source.width || y >= source.height { return None; }
+        // A previewed snapshot is what the viewport shows, so that is the look to probe.
+        let previewed = self.previewing.and_then(|i| self.snapshots.get(i)).map(|s| (&s.look, (s.look.curves_lut(), s.look.gradient_lut(), s.look.palette_lut())));
+        let (look, (curves, gradient, palette)) = previewed.as_ref().map_or((&self.look, (&self.curves_data, &self.gradient_data, &self.palette_data)), |(look, luts)| (*look, (&luts.0, &luts.1, &luts.2)));
+        if !look.threshold_map_ready() { return None; }
+        let (palette, threshold) = (pipeline::cpu::decode_palette(palette), look.threshold_map());
+        let shader = pipeline::cpu::Shader { source, settings: &look.settings, curves, gradient, palette: &palette, threshold: &threshold };
+        let mut probe = shader.probe(x, y);
+        if let Some(p) = self.cpu_output.as_ref().filter(|_| look.settings.needs_cpu_pass()).and_then(|out| out.get_pixel_checked(x, y)).map(|p| p.0) {
+            let a = p[3] as f32 / 255.0;
+            let unmultiply = if look.settings.alpha_premultiply < 0.5 && a > 0.0 { 1.0 / a } else { 1.0 };
+            probe.output = [0, 1, 2, 3].map(|c| if c == 3 { a } else { (pipeline::cpu::srgb_to_linear(p[c] as f32 / 255.0) * unmultiply).min(1.0) });
         }
+        Some((probe, look.settings.dither_space))
     }
 
-    fn reset_adjustments(&mut self) {
-        self.settings = ColorSettings::default(); 
-        self.curve_points = [
-            vec![egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)],
-            vec![egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)],
-            vec![egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)],
-            vec![egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)],
-        ];
-        self.selected_curve_idx = 0;
-        let lut = spline::interpolate_spline(&self.curve_points[0]);
-        for i in 0..256 { self.curves_data[i * 4] = lut[i]; self.curves_data[i * 4 + 1] = lut[i]; self.curves_data[i * 4 + 2] = lut[i]; self.curves_data[i * 4 + 3] = 255; }
-        self.gradient_stops = vec![GradientStop { id: 0, pos: 0.0, color: egui::Color32::BLACK }, GradientStop { id: 1, pos: 1.0, color: egui::Color32::WHITE }];
-        self.selected_stop_id = Some(0); self.next_stop_id = 2; Self::generate_gradient_data(&self.gradient_stops, &mut self.gradient_data);
-        if let (Some(q), Some(device)) = (&self.queue, &self.device) {
-            self.pipeline.update_curves(q, &self.curves_data); self.pipeline.update_gradient(q, &self.gradient_data);
-            if let (Some(input), Some(output)) = (&self.input_texture, &self.output_texture) {
-                self.pipeline.render(device, q, &input.create_view(&wgpu::TextureViewDescriptor::default()), &output.create_view(&wgpu::TextureViewDescriptor::default()), &self.settings);
-            }
+    /// Eyedropper: the source color goes into the selected gradient stop, or with `to_curve` a
+    /// point goes on the selected curve where the pixel enters it, leaving the curve's shape as is.
+    fn pick(&mut self, probe: &pipeline::cpu::PixelProbe, to_curve: bool) {
+        if to_curve {
+            let x = probe.curve_input;
+            let points = &mut self.look.curves[self.selected_curve_idx];
+            if points.iter().any(|p| (p.x - x).abs() < 0.02) { return; }
+            points.push(CurvePoint::new(x, spline::curve_at(points, x))); points.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
+            self.curves_data = spline::build_curves_lut(&self.look.curves);
+            if let Some(q) = &self.queue { self.pipeline.update_curves(q, &self.curves_data); }
+        } else {
+            let Some(stop) = self.look.gradient.iter_mut().find(|s| Some(s.id) == self.selected_stop_id) else { return };
+            stop.color = [0, 1, 2].map(|c| (pipeline::cpu::linear_to_srgb(probe.source[c].clamp(0.0, 1.0)) * 255.0 + 0.5) as u8);
+            gradient::generate_gradient_data(&self.look.gradient, &mut self.gradient_data);
+            if let Some(q) = &self.queue { self.pipeline.update_gradient(q, &self.gradient_data); }
         }
+        self.render_output();
+    }
+
+    /// Renders a look other than the current one into `target`. Its LUTs go up for the render and
+    /// the current look's come back after, so the pipeline keeps holding `self.look`'s.
+    fn render_other_look(&mut self, look: &Look, target: RenderTarget) {
+        if !look.threshold_map_ready() { self.noise_pending = true; return; }
+        let viewport = matches!(target, RenderTarget::Viewport);
+        if viewport && look.settings.needs_cpu_pass() { self.queue_cpu_pass(look.clone(), (look.curves_lut(), look.gradient_lut(), look.palette_lut())); return; }
+        if viewport { self.cancel_cpu_pass(); }
+        let (Some(device), Some(queue)) = (self.device.clone(), self.queue.clone()) else { return };
+        let luts = (look.curves_lut(), look.gradient_lut(), look.palette_lut());
+        self.pipeline.update_curves(&queue, &luts.0); self.pipeline.update_gradient(&queue, &luts.1); self.pipeline.update_palette(&queue, &luts.2);
+        self.pipeline.update_threshold_map(&device, &queue, &look.threshold_map());
+        let full = self.current_image.as_ref().zip(self.input_texture.as_ref());
+        let target = match target {
+            RenderTarget::Viewport => full.zip(self.output_texture.as_ref()).map(|((img, input), output)| (img, input, output)),
+            RenderTarget::Original => full.zip(self.original_texture.as_ref()).map(|((img, input), (output, _))| (img, input, output)),
+            RenderTarget::Thumb(idx) => self.thumb_source.as_ref().zip(self.snapshots[idx].thumb.as_ref()).map(|((img, input), (output, _))| (img, input, output)),
+        };
+        if let Some((img, input, output)) = target { self.render_look(look, (&luts.0, &luts.1, &luts.2), img, input, output); }
+        self.pipeline.update_curves(&queue, &self.curves_data); self.pipeline.update_gradient(&queue, &self.gradient_data); self.pipeline.update_palette(&queue, &self.palette_data);
+        self.pipeline.update_threshold_map(&device, &queue, &self.look.threshold_map());
+    }
+
+    /// Measures the scopes on the reduced source with the CPU renderer, once before the dither stage
+    /// and once with the whole look.
+    fn update_scopes(&mut self) {
+        let Some(src) = self.scope_source.as_ref().filter(|_| self.look.threshold_map_ready()) else { return };
+        let threshold = self.look.threshold_map();
+        let render = |settings: &ColorSettings| pipeline::cpu::render(src, settings, &self.curves_data, &self.gradient_data, &self.palette_data, &threshold);
+        self.scopes = Some([render(&self.look.settings.without_dither()), render(&self.look.settings)].map(|img| (Histogram::of(&img), Waveform::of(&img, WAVEFORM_COLUMNS))));
+        self.scopes_dirty = false;
+    }
+
+    /// A texture the pipeline renders into and egui draws, registered with egui.
+    fn display_texture(&self, size: wgpu::Extent3d) -> Option<(wgpu::Texture, egui::TextureId)> {
+        let (Some(device), Some(renderer)) = (&self.device, &self.renderer) else { return None };
+        let texture = device.create_texture(&wgpu::TextureDescriptor { label: Some("output_texture"), size, mip_level_count: 1, sample_count: 1, dimension: wgpu::TextureDimension::D2, format: self.target_format, usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST, view_formats: &[] });
+        let id = renderer.write().register_native_texture(device, &texture.create_view(&wgpu::TextureViewDescriptor::default()), wgpu::FilterMode::Nearest);
+        Some((texture, id))
+    }
+
+    /// Shows snapshot `idx` in the viewport without touching the current look, or the current
+    /// look again when it is already shown.
+    fn preview_snapshot(&mut self, idx: usize) {
+        if idx >= self.snapshots.len() || self.previewing == Some(idx) { self.render_output(); return; }
+        self.render_other_look(&self.snapshots[idx].look.clone(), RenderTarget::Viewport);
+        self.previewing = Some(idx);
+    }
+
+    /// Keeps the current look as a new snapshot, named by the first free letter.
+    fn take_snapshot(&mut self) {
+        if self.snapshots.len() >= MAX_SNAPSHOTS { return; }
+        let name = ('A'..='Z').map(String::from).find(|n| self.snapshots.iter().all(|s| s.name != *n)).unwrap_or_default();
+        self.snapshots.push(Snapshot { name, look: self.look.clone(), thumb: None });
+        self.render_thumbnail(self.snapshots.len() - 1);
+    }
+
+    /// Makes snapshot `idx` the current look, as one undoable step. An edit still in progress is
+    /// recorded first so it stays a step of its own.
+    fn promote_snapshot(&mut self, idx: usize) {
+        let Some(look) = self.snapshots.get(idx).map(|s| s.look.clone()) else { return };
+        self.history.record(&self.look);
+        self.set_look(look);
+        self.history.record(&self.look);
+    }
+
+    fn delete_snapshot(&mut self, idx: usize) {
+        if idx >= self.snapshots.len() { return; }
+        if self.previewing == Some(idx) { self.render_output(); }
+        if let (Some((_, id)), Some(renderer)) = (self.snapshots.remove(idx).thumb, &self.renderer) { renderer.write().free_texture(&id); }
+        self.previewing = self.previewing.map(|p| if p > idx { p - 1 } else { p });
+    }
+
+    /// Renders the thumbnail of snapshot `idx` through the pipeline at the reduced source size.
+    fn render_thumbnail(&mut self, idx: usize) {
+        let Some((_, input)) = &self.thumb_source else { return };
+        if self.snapshots[idx].thumb.is_none() { self.snapshots[idx].thumb = self.display_texture(input.size()); }
+        self.render_other_look(&self.snapshots[idx].look.clone(), RenderTarget::Thumb(idx));
     }
 
     fn load_image_to_gpu(&mut self, _ctx: &egui::Context, img: DynamicImage) {
@@ -156,48 +341,147 @@ impl VibeDitherApp {
         let Some(queue) = self.queue.clone() else { return };
         let Some(renderer) = self.renderer.clone() else { return };
         let input_tex = self.pipeline.create_texture_from_image(&device, &queue, &img);
-        let output_tex = device.create_texture(&wgpu::TextureDescriptor { label: Some("output_texture"), size: input_tex.size(), mip_level_count: 1, sample_count: 1, dimension: wgpu::TextureDimension::D2, format: self.target_format, usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC, view_formats: &[] });
-        let tex_id = renderer.write().register_native_texture(&device, &output_tex.create_view(&wgpu::TextureViewDescriptor::default()), wgpu::FilterMode::Nearest);
-        self.pipeline.update_curves(&queue, &self.curves_data); self.pipeline.update_gradient(&queue, &self.gradient_data);
+        let size = input_tex.size();
+        let Some((output_tex, tex_id)) = self.display_texture(size) else { return };
+        self.pipeline.update_curves(&queue, &self.curves_data); self.pipeline.update_gradient(&queue, &self.gradient_data); self.pipeline.update_palette(&queue, &self.palette_data);
+        self.cancel_cpu_pass();
         self.current_image = Some(img.clone()); self.export_settings.width_px = img.width(); self.export_settings.height_px = img.height();
         self.input_texture = Some(input_tex); self.output_texture = Some(output_tex); self.egui_texture_id = Some(tex_id);
-        if let (Some(input), Some(output)) = (&self.input_texture, &self.output_texture) {
-            self.pipeline.render(&device, &queue, &input.create_view(&wgpu::TextureViewDescriptor::default()), &output.create_view(&wgpu::TextureViewDescriptor::default()), &self.settings);
+        // The original for comparing, decoded by the same pipeline as the output.
+        if let Some((_, id)) = self.original_texture.take() { renderer.write().free_texture(&id); }
+        self.original_texture = self.display_texture(size);
+        self.render_other_look(&Look::default(), RenderTarget::Original);
+        self.render_output();
+        // Snapshots stay with the session; their thumbnails follow the new image.
+        self.scope_source = Some(img.thumbnail(SCOPE_SIZE, SCOPE_SIZE)); self.scopes_dirty = true; self.probe_source = None;
+        let thumb = img.thumbnail(THUMB_SIZE, THUMB_SIZE);
+        self.thumb_source = Some((thumb.clone(), self.pipeline.create_texture_from_image(&device, &queue, &thumb)));
+        for snap in &mut self.snapshots { if let Some((_, id)) = snap.thumb.take() { renderer.write().free_texture(&id); } }
+        for i in 0..self.snapshots.len() { self.render_thumbnail(i); }
+    }
+
+    fn save_preset(&self) {
+        if let Some(path) = rfd::FileDialog::new().add_filter("VibeDither Preset", &[preset::PRESET_EXTENSION]).set_file_name(format!("look.{}", preset::PRESET_EXTENSION)).save_file() {
+            if let Err(e) = preset::save(&path, &self.look) { log::error!("Failed to save preset: {:#}", e); }
+        }
+    }
+
+    fn load_preset(&mut self) {
+        if let Some(path) = rfd::FileDialog::new().add_filter("VibeDither Preset", &[preset::PRESET_EXTENSION]).pick_file() {
+            match preset::load(&path) { Ok(look) => self.set_look(look), Err(e) => log::error!("Failed to load preset: {:#}", e) }
+        }
+    }
+
+    fn import_matrix(&mut self) {
+        if let Some(path) = rfd::FileDialog::new().add_filter("Threshold Matrix", &["csv", "txt", "png"]).pick_file() { self.load_matrix(&path); }
+    }
+
+    fn load_matrix(&mut self, path: &std::path::Path) {
+        match matrix::load(path) {
+            Ok(m) => { self.look.custom_matrix = Some(Arc::new(m)); self.look.settings.ordered_matrix = matrix::CUSTOM as f32; self.render_output(); }
+            Err(e) => log::error!("Failed to import threshold matrix: {:#}", e),
+        }
+    }
+
+    fn import_palette(&mut self) {
+        if let Some(path) = rfd::FileDialog::new().add_filter("Palette", &palette::import::EXTENSIONS).pick_file() { self.load_palette(&path); }
+    }
+
+    fn load_palette(&mut self, path: &std::path::Path) {
+        match palette::import::load(path) { Ok(colors) => self.set_palette(colors), Err(e) => log::error!("Failed to import palette: {:#}", e) }
+    }
+
+    /// Reads a palette from clipboard text, e.g. a hex list copied from Lospec or a whole .gpl file.
+    fn paste_palette(&mut self) {
+        let Some(text) = image_io::get_clipboard_text() else { return };
+        match palette::import::parse_text(&text) { Ok(colors) => self.set_palette(colors), Err(e) => log::error!("Failed to paste palette: {:#}", e) }
+    }
+
+    /// Makes `colors` the dither palette and turns palette dithering on.
+    fn set_palette(&mut self, colors: Vec<[u8; 3]>) {
+        self.look.palette = colors; self.look.palette_name = None;
+        self.apply_palette();
+    }
+
+    /// Switches to a built-in palette by id and turns palette dithering on.
+    fn set_builtin_palette(&mut self, id: &str) {
+        if let Err(e) = self.look.set_builtin_palette(id) { log::error!("{:#}", e); return; }
+        self.apply_palette();
+    }
+
+    /// Steps through the built-in palettes; from a custom palette, forward starts at the first one.
+    fn cycle_builtin_palette(&mut self, delta: isize) {
+        let n = palette::builtin::BUILTINS.len() as isize;
+        let idx = match self.look.palette_name.as_deref().and_then(|id| palette::builtin::BUILTINS.iter().position(|b| b.id == id)) { Some(i) => (i as isize + delta).rem_euclid(n), None => if delta > 0 { 0 } else { n - 1 } };
+        self.set_builtin_palette(palette::builtin::BUILTINS[idx as usize].id);
+    }
+
+    fn apply_palette(&mut self) {
+        self.look.settings.palette_enabled = 1.0; self.palette_locked.clear();
+        self.palette_data = self.look.palette_lut();
+        if let Some(q) = &self.queue { self.pipeline.update_palette(q, &self.palette_data); }
+        self.render_output();
+    }
+
+    /// Picks `extract_count` colors from the image, keeping the locked swatches. The adjusted image
+    /// is the current look with dithering, palette and gradient remap off.
+    fn extract_palette(&mut self) {
+        let Some(img) = &self.current_image else { return };
+        let thumb = img.thumbnail(512, 512);
+        let source = if self.extract_adjusted {
+            let mut settings = self.look.settings; settings.dither_enabled = 0.0; settings.palette_enabled = 0.0; settings.grad_enabled = 0.0;
+            pipeline::cpu::render(&thumb, &settings, &self.curves_data, &self.gradient_data, &self.palette_data, &self.look.threshold_map())
+        } else { thumb.to_rgba8() };
+        let locked: Vec<[u8; 3]> = self.look.palette.iter().zip(&self.palette_locked).filter(|(_, &l)| l).map(|(&c, _)| c).collect();
+        match extract::extract(&source, self.extract_count.max(locked.len()), self.extract_method, &locked) {
+            Ok(colors) => { self.set_palette(colors); self.palette_locked = self.look.palette.iter().map(|c| locked.contains(c)).collect(); }
+            Err(e) => log::error!("Failed to extract palette: {:#}", e),
         }
     }
 
+    fn export_palette(&mut self) {
+        if let Some(path) = rfd::FileDialog::new().add_filter("Palette", &palette::export::EXTENSIONS).set_file_name("palette.gpl").save_file() {
+            if let Err(e) = palette::export::save(&path, &self.look.palette) { log::error!("Failed to export palette: {:#}", e); }
+        }
+    }
+
+    /// Replaces the gradient remap stops with the palette, darkest to lightest, and turns the remap on.
+    fn palette_to_gradient(&mut self) {
+        self.look.gradient = palette::to_gradient(&self.look.palette); self.look.settings.grad_enabled = 1.0;
+        self.selected_stop_id = self.look.gradient.first().map(|s| s.id); self.next_stop_id = self.look.gradient.len() as u64;
+        gradient::generate_gradient_data(&self.look.gradient, &mut self.gradient_data);
+        if let Some(q) = &self.queue { self.pipeline.update_gradient(q, &self.gradient_data); }
+        self.render_output();
+    }
+
     fn export_image(&mut self) {
-        let (Some(device), Some(queue), Some(input_tex), Some(current_img)) = (&self.device, &self.queue, &self.input_texture, &self.current_image) else { return };
-        let width = current_img.width(); let height = current_img.height();
-        let output_tex = device.create_texture(&wgpu::TextureDescriptor { label: Some("export_output_texture"), size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 }, mip_level_count: 1, sample_count: 1, dimension: wgpu::TextureDimension::D2, format: self.target_format, usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC, view_formats: &[] });
-        self.pipeline.render(device, queue, &input_tex.create_view(&wgpu::TextureViewDescriptor::default()), &output_tex.create_view(&wgpu::TextureViewDescriptor::default()), &self.settings);
-        
-        if let Some(img_buf) = self.read_back_image(&output_tex) {
-            let mut dimg = image::DynamicImage::ImageRgba8(img_buf);
-            if dimg.width() != self.export_settings.width_px || dimg.height() != self.export_settings.height_px { dimg = dimg.resize_exact(self.export_settings.width_px, self.export_settings.height_px, image::imageops::FilterType::Nearest); }
-            if !self.export_settings.transparency || self.export_settings.format == ExportFormat::Jpg { dimg = image::DynamicImage::ImageRgb8(dimg.to_rgb8()); }
-            let (ext, filt) = match self.export_settings.format { ExportFormat::Png => ("png", "PNG"), ExportFormat::Jpg => ("jpg", "JPEG"), ExportFormat::Webp => ("webp", "WEBP") };
-            let d_names = ["None", "Threshold", "Random", "Bayer", "BlueNoise", "DiffusionApprox", "Stucki", "Atkinson", "GradientBased", "LatticeBoltzmann"];
-            let d_idx = self.settings.dither_type as usize;
-            let d_name = d_names.get(d_idx).unwrap_or(&"Custom");
-            let color_suffix = if self.settings.grad_enabled > 0.5 { "_Colored" } else { "" };
+        let (Some(device), Some(queue), Some(current_img)) = (&self.device, &self.queue, &self.current_image) else { return };
+        let threshold = self.look.threshold_map();
+        self.pipeline.update_threshold_map(device, queue, &threshold);
+        let render = |settings: &ColorSettings| if settings.needs_cpu_pass() { Some(pipeline::cpu::render(current_img, settings, &self.curves_data, &self.gradient_data, &self.palette_data, &threshold)) } else { self.pipeline.render_image(device, queue, current_img, settings, self.target_format) };
+        // Continuous-tone PNG/TIFF can keep 16 bits per channel; dithered output never needs them.
+        let deep = self.export_settings.deep && self.export_settings.format.supports_16bit() && self.look.settings.allows_deep_output();
+        let rendered = if deep { self.pipeline.render_image_linear(device, queue, current_img, &self.look.settings).map(|img| image::DynamicImage::ImageRgba16(image_io::to_srgb16(&img))) } else { render(&self.look.settings).map(image::DynamicImage::ImageRgba8) };
+        if let Some(mut dimg) = rendered {
+            let svg = self.export_settings.format == ExportFormat::Svg;
+            dimg = image_io::resize_for_export(dimg, self.export_settings.format, self.export_settings.width_px, self.export_settings.height_px);
+            if !self.export_settings.transparency || !self.export_settings.format.supports_alpha() { dimg = image_io::flatten(&dimg, self.export_settings.matte, self.look.settings.alpha_premultiply > 0.5); }
+            let (ext, filt) = self.export_settings.format.extension();
+            let d_name = pipeline::DITHER_NAMES.get(self.look.settings.dither_type as usize).map(|n| n.replace(' ', "")).unwrap_or_else(|| "Custom".to_string());
+            let color_suffix = if self.look.settings.grad_enabled > 0.5 { "_Colored" } else { "" };
             let default_name = format!("VibeDither_{}{}.{}", d_name, color_suffix, ext);
             if let Some(path) = rfd::FileDialog::new().add_filter(filt, &[ext]).set_file_name(&default_name).save_file() {
-                match self.export_settings.format {
-                    ExportFormat::Png => {
-                        let mut f = std::fs::File::create(&path).unwrap();
-                        let level = if self.export_settings.compression > 0.8 { image::codecs::png::CompressionType::Best } else if self.export_settings.compression > 0.3 { image::codecs::png::CompressionType::Default } else { image::codecs::png::CompressionType::Fast };
-                        let encoder = image::codecs::png::PngEncoder::new_with_quality(&mut f, level, image::codecs::png::FilterType::Adaptive);
-                        let (w, h) = dimg.dimensions(); let color_type = dimg.color();
-                        encoder.write_image(dimg.as_bytes(), w, h, color_type).ok();
-                    },
-                    ExportFormat::Jpg => {
-                        let mut f = std::fs::File::create(&path).unwrap();
-                        let quality = (self.export_settings.compression * 100.0).clamp(1.0, 100.0) as u8;
-                        let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut f, quality);
-                        encoder.encode_image(&dimg).ok();
-                    },
-                    ExportFormat::Webp => { dimg.save(path).ok(); },
+                let saved = if svg { std::fs::write(&path, image_io::encode_svg(&dimg.to_rgba8(), self.export_settings.width_px, self.export_settings.height_px)).map_err(anyhow::Error::from) } else { image_io::save_image(&path, &dimg, self.export_settings.format, self.export_settings.compression) };
+                if let Err(e) = saved { log::error!("Failed to export image to {:?}: {}", path, e); }
+                if self.export_settings.cmyk_plates && self.look.settings.is_cmyk() {
+                    // Each plate as its own grayscale PNG next to the composite, ink black.
+                    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+                    for (i, name) in pipeline::CMYK_PLATES.iter().enumerate() {
+                        let Some(plate) = render(&self.look.settings.with_cmyk_plate(i)) else { continue };
+                        let plate = image::DynamicImage::ImageLuma8(image::DynamicImage::ImageRgba8(plate).resize_exact(self.export_settings.width_px, self.export_settings.height_px, image::imageops::FilterType::Nearest).to_luma8());
+                        let plate_path = path.with_file_name(format!("{}_{}.png", stem, name));
+                        if let Err(e) = image_io::save_image(&plate_path, &plate, ExportFormat::Png, self.export_settings.compression) { log::error!("Failed to export plate to {:?}: {}", plate_path, e); }
+                    }
                 }
             }
         }
@@ -208,8 +492,8 @@ impl eframe::App for VibeDitherApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
 
         let mut changed = false;
-        let (esc, space, k_a, k_d, k_q, k_e, k_c, k_h, _k_z, k_s, k_b, k_w, k_f, k_t, k_v, k_m, _k_o, k_p, k_n, k_g, k_r, k_y, k_l, k_j, k_k, k_up_p, k_down_p, k_left_p, k_right_p, shift, ctrl, keys_0_9, k_up_d, k_down_d, k_left_d, k_right_d) = ctx.input(|i| (
-            i.key_pressed(egui::Key::Escape), i.key_pressed(egui::Key::Space), i.key_pressed(egui::Key::A), i.key_pressed(egui::Key::D), i.key_pressed(egui::Key::Q), i.key_pressed(egui::Key::E), i.key_pressed(egui::Key::C), i.key_pressed(egui::Key::H), i.key_pressed(egui::Key::Z), i.key_pressed(egui::Key::S), i.key_pressed(egui::Key::B), i.key_pressed(egui::Key::W), i.key_pressed(egui::Key::F), i.key_pressed(egui::Key::T), i.key_pressed(egui::Key::V), i.key_pressed(egui::Key::M), i.key_pressed(egui::Key::O), i.key_pressed(egui::Key::P), i.key_pressed(egui::Key::N), i.key_pressed(egui::Key::G), i.key_pressed(egui::Key::R), i.key_pressed(egui::Key::Y), i.key_pressed(egui::Key::L), i.key_pressed(egui::Key::J), i.key_pressed(egui::Key::K),
+        let (esc, space, k_a, k_d, k_q, k_e, k_c, k_h, k_z, k_s, k_b, k_w, k_f, k_t, k_v, k_m, k_o, k_p, k_n, k_g, k_r, k_y, k_l, k_j, k_k, k_i, k_x, k_up_p, k_down_p, k_left_p, k_right_p, shift, ctrl, keys_0_9, k_up_d, k_down_d, k_left_d, k_right_d) = ctx.input(|i| (
+            i.key_pressed(egui::Key::Escape), i.key_pressed(egui::Key::Space), i.key_pressed(egui::Key::A), i.key_pressed(egui::Key::D), i.key_pressed(egui::Key::Q), i.key_pressed(egui::Key::E), i.key_pressed(egui::Key::C), i.key_pressed(egui::Key::H), i.key_pressed(egui::Key::Z), i.key_pressed(egui::Key::S), i.key_pressed(egui::Key::B), i.key_pressed(egui::Key::W), i.key_pressed(egui::Key::F), i.key_pressed(egui::Key::T), i.key_pressed(egui::Key::V), i.key_pressed(egui::Key::M), i.key_pressed(egui::Key::O), i.key_pressed(egui::Key::P), i.key_pressed(egui::Key::N), i.key_pressed(egui::Key::G), i.key_pressed(egui::Key::R), i.key_pressed(egui::Key::Y), i.key_pressed(egui::Key::L), i.key_pressed(egui::Key::J), i.key_pressed(egui::Key::K), i.key_pressed(egui::Key::I), i.key_pressed(egui::Key::X),
             i.key_pressed(egui::Key::W) || i.key_pressed(egui::Key::ArrowUp), i.key_pressed(egui::Key::S) || i.key_pressed(egui::Key::ArrowDown), i.key_pressed(egui::Key::A) || i.key_pressed(egui::Key::ArrowLeft), i.key_pressed(egui::Key::D) || i.key_pressed(egui::Key::ArrowRight),
             i.modifiers.shift, i.modifiers.ctrl,
             [i.key_pressed(egui::Key::Num0), i.key_pressed(egui::Key::Num1), i.key_pressed(egui::Key::Num2), i.key_pressed(egui::Key::Num3), i.key_pressed(egui::Key::Num4), i.key_pressed(egui::Key::Num5), i.key_pressed(egui::Key::Num6), i.key_pressed(egui::Key::Num7), i.key_pressed(egui::Key::Num8), i.key_pressed(egui::Key::Num9)],
@@ -218,6 +502,7 @@ impl eframe::App for VibeDitherApp {
 
         if !ctx.wants_keyboard_input() {
             if ctrl && k_s { self.focus = KeyboardFocus::Export; self.show_export_window = true; }
+            if ctrl && k_z { self.step_history(shift); }
 
             for (idx, &pressed) in keys_0_9.iter().enumerate() { if pressed && self.focus == KeyboardFocus::Main { self.zoom_factor = match idx { 1 => 1.0, 0 => 0.1, 2 => 2.0, 3 => 4.0, 4 => 8.0, 5 => 12.0, 6 => 16.0, 7 => 20.0, 8 => 24.0, 9 => 32.0, _ => self.zoom_factor }; self.fit_to_screen = false; self.pan_offset = egui::Vec2::ZERO; } }
 
@@ -225,8 +510,9 @@ impl eframe::App for VibeDitherApp {
                 self.focus = match self.focus {
                     KeyboardFocus::Editing(_) => if self.active_tab == Tab::Adjust { KeyboardFocus::Adjust } else { KeyboardFocus::Dither },
                     KeyboardFocus::Light | KeyboardFocus::Color => KeyboardFocus::Adjust,
-                    KeyboardFocus::ModeSelection | KeyboardFocus::PosterizeMenu | KeyboardFocus::BayerSizeMenu | KeyboardFocus::GradientMapMenu | KeyboardFocus::Export => KeyboardFocus::Dither,
+                    KeyboardFocus::ModeSelection | KeyboardFocus::PosterizeMenu | KeyboardFocus::BayerSizeMenu | KeyboardFocus::NoiseSizeMenu | KeyboardFocus::KernelMenu | KeyboardFocus::GradientMapMenu | KeyboardFocus::PaletteMenu | KeyboardFocus::Export => KeyboardFocus::Dither,
                     KeyboardFocus::GradientPointEdit => KeyboardFocus::GradientMapMenu,
+                    KeyboardFocus::PaletteLibraryMenu => KeyboardFocus::PaletteMenu,
                     _ => KeyboardFocus::Main,
                 };
                 if self.focus != KeyboardFocus::Export { self.show_export_window = false; }
@@ -236,55 +522,110 @@ impl eframe::App for VibeDitherApp {
                 KeyboardFocus::Main => { 
                     if k_a { self.active_tab = Tab::Adjust; self.focus = KeyboardFocus::Adjust; } 
                     if k_d { self.active_tab = Tab::Dither; self.focus = KeyboardFocus::Dither; } 
+                    if k_p { self.focus = KeyboardFocus::PresetMenu; }
+                    if k_c { self.compare = match self.compare { Compare::Off => Compare::Split, Compare::Split => Compare::SideBySide, Compare::SideBySide => Compare::Off }; }
+                    if k_v { self.split_vertical = !self.split_vertical; }
+                    if k_n { self.focus = KeyboardFocus::Snapshots; self.show_snapshots = true; }
+                    if k_i { self.inspector = !self.inspector; }
                     let pan_speed = if shift { 50.0 } else { 10.0 };
                     if k_up_d { self.pan_offset.y += pan_speed; }
                     if k_down_d { self.pan_offset.y -= pan_speed; }
                     if k_left_d { self.pan_offset.x += pan_speed; }
                     if k_right_d { self.pan_offset.x -= pan_speed; }
                 }
-                KeyboardFocus::Adjust => { if k_q { self.focus = KeyboardFocus::Light; } if k_e { self.focus = KeyboardFocus::Color; } if k_d { self.active_tab = Tab::Dither; self.focus = KeyboardFocus::Dither; } }
-                KeyboardFocus::Light => { if k_e { self.focus = KeyboardFocus::Editing("exposure"); } if k_c { self.focus = KeyboardFocus::Editing("contrast"); } if k_h { self.focus = KeyboardFocus::Editing("highlights"); } if k_s { self.focus = KeyboardFocus::Editing("shadows"); } if k_b { self.focus = KeyboardFocus::Editing("blacks"); } if k_w { self.focus = KeyboardFocus::Editing("whites"); } if k_f { self.focus = KeyboardFocus::Editing("sharpness"); } }
-                KeyboardFocus::Color => { if k_t { self.focus = KeyboardFocus::Editing("temperature"); } if k_e { self.focus = KeyboardFocus::Editing("tint"); } if k_s { self.focus = KeyboardFocus::Editing("saturation"); } if k_v { self.focus = KeyboardFocus::Editing("vibrance"); } if k_f { self.focus = KeyboardFocus::Editing("sharpness"); } }
+                KeyboardFocus::Adjust => { if k_q { self.focus = KeyboardFocus::Light; } else if k_e { self.focus = KeyboardFocus::Color; } else if k_w { self.look.settings.adjust_space = 1.0 - self.look.settings.adjust_space.round(); changed = true; } else if k_d { self.active_tab = Tab::Dither; self.focus = KeyboardFocus::Dither; } }
+                KeyboardFocus::Light => { if k_e { self.focus = KeyboardFocus::Editing("exposure"); } else if k_c { self.focus = KeyboardFocus::Editing("contrast"); } else if k_h { self.focus = KeyboardFocus::Editing("highlights"); } else if k_s { self.focus = KeyboardFocus::Editing("shadows"); } else if k_b { self.focus = KeyboardFocus::Editing("blacks"); } else if k_w { self.focus = KeyboardFocus::Editing("whites"); } else if k_f { self.focus = KeyboardFocus::Editing("sharpness"); } }
+                KeyboardFocus::Color => { if k_t { self.focus = KeyboardFocus::Editing("temperature"); } else if k_e { self.focus = KeyboardFocus::Editing("tint"); } else if k_s { self.focus = KeyboardFocus::Editing("saturation"); } else if k_v { self.focus = KeyboardFocus::Editing("vibrance"); } else if k_f { self.focus = KeyboardFocus::Editing("sharpness"); } }
                 KeyboardFocus::Dither => {
-                    if k_m { self.focus = KeyboardFocus::ModeSelection; } if k_s { self.focus = KeyboardFocus::Editing("scale"); } 
+                    if k_m { self.focus = KeyboardFocus::ModeSelection; } else if k_s { self.focus = KeyboardFocus::Editing("scale"); } 
                     if k_p { 
                         self.focus = KeyboardFocus::PosterizeMenu; 
                     }
-                    if k_t && self.settings.dither_type == 1.0 { self.focus = KeyboardFocus::Editing("threshold"); } 
-                    if k_f && self.settings.dither_type == 3.0 { self.focus = KeyboardFocus::BayerSizeMenu; }
-                    if k_c && self.settings.dither_type != 1.0 { self.settings.dither_color = if self.settings.dither_color > 0.5 { 0.0 } else { 1.0 }; changed = true; } 
-                    if k_g { self.focus = KeyboardFocus::GradientMapMenu; } if k_a { self.active_tab = Tab::Adjust; self.focus = KeyboardFocus::Adjust; }
+                    if k_t && (self.look.settings.dither_type == 1.0 || self.look.settings.dither_type == 10.0) { self.focus = KeyboardFocus::Editing("threshold"); } 
+                    if k_f && self.look.settings.dither_type == 3.0 { self.focus = KeyboardFocus::BayerSizeMenu; }
+                    if k_f && self.look.settings.dither_type == 4.0 { self.focus = KeyboardFocus::NoiseSizeMenu; }
+                    if self.look.settings.dither_type == 10.0 {
+                        if k_k { self.focus = KeyboardFocus::KernelMenu; }
+                        if k_r { self.look.settings.diffusion_serpentine = if self.look.settings.diffusion_serpentine > 0.5 { 0.0 } else { 1.0 }; changed = true; }
+                        if k_b { self.focus = KeyboardFocus::Editing("strength"); }
+                        if k_e { self.focus = KeyboardFocus::Editing("clamp"); }
+                    }
+                    if self.look.settings.dither_type == 12.0 {
+                        if k_n { self.focus = KeyboardFocus::Editing("points"); }
+                        if k_i { self.focus = KeyboardFocus::Editing("iterations"); }
+                        if k_b { self.focus = KeyboardFocus::Editing("dot min"); }
+                        if k_e { self.focus = KeyboardFocus::Editing("dot max"); }
+                    }
+                    if self.look.settings.dither_type == 11.0 {
+                        if k_f { self.focus = KeyboardFocus::Editing("frequency"); }
+                        if k_r { self.focus = KeyboardFocus::Editing("angle"); }
+                        if k_k { self.look.settings.halftone_shape = ((self.look.settings.halftone_shape as usize + 1) % halftone::SHAPE_NAMES.len()) as f32; changed = true; }
+                        if k_y { self.look.settings.halftone_cmyk = if self.look.settings.halftone_cmyk > 0.5 { 0.0 } else { 1.0 }; changed = true; }
+                        if self.look.settings.halftone_cmyk > 0.5 {
+                            if k_b { self.focus = KeyboardFocus::Editing("black"); }
+                            if k_e { self.focus = KeyboardFocus::Editing("black start"); }
+                            if k_v { self.look.settings.cmyk_plate = ((self.look.settings.cmyk_plate as usize + 1) % (pipeline::CMYK_PLATES.len() + 1)) as f32; changed = true; }
+                        }
+                    }
+                    if k_c && self.look.settings.dither_type != 1.0 { self.look.settings.dither_color = if self.look.settings.dither_color > 0.5 { 0.0 } else { 1.0 }; changed = true; } 
+                    if k_w { self.look.settings.dither_space = 1.0 - self.look.settings.dither_space.round(); changed = true; }
+                    if k_g { self.focus = KeyboardFocus::GradientMapMenu; } else if k_l { self.focus = KeyboardFocus::PaletteMenu; } else if k_a { self.active_tab = Tab::Adjust; self.focus = KeyboardFocus::Adjust; }
                 }
                 KeyboardFocus::ModeSelection => {
-                    let mut m = None; if k_a { m = Some(0.0); } if k_s { m = Some(1.0); } if k_d { m = Some(2.0); } if k_f { m = Some(3.0); } if k_g { m = Some(4.0); } if k_h { m = Some(5.0); } if k_j { m = Some(6.0); } if k_k { m = Some(7.0); } if k_l { m = Some(8.0); } if k_c { m = Some(9.0); }
-                    if let Some(val) = m { self.settings.dither_type = val; self.settings.dither_enabled = if val > 0.0 { 1.0 } else { 0.0 }; self.focus = KeyboardFocus::Dither; changed = true; }
+                    let mut m = None; if k_a { m = Some(0.0); } else if k_s { m = Some(1.0); } else if k_d { m = Some(2.0); } else if k_f { m = Some(3.0); } else if k_g { m = Some(4.0); } else if k_h { m = Some(5.0); } else if k_j { m = Some(6.0); } else if k_k { m = Some(7.0); } else if k_l { m = Some(8.0); } else if k_c { m = Some(9.0); } else if k_e { m = Some(10.0); } else if k_t { m = Some(11.0); } else if k_v { m = Some(12.0); }
+                    if let Some(val) = m { self.look.settings.dither_type = val; self.look.settings.dither_enabled = if val > 0.0 { 1.0 } else { 0.0 }; self.focus = KeyboardFocus::Dither; changed = true; }
                 }
                 KeyboardFocus::PosterizeMenu => { 
                     if k_e { 
-                        self.settings.posterize_levels = if self.settings.posterize_levels > 0.0 { 0.0 } else { 4.0 }; 
+                        self.look.settings.posterize_levels = if self.look.settings.posterize_levels > 0.0 { 0.0 } else { 4.0 }; 
                         changed = true;
                     } 
                     let delta = if k_right_p || k_up_p { 1.0 } else if k_left_p || k_down_p { -1.0 } else { 0.0 };
-                    if delta != 0.0 && self.settings.posterize_levels > 0.0 {
+                    if delta != 0.0 && self.look.settings.posterize_levels > 0.0 {
                         let now = ctx.input(|i| i.time);
                         if now - self.last_edit_time > 0.1 {
                             let step = if shift { 2.0 } else { 1.0 };
-                            self.settings.posterize_levels = (self.settings.posterize_levels + delta * step).clamp(2.0, 64.0);
+                            self.look.settings.posterize_levels = (self.look.settings.posterize_levels + delta * step).clamp(2.0, 64.0);
                             self.last_edit_time = now; changed = true;
                         }
                     }
                 }
-                KeyboardFocus::BayerSizeMenu => { let mut sz = None; if keys_0_9[2] { sz = Some(2.0); } if keys_0_9[3] { sz = Some(3.0); } if keys_0_9[4] { sz = Some(4.0); } if keys_0_9[8] { sz = Some(8.0); } if let Some(s) = sz { self.settings.bayer_size = s; self.focus = KeyboardFocus::Dither; changed = true; } }
+                KeyboardFocus::BayerSizeMenu => {
+                    let mut sz = None; if keys_0_9[2] { sz = Some(2.0); } else if keys_0_9[3] { sz = Some(3.0); } else if keys_0_9[4] { sz = Some(4.0); } else if keys_0_9[8] { sz = Some(8.0); } else if keys_0_9[5] { sz = Some(16.0); } else if keys_0_9[6] { sz = Some(32.0); } else if keys_0_9[7] { sz = Some(64.0); }
+                    if let Some(s) = sz { self.look.settings.bayer_size = s; self.focus = KeyboardFocus::Dither; changed = true; }
+                    if k_q { self.look.settings.ordered_matrix = ((self.look.settings.ordered_matrix as usize + 1) % matrix::MATRIX_NAMES.len()) as f32; changed = true; }
+                    if k_i { self.import_matrix(); }
+                }
+                KeyboardFocus::NoiseSizeMenu => { if let Some(i) = (1..=blue_noise::SIZES.len()).find(|&i| keys_0_9[i]) { self.look.settings.blue_noise_size = blue_noise::SIZES[i - 1] as f32; self.focus = KeyboardFocus::Dither; changed = true; } }
+                KeyboardFocus::KernelMenu => { if let Some(k) = (1..=diffusion::KERNELS.len()).find(|&k| keys_0_9[k]) { self.look.settings.diffusion_kernel = (k - 1) as f32; self.focus = KeyboardFocus::Dither; changed = true; } }
                 KeyboardFocus::GradientMapMenu => {
-                    if k_e { self.settings.grad_enabled = if self.settings.grad_enabled > 0.5 { 0.0 } else { 1.0 }; changed = true; }
+                    if k_e { self.look.settings.grad_enabled = if self.look.settings.grad_enabled > 0.5 { 0.0 } else { 1.0 }; changed = true; }
                     let now = ctx.input(|i| i.time);
                     if now - self.last_edit_time > 0.166 {
-                        if k_left_p { if let Some(id) = self.selected_stop_id { if let Some(idx) = self.gradient_stops.iter().position(|s| s.id == id) { if idx > 0 { self.selected_stop_id = Some(self.gradient_stops[idx-1].id); self.last_edit_time = now; } } } }
-                        if k_right_p { if let Some(id) = self.selected_stop_id { if let Some(idx) = self.gradient_stops.iter().position(|s| s.id == id) { if idx < self.gradient_stops.len() - 1 { self.selected_stop_id = Some(self.gradient_stops[idx+1].id); self.last_edit_time = now; } } } }
+                        if k_left_p { if let Some(id) = self.selected_stop_id { if let Some(idx) = self.look.gradient.iter().position(|s| s.id == id) { if idx > 0 { self.selected_stop_id = Some(self.look.gradient[idx-1].id); self.last_edit_time = now; } } } }
+                        if k_right_p { if let Some(id) = self.selected_stop_id { if let Some(idx) = self.look.gradient.iter().position(|s| s.id == id) { if idx < self.look.gradient.len() - 1 { self.selected_stop_id = Some(self.look.gradient[idx+1].id); self.last_edit_time = now; } } } }
                     }
                     if space { self.focus = KeyboardFocus::GradientPointEdit; }
-                    if k_n { let nid = self.next_stop_id; self.next_stop_id += 1; self.gradient_stops.push(GradientStop { id: nid, pos: 0.5, color: egui::Color32::GRAY }); self.selected_stop_id = Some(nid); self.gradient_stops.sort_by(|a,b| a.pos.partial_cmp(&b.pos).unwrap()); Self::generate_gradient_data(&self.gradient_stops, &mut self.gradient_data); if let Some(q) = &self.queue { self.pipeline.update_gradient(q, &self.gradient_data); } changed = true; }
-                    if k_b { if let Some(id) = self.selected_stop_id { if self.gradient_stops.len() > 2 { self.gradient_stops.retain(|s| s.id != id); self.selected_stop_id = self.gradient_stops.first().map(|s| s.id); Self::generate_gradient_data(&self.gradient_stops, &mut self.gradient_data); if let Some(q) = &self.queue { self.pipeline.update_gradient(q, &self.gradient_data); } changed = true; } } }
+                    if k_n { let nid = self.next_stop_id; self.next_stop_id += 1; self.look.gradient.push(GradientStop::new(nid, 0.5, [160, 160, 160])); self.selected_stop_id = Some(nid); self.look.gradient.sort_by(|a,b| a.pos.partial_cmp(&b.pos).unwrap()); gradient::generate_gradient_data(&self.look.gradient, &mut self.gradient_data); if let Some(q) = &self.queue { self.pipeline.update_gradient(q, &self.gradient_data); } changed = true; }
+                    if k_b { if let Some(id) = self.selected_stop_id { if self.look.gradient.len() > 2 { self.look.gradient.retain(|s| s.id != id); self.selected_stop_id = self.look.gradient.first().map(|s| s.id); gradient::generate_gradient_data(&self.look.gradient, &mut self.gradient_data); if let Some(q) = &self.queue { self.pipeline.update_gradient(q, &self.gradient_data); } changed = true; } } }
+                }
+                KeyboardFocus::PaletteMenu => {
+                    if k_e { self.look.settings.palette_enabled = if self.look.settings.palette_enabled > 0.5 { 0.0 } else { 1.0 }; changed = true; }
+                    if k_i { self.import_palette(); }
+                    if k_v { self.paste_palette(); }
+                    if k_g { self.palette_to_gradient(); }
+                    if k_b { self.focus = KeyboardFocus::PaletteLibraryMenu; }
+                    if k_x { self.extract_palette(); }
+                    if k_c { self.look.settings.color_metric = ((self.look.settings.color_metric as usize + 1) % pipeline::COLOR_METRIC_NAMES.len()) as f32; changed = true; }
+                    if k_o { self.export_palette(); }
+                    if k_m { let i = extract::Method::ALL.iter().position(|&m| m == self.extract_method).unwrap_or(0); self.extract_method = extract::Method::ALL[(i + 1) % extract::Method::ALL.len()]; }
+                    if k_right_p { self.extract_count = (self.extract_count + if shift { 8 } else { 1 }).min(palette::MAX_COLORS); }
+                    if k_left_p { self.extract_count = self.extract_count.saturating_sub(if shift { 8 } else { 1 }).max(2); }
+                }
+                KeyboardFocus::PaletteLibraryMenu => {
+                    if k_down_p || k_right_p { self.cycle_builtin_palette(1); }
+                    if k_up_p || k_left_p { self.cycle_builtin_palette(-1); }
+                    if space { self.focus = KeyboardFocus::PaletteMenu; }
                 }
                 KeyboardFocus::GradientPointEdit => {
                     if space { self.focus = KeyboardFocus::GradientMapMenu; }
@@ -292,8 +633,8 @@ impl eframe::App for VibeDitherApp {
                     if now - self.last_edit_time > 0.166 {
                         let mut st_ch = false;
                         if let Some(id) = self.selected_stop_id {
-                            if let Some(stop) = self.gradient_stops.iter_mut().find(|s| s.id == id) {
-                                let mut hsva = egui::ecolor::Hsva::from(stop.color);
+                            if let Some(stop) = self.look.gradient.iter_mut().find(|s| s.id == id) {
+                                let mut hsva = egui::ecolor::Hsva::from(egui::Color32::from_rgb(stop.color[0], stop.color[1], stop.color[2]));
                                 let h_step = if shift { 1.0/360.0 } else { 10.0/360.0 };
                                 let sv_step = if shift { 0.01 } else { 0.1 };
                                 if k_r { hsva.h = (hsva.h + h_step).fract(); st_ch = true; }
@@ -304,12 +645,12 @@ impl eframe::App for VibeDitherApp {
                                 if k_h { hsva.v = (hsva.v - sv_step).clamp(0.0, 1.0); st_ch = true; }
                                 if k_left_p { stop.pos = (stop.pos - 0.01).clamp(0.0, 1.0); st_ch = true; }
                                 if k_right_p { stop.pos = (stop.pos + 0.01).clamp(0.0, 1.0); st_ch = true; }
-                                if st_ch { stop.color = egui::Color32::from(hsva); }
+                                if st_ch { stop.color = hsva.to_srgb(); }
                             }
                         }
                         if st_ch {
-                            self.gradient_stops.sort_by(|a,b| a.pos.partial_cmp(&b.pos).unwrap());
-                            Self::generate_gradient_data(&self.gradient_stops, &mut self.gradient_data);
+                            self.look.gradient.sort_by(|a,b| a.pos.partial_cmp(&b.pos).unwrap());
+                            gradient::generate_gradient_data(&self.look.gradient, &mut self.gradient_data);
                             if let Some(queue) = &self.queue { self.pipeline.update_gradient(queue, &self.gradient_data); }
                             changed = true; self.last_edit_time = now;
                         }
@@ -324,43 +665,71 @@ impl eframe::App for VibeDitherApp {
                             let mut act_step = if shift { 0.1 } else { 0.05 };
                             if id == "exposure" { act_step = if shift { 0.15 } else { 0.05 }; }
                             if id == "scale" || id == "posterize" { act_step = 1.0; if shift { act_step = 2.0; } }
+                            if id == "frequency" { act_step = if shift { 10.0 } else { 1.0 }; }
+                            if id == "angle" { act_step = if shift { 15.0 } else { 1.0 }; }
+                            if id == "points" { act_step = if shift { 1000.0 } else { 100.0 }; }
+                            if id == "iterations" { act_step = if shift { 10.0 } else { 1.0 }; }
                             match id {
-                                "exposure" => self.settings.exposure = (self.settings.exposure + delta * act_step).clamp(-5.0, 5.0),
-                                "contrast" => self.settings.contrast = (self.settings.contrast + delta * act_step).clamp(0.0, 2.0),
-                                "highlights" => self.settings.highlights = (self.settings.highlights + delta * act_step).clamp(-1.0, 1.0),
-                                "shadows" => self.settings.shadows = (self.settings.shadows + delta * act_step).clamp(-1.0, 1.0),
-                                "whites" => self.settings.whites = (self.settings.whites + delta * act_step).clamp(-1.0, 1.0),
-                                "blacks" => self.settings.blacks = (self.settings.blacks + delta * act_step).clamp(-1.0, 1.0),
-                                "sharpness" => self.settings.sharpness = (self.settings.sharpness + delta * act_step).clamp(0.0, 2.0),
-                                "temperature" => self.settings.temperature = (self.settings.temperature + delta * act_step).clamp(-1.0, 1.0),
-                                "tint" => self.settings.tint = (self.settings.tint + delta * act_step).clamp(-1.0, 1.0),
-                                "saturation" => self.settings.saturation = (self.settings.saturation + delta * act_step).clamp(0.0, 2.0),
-                                "vibrance" => self.settings.vibrance = (self.settings.vibrance + delta * act_step).clamp(-1.0, 1.0),
-                                "scale" => self.settings.dither_scale = (self.settings.dither_scale + delta * act_step).clamp(1.0, 32.0),
-                                "threshold" => self.settings.dither_threshold = (self.settings.dither_threshold + delta * act_step).clamp(0.0, 1.0),
-                                "posterize" => self.settings.posterize_levels = (self.settings.posterize_levels + delta * act_step).clamp(0.0, 64.0),
+                                "exposure" => self.look.settings.exposure = (self.look.settings.exposure + delta * act_step).clamp(-5.0, 5.0),
+                                "contrast" => self.look.settings.contrast = (self.look.settings.contrast + delta * act_step).clamp(0.0, 2.0),
+                                "highlights" => self.look.settings.highlights = (self.look.settings.highlights + delta * act_step).clamp(-1.0, 1.0),
+                                "shadows" => self.look.settings.shadows = (self.look.settings.shadows + delta * act_step).clamp(-1.0, 1.0),
+                                "whites" => self.look.settings.whites = (self.look.settings.whites + delta * act_step).clamp(-1.0, 1.0),
+                                "blacks" => self.look.settings.blacks = (self.look.settings.blacks + delta * act_step).clamp(-1.0, 1.0),
+                                "sharpness" => self.look.settings.sharpness = (self.look.settings.sharpness + delta * act_step).clamp(0.0, 2.0),
+                                "temperature" => self.look.settings.temperature = (self.look.settings.temperature + delta * act_step).clamp(-1.0, 1.0),
+                                "tint" => self.look.settings.tint = (self.look.settings.tint + delta * act_step).clamp(-1.0, 1.0),
+                                "saturation" => self.look.settings.saturation = (self.look.settings.saturation + delta * act_step).clamp(0.0, 2.0),
+                                "vibrance" => self.look.settings.vibrance = (self.look.settings.vibrance + delta * act_step).clamp(-1.0, 1.0),
+                                "scale" => self.look.settings.dither_scale = (self.look.settings.dither_scale + delta * act_step).clamp(1.0, 32.0),
+                                "threshold" => self.look.settings.dither_threshold = (self.look.settings.dither_threshold + delta * act_step).clamp(0.0, 1.0),
+                                "posterize" => self.look.settings.posterize_levels = (self.look.settings.posterize_levels + delta * act_step).clamp(0.0, 64.0),
+                                "strength" => self.look.settings.diffusion_strength = (self.look.settings.diffusion_strength + delta * act_step).clamp(0.0, 1.0),
+                                "clamp" => self.look.settings.diffusion_clamp = (self.look.settings.diffusion_clamp + delta * act_step).clamp(0.0, 1.0),
+                                "frequency" => self.look.settings.halftone_frequency = (self.look.settings.halftone_frequency + delta * act_step).clamp(halftone::MIN_FREQUENCY, halftone::MAX_FREQUENCY),
+                                "angle" => self.look.settings.halftone_angle = (self.look.settings.halftone_angle + delta * act_step).rem_euclid(180.0),
+                                "black" => self.look.settings.cmyk_black = (self.look.settings.cmyk_black + delta * act_step).clamp(0.0, 1.0),
+                                "black start" => self.look.settings.cmyk_black_start = (self.look.settings.cmyk_black_start + delta * act_step).clamp(0.0, 0.95),
+                                "points" => self.look.settings.stipple_points = (self.look.settings.stipple_points + delta * act_step).clamp(stipple::MIN_POINTS, stipple::MAX_POINTS),
+                                "iterations" => self.look.settings.stipple_iterations = (self.look.settings.stipple_iterations + delta * act_step).clamp(0.0, stipple::MAX_ITERATIONS),
+                                "dot min" => self.look.settings.stipple_min_size = (self.look.settings.stipple_min_size + delta * act_step).clamp(0.0, self.look.settings.stipple_max_size),
+                                "dot max" => self.look.settings.stipple_max_size = (self.look.settings.stipple_max_size + delta * act_step).clamp(self.look.settings.stipple_min_size, 1.0),
                                 _ => {}
                             }
                             self.last_edit_time = now; changed = true;
                         }
                     }
                 }
+                KeyboardFocus::PresetMenu => {
+                    if k_s && !ctrl { self.save_preset(); self.focus = KeyboardFocus::Main; }
+                    if k_l { self.load_preset(); self.focus = KeyboardFocus::Main; }
+                }
+                KeyboardFocus::Snapshots => {
+                    for (idx, &pressed) in keys_0_9.iter().enumerate() { if pressed { if idx == 0 { if self.previewing.is_some() { self.render_output(); } } else { self.preview_snapshot(idx - 1); } } }
+                    if k_n { self.take_snapshot(); }
+                    if let Some(idx) = self.previewing { if k_p { self.promote_snapshot(idx); } else if k_x { self.delete_snapshot(idx); } }
+                }
                 KeyboardFocus::Export => {
                     if k_up_p { self.export_row = self.export_row.saturating_sub(1); }
-                    if k_down_p { self.export_row = (self.export_row + 1).min(5); }
+                    if k_down_p { self.export_row = (self.export_row + 1).min(6); }
                     if k_left_p { self.export_col = self.export_col.saturating_sub(1); }
-                    if k_right_p { self.export_col = (self.export_col + 1).min(2); }
+                    if k_right_p { self.export_col = (self.export_col + 1).min(4); }
                     if space {
                         match (self.export_row, self.export_col) {
                             (0, 0) => self.export_settings.format = ExportFormat::Png,
                             (0, 1) => self.export_settings.format = ExportFormat::Jpg,
                             (0, 2) => self.export_settings.format = ExportFormat::Webp,
-                            (2, _) => if self.export_settings.format != ExportFormat::Jpg { self.export_settings.transparency = !self.export_settings.transparency; },
+                            (0, 3) => self.export_settings.format = ExportFormat::Svg,
+                            (0, 4) => self.export_settings.format = ExportFormat::Tiff,
+                            (2, 1) if self.export_settings.format.supports_16bit() && self.look.settings.allows_deep_output() => self.export_settings.deep = !self.export_settings.deep,
+                            (2, 0) if self.export_settings.format.supports_alpha() => self.export_settings.transparency = !self.export_settings.transparency,
+                            (2, 2) => self.export_settings.matte = if self.export_settings.matte == [255; 3] { [0; 3] } else { [255; 3] },
                             (3, 0) => self.export_settings.use_percentage = true,
                             (3, 1) => self.export_settings.use_percentage = false,
                             (4, 1) => self.export_settings.link_aspect = !self.export_settings.link_aspect,
-                            (5, 0) => { self.show_export_window = false; self.focus = KeyboardFocus::Main; },
-                            (5, 1) => { self.export_image(); self.show_export_window = false; self.focus = KeyboardFocus::Main; },
+                            (5, _) if self.look.settings.is_cmyk() => self.export_settings.cmyk_plates = !self.export_settings.cmyk_plates,
+                            (6, 0) => { self.show_export_window = false; self.focus = KeyboardFocus::Main; },
+                            (6, 1) => { self.export_image(); self.show_export_window = false; self.focus = KeyboardFocus::Main; },
                             _ => {}
                         }
                     }
@@ -397,7 +766,7 @@ impl eframe::App for VibeDitherApp {
                 let frame = egui::Frame::none().fill(egui::Color32::BLACK).stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 255, 0))).inner_margin(20.0);
                 frame.show(ui, |ui| {
                     let val = match id {
-                        "exposure" => self.settings.exposure, "contrast" => self.settings.contrast, "highlights" => self.settings.highlights, "shadows" => self.settings.shadows, "whites" => self.settings.whites, "blacks" => self.settings.blacks, "sharpness" => self.settings.sharpness, "temperature" => self.settings.temperature, "tint" => self.settings.tint, "saturation" => self.settings.saturation, "vibrance" => self.settings.vibrance, "scale" => self.settings.dither_scale, "threshold" => self.settings.dither_threshold, "posterize" => self.settings.posterize_levels, _ => 0.0,
+                        "exposure" => self.look.settings.exposure, "contrast" => self.look.settings.contrast, "highlights" => self.look.settings.highlights, "shadows" => self.look.settings.shadows, "whites" => self.look.settings.whites, "blacks" => self.look.settings.blacks, "sharpness" => self.look.settings.sharpness, "temperature" => self.look.settings.temperature, "tint" => self.look.settings.tint, "saturation" => self.look.settings.saturation, "vibrance" => self.look.settings.vibrance, "scale" => self.look.settings.dither_scale, "threshold" => self.look.settings.dither_threshold, "posterize" => self.look.settings.posterize_levels, "strength" => self.look.settings.diffusion_strength, "clamp" => self.look.settings.diffusion_clamp, "frequency" => self.look.settings.halftone_frequency, "angle" => self.look.settings.halftone_angle, "black" => self.look.settings.cmyk_black, "black start" => self.look.settings.cmyk_black_start, "points" => self.look.settings.stipple_points, "iterations" => self.look.settings.stipple_iterations, "dot min" => self.look.settings.stipple_min_size, "dot max" => self.look.settings.stipple_max_size, _ => 0.0,
                     };
                     ui.heading(format!("{}: {:.2}", id.to_uppercase(), val));
                 });
@@ -417,45 +786,97 @@ impl eframe::App for VibeDitherApp {
                 let focus_label = match self.focus {
                     KeyboardFocus::Main => "[MAIN]",
                     KeyboardFocus::Adjust | KeyboardFocus::Light | KeyboardFocus::Color | KeyboardFocus::Editing("exposure") | KeyboardFocus::Editing("contrast") | KeyboardFocus::Editing("highlights") | KeyboardFocus::Editing("shadows") | KeyboardFocus::Editing("whites") | KeyboardFocus::Editing("blacks") | KeyboardFocus::Editing("sharpness") | KeyboardFocus::Editing("temperature") | KeyboardFocus::Editing("tint") | KeyboardFocus::Editing("saturation") | KeyboardFocus::Editing("vibrance") => "[ADJUST]",
-                    KeyboardFocus::Dither | KeyboardFocus::ModeSelection | KeyboardFocus::PosterizeMenu | KeyboardFocus::BayerSizeMenu | KeyboardFocus::Editing("scale") | KeyboardFocus::Editing("threshold") | KeyboardFocus::Editing("posterize") => "[DITHER]",
+                    KeyboardFocus::Dither | KeyboardFocus::ModeSelection | KeyboardFocus::PosterizeMenu | KeyboardFocus::BayerSizeMenu | KeyboardFocus::NoiseSizeMenu | KeyboardFocus::KernelMenu | KeyboardFocus::Editing("scale") | KeyboardFocus::Editing("threshold") | KeyboardFocus::Editing("posterize") | KeyboardFocus::Editing("strength") | KeyboardFocus::Editing("clamp") | KeyboardFocus::Editing("frequency") | KeyboardFocus::Editing("angle") | KeyboardFocus::Editing("black") | KeyboardFocus::Editing("black start") | KeyboardFocus::Editing("points") | KeyboardFocus::Editing("iterations") | KeyboardFocus::Editing("dot min") | KeyboardFocus::Editing("dot max") => "[DITHER]",
                     KeyboardFocus::GradientMapMenu | KeyboardFocus::GradientPointEdit => "[GRADIENT]",
+                    KeyboardFocus::PaletteMenu | KeyboardFocus::PaletteLibraryMenu => "[PALETTE]",
                     KeyboardFocus::Export => "[EXPORT]",
+                    KeyboardFocus::PresetMenu => "[PRESET]",
+                    KeyboardFocus::Snapshots => "[SNAPSHOTS]",
                     _ => "[EDITING]",
                 };
                 ui.label(focus_label);
                 ui.add_space(8.0);
 
-                let d_type = self.settings.dither_type as i32;
+                let d_type = self.look.settings.dither_type as i32;
                 let shortcut_text = match self.focus {
-                    KeyboardFocus::Main => "Esc:Back  Ctrl+S:Export  0-9:Zoom    |    A:Adjust  D:Dither",
-                    KeyboardFocus::Adjust => "Q:Light  E:Color  Esc:Back",
+                    KeyboardFocus::Main => "Esc:Back  Ctrl+S:Export  Ctrl+Z:Undo  Ctrl+Shift+Z:Redo  0-9:Zoom    |    A:Adjust  D:Dither  P:Preset  N:Snapshots  I:Inspect  C:Compare  V:Split Dir  \\(hold):Original",
+                    KeyboardFocus::PresetMenu => "S:Save Preset  L:Load Preset  Esc:Back",
+                    KeyboardFocus::Snapshots => "1-9:Show Snapshot  0:Current  N:New Snapshot  P:Promote Shown  X:Delete Shown  Esc:Back",
+                    KeyboardFocus::Adjust => "Q:Light  E:Color  W:Lin/Gamma  Esc:Back",
                     KeyboardFocus::Light => "E:Exp C:Cont H:High S:Shad B:Black W:White F:Sharp Esc:Back",
                     KeyboardFocus::Color => "T:Temp E:Tint S:Sat V:Vib F:Sharp Esc:Back",
                     KeyboardFocus::Dither => {
                         if d_type == 1 || d_type == 3 {
-                            "M:Mode S:Scale P:Post T:Thresh F:Bayer C:Color G:Ramp Esc:Back"
+                            "M:Mode S:Scale P:Post T:Thresh F:Bayer C:Color L:Palette G:Ramp W:Lin/Gamma Esc:Back"
+                        } else if d_type == 4 {
+                            "M:Mode S:Scale P:Post F:Size C:Color L:Palette G:Ramp W:Lin/Gamma Esc:Back"
+                        } else if d_type == 10 {
+                            "M:Mode S:Scale P:Post T:Thresh K:Kernel R:Serp B:Strength E:Clamp C:Color L:Palette G:Ramp W:Lin/Gamma Esc:Back"
+                        } else if d_type == 12 {
+                            "M:Mode S:Scale P:Post N:Points I:Iter B:DotMin E:DotMax C:Color L:Palette G:Ramp W:Lin/Gamma Esc:Back"
+                        } else if d_type == 11 && self.look.settings.halftone_cmyk > 0.5 {
+                            "M:Mode S:Scale P:Post F:Freq R:Angle K:Shape Y:CMYK B:Black E:BlackStart V:Plate L:Palette G:Ramp W:Lin/Gamma Esc:Back"
+                        } else if d_type == 11 {
+                            "M:Mode S:Scale P:Post F:Freq R:Angle K:Shape Y:CMYK C:Color L:Palette G:Ramp W:Lin/Gamma Esc:Back"
                         } else {
-                            "M:Mode S:Scale P:Post C:Color G:Ramp Esc:Back"
+                            "M:Mode S:Scale P:Post C:Color L:Palette G:Ramp W:Lin/Gamma Esc:Back"
                         }
                     },
                     KeyboardFocus::PosterizeMenu => "E:Toggle ARROWS:Levels Esc:Back",
-                    KeyboardFocus::BayerSizeMenu => "2,3,4,8:Size  Esc:Back",
+                    KeyboardFocus::BayerSizeMenu => "2,3,4,8:Size 5:16 6:32 7:64  Q:Matrix Type  I:Import  Esc:Back",
+                    KeyboardFocus::NoiseSizeMenu => "1:16 2:32 3:64 4:128 5:256  Esc:Back",
+                    KeyboardFocus::PaletteMenu => "E:Toggle  B:Built-in  I:Import  V:Paste  O:Export  G:To Ramp  C:Distance    |    X:Extract  M:Method  ARROWS:Count  Esc:Back",
+                    KeyboardFocus::PaletteLibraryMenu => "ARROWS:Prev/Next Built-in  Space:Done  Esc:Back",
+                    KeyboardFocus::KernelMenu => "1:Floyd 2:JJN 3:Stucki 4:Burkes 5:Sierra3 6:Sierra2 7:SierraLite 8:Atkinson  Esc:Back",
                     KeyboardFocus::GradientMapMenu | KeyboardFocus::GradientPointEdit => "Esc:Back  Ctrl+S:Export  0-9:Zoom    |    RTY/FGH: HSB +/-   A/D:Move  Shift:Fine  Space:Done",
                     KeyboardFocus::Editing(_) => "Esc:Back  Ctrl+S:Export  0-9:Zoom    |    WASD/Arrows:Change  Shift:Fast  Space:Ok",
-                    KeyboardFocus::ModeSelection => "Esc:Back  Ctrl+S:Export  0-9:Zoom    |    A:None S:Thres D:Rand F:Bayer G:Blue H:Diff J:Stuck K:Atkin L:Grad C:Latt",
+                    KeyboardFocus::ModeSelection => "Esc:Back  Ctrl+S:Export  0-9:Zoom    |    A:None S:Thres D:Rand F:Bayer G:Blue H:Blend J:Offset K:2Lvl L:Grad C:Latt E:ErrDiff T:Halftone V:Stip",
                     _ => "Esc:Back  Ctrl+S:Export  0-9:Zoom    |    A:Adjust  D:Dither",
                 };
                 ui.label(shortcut_text);
             });
         });
 
+        if self.show_snapshots {
+            egui::SidePanel::right("snapshots").resizable(false).exact_width(THUMB_SIZE as f32 + 40.0).frame(egui::Frame::none().fill(egui::Color32::BLACK).inner_margin(12.0)).show(ctx, |ui| {
+                ui.label("SNAPSHOTS");
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(self.snapshots.len() < MAX_SNAPSHOTS && self.current_image.is_some(), egui::Button::new("[+ New]")).on_hover_text("Keep the current look").clicked() { self.take_snapshot(); }
+                    if ui.add_enabled(self.previewing.is_some(), egui::Button::new("[Current]")).on_hover_text("Back to the look being edited").clicked() { self.render_output(); }
+                });
+                ui.separator();
+                let (mut show, mut promote, mut delete) = (None, None, None);
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, snap) in self.snapshots.iter_mut().enumerate() {
+                        let shown = self.previewing == Some(i);
+                        if let Some((texture, id)) = &snap.thumb {
+                            let size = egui::vec2(texture.width() as f32, texture.height() as f32);
+                            let r = ui.add(egui::ImageButton::new(egui::load::SizedTexture::new(*id, size)).selected(shown)).on_hover_text("Show this look, again for the current one");
+                            if r.clicked() { show = Some(i); }
+                            if shown { ui.painter().rect_stroke(r.rect.expand(2.0), 0.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 255, 0))); }
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{}", i + 1));
+                            ui.add(egui::TextEdit::singleline(&mut snap.name).desired_width(48.0));
+                            if ui.button("[Promote]").on_hover_text("Make this the current look").clicked() { promote = Some(i); }
+                            if ui.button("[x]").clicked() { delete = Some(i); }
+                        });
+                        ui.add_space(6.0);
+                    }
+                });
+                if let Some(i) = show { self.preview_snapshot(i); }
+                if let Some(i) = promote { self.promote_snapshot(i); }
+                if let Some(i) = delete { self.delete_snapshot(i); }
+            });
+        }
+
         egui::SidePanel::left("control_panel").resizable(true).default_width(320.0).frame(egui::Frame::none().fill(egui::Color32::BLACK).inner_margin(12.0)).show(ctx, |ui| {
             ui.heading("VibeDither v0.9"); ui.add_space(8.0);
             ui.vertical(|ui| {
                 ui.horizontal(|ui| {
                     if ui.button("[Load Image]").clicked() { 
                         if let Some(path) = rfd::FileDialog::new()
-                            .add_filter("Images", &["png", "jpg", "jpeg", "webp", "bmp", "tiff", "gif"])
+                            .add_filter("Images", &image_io::SUPPORTED_EXTENSIONS)
                             .pick_file() { 
                             self.load_content(ctx, path); 
                         }
@@ -463,6 +884,15 @@ impl eframe::App for VibeDitherApp {
                     if ui.button("[Paste]").clicked() { if let Some(img) = image_io::get_clipboard_image() { self.load_image_to_gpu(ctx, img); } }
                 });
                 if ui.button("[Export Image]").clicked() { self.show_export_window = true; self.focus = KeyboardFocus::Export; }
+                ui.horizontal(|ui| {
+                    if ui.button("[Save Preset]").clicked() { self.save_preset(); }
+                    if ui.button("[Load Preset]").clicked() { self.load_preset(); }
+                });
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(self.history.can_undo(), egui::Button::new("[Undo]")).on_hover_text("Ctrl+Z").clicked() { self.step_history(false); }
+                    if ui.add_enabled(self.history.can_redo(), egui::Button::new("[Redo]")).on_hover_text("Ctrl+Shift+Z").clicked() { self.step_history(true); }
+                    if ui.selectable_label(self.show_snapshots, "[Snapshots]").clicked() { self.show_snapshots = !self.show_snapshots; }
+                });
                 
                 ui.add_space(10.0);
                 ui.horizontal(|ui| { 
@@ -475,29 +905,34 @@ impl eframe::App for VibeDitherApp {
                 let mut side_changed = false;
                 match self.active_tab {
                     Tab::Adjust => {
+                        ui.horizontal(|ui| {
+                            ui.label("Working Space:");
+                            for (i, name) in pipeline::WORKING_SPACE_NAMES.iter().enumerate() { if ui.selectable_label(self.look.settings.adjust_space as usize == i, *name).on_hover_text("Space exposure, tone sliders and curves operate in").clicked() { self.look.settings.adjust_space = i as f32; side_changed = true; } }
+                        });
+                        ui.add_space(4.0);
                         ui.label("------------ [ Light ] ------------"); ui.add_space(4.0);
                         ui.vertical(|ui| {
-                            side_changed |= ui.add(egui::Slider::new(&mut self.settings.exposure, -5.0..=5.0).text("Exposure")).changed();
-                            side_changed |= ui.add(egui::Slider::new(&mut self.settings.contrast, 0.0..=2.0).text("Contrast")).changed();
-                            side_changed |= ui.add(egui::Slider::new(&mut self.settings.highlights, -1.0..=1.0).text("Highlights")).changed();
-                            side_changed |= ui.add(egui::Slider::new(&mut self.settings.shadows, -1.0..=1.0).text("Shadows")).changed();
-                            side_changed |= ui.add(egui::Slider::new(&mut self.settings.whites, -1.0..=1.0).text("Whites")).changed();
-                            side_changed |= ui.add(egui::Slider::new(&mut self.settings.blacks, -1.0..=1.0).text("Blacks")).changed();
+                            side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.exposure, -5.0..=5.0).text("Exposure")).changed();
+                            side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.contrast, 0.0..=2.0).text("Contrast")).changed();
+                            side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.highlights, -1.0..=1.0).text("Highlights")).changed();
+                            side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.shadows, -1.0..=1.0).text("Shadows")).changed();
+                            side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.whites, -1.0..=1.0).text("Whites")).changed();
+                            side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.blacks, -1.0..=1.0).text("Blacks")).changed();
                         });
                         ui.add_space(4.0);
-                        if ui.button("[Reset]").clicked() { self.settings = ColorSettings::default(); side_changed = true; }
+                        if ui.button("[Reset]").clicked() { self.look.settings = ColorSettings::default(); side_changed = true; }
 
                         ui.add_space(8.0);
                         ui.label("------------ [ Color ] ------------"); ui.add_space(4.0);
                         ui.vertical(|ui| {
-                            side_changed |= ui.add(egui::Slider::new(&mut self.settings.temperature, -1.0..=1.0).text("Temperature")).changed();
-                            side_changed |= ui.add(egui::Slider::new(&mut self.settings.tint, -1.0..=1.0).text("Tint")).changed();
-                            side_changed |= ui.add(egui::Slider::new(&mut self.settings.saturation, 0.0..=2.0).text("Saturation")).changed();
-                            side_changed |= ui.add(egui::Slider::new(&mut self.settings.vibrance, -1.0..=1.0).text("Vibrance")).changed();
-                            side_changed |= ui.add(egui::Slider::new(&mut self.settings.sharpness, 0.0..=2.0).text("Sharpness")).changed();
+                            side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.temperature, -1.0..=1.0).text("Temperature")).changed();
+                            side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.tint, -1.0..=1.0).text("Tint")).changed();
+                            side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.saturation, 0.0..=2.0).text("Saturation")).changed();
+                            side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.vibrance, -1.0..=1.0).text("Vibrance")).changed();
+                            side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.sharpness, 0.0..=2.0).text("Sharpness")).changed();
                         });
                         ui.add_space(4.0);
-                        if ui.button("[Reset]").clicked() { self.settings = ColorSettings::default(); side_changed = true; }
+                        if ui.button("[Reset]").clicked() { self.look.settings = ColorSettings::default(); side_changed = true; }
                         
                         ui.add_space(8.0);
                         ui.label("------------ [ Curves ] -----------"); ui.add_space(4.0);
@@ -519,6 +954,15 @@ impl eframe::App for VibeDitherApp {
                             let size = egui::vec2(ui.available_width(), 160.0);
                             let (rect, response) = ui.allocate_at_least(size, egui::Sense::click_and_drag());
                             ui.painter().rect_stroke(rect, 0.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 255, 0)));
+
+                            // Histogram of the adjusted image behind the grid, for the selected channel.
+                            if let Some([(hist, _), _]) = &self.scopes {
+                                let (bins, peak) = (hist.channel(self.selected_curve_idx), hist.peak() as f32);
+                                for (i, &n) in bins.iter().enumerate().filter(|(_, &n)| n > 0) {
+                                    let (x0, x1) = (rect.left() + rect.width() * i as f32 / 256.0, rect.left() + rect.width() * (i + 1) as f32 / 256.0);
+                                    ui.painter().rect_filled(egui::Rect::from_min_max(egui::pos2(x0, rect.bottom() - rect.height() * (n as f32 / peak).min(1.0)), egui::pos2(x1, rect.bottom())), 0.0, egui::Color32::from_rgba_unmultiplied(0, 255, 0, 28));
+                                }
+                            }
                             
                             // 4x4 grid
                             for i in 1..4 {
@@ -536,10 +980,9 @@ impl eframe::App for VibeDitherApp {
                                 _ => egui::Color32::WHITE,
                             };
 
-                            let lut = spline::interpolate_spline(&self.curve_points[self.selected_curve_idx]);
+                            let lut = spline::interpolate_spline(&self.look.curves[self.selected_curve_idx]);
                             let mut points: Vec<egui::Pos2> = Vec::new();
-                            for i in 0..256 {
-                                let val = lut[i];
+                            for (i, &val) in lut.iter().enumerate() {
                                 let x = rect.left() + (i as f32 / 255.0) * rect.width();
                                 let y = rect.bottom() - (val as f32 / 255.0) * rect.height();
                                 points.push(egui::pos2(x, y));
@@ -549,7 +992,7 @@ impl eframe::App for VibeDitherApp {
                             if let Some(pos) = response.interact_pointer_pos() {
                                 let x_norm = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
                                 let y_norm = ((rect.bottom() - pos.y) / rect.height()).clamp(0.0, 1.0);
-                                let current_points = &mut self.curve_points[self.selected_curve_idx];
+                                let current_points = &mut self.look.curves[self.selected_curve_idx];
                                 
                                 if response.drag_started() {
                                     let mut closest_idx = None; let mut min_dist = 0.05;
@@ -563,78 +1006,210 @@ impl eframe::App for VibeDitherApp {
                                         if idx > 0 { min_x = current_points[idx-1].x + 0.001; }
                                         if idx < current_points.len() - 1 { max_x = current_points[idx+1].x - 0.001; }
                                         
-                                        current_points[idx] = egui::pos2(x_norm.clamp(min_x, max_x), y_norm);
+                                        current_points[idx] = CurvePoint::new(x_norm.clamp(min_x, max_x), y_norm);
                                         curves_changed = true;
                                     }
                                 }
                                 if response.drag_stopped() { self.dragging_point_idx = None; }
                                 if response.clicked() && !response.dragged() {
                                     let mut exists = false; for p in current_points.iter() { if (p.x - x_norm).abs() < 0.02 { exists = true; break; } }
-                                    if !exists { current_points.push(egui::pos2(x_norm, y_norm)); current_points.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap()); curves_changed = true; }
+                                    if !exists { current_points.push(CurvePoint::new(x_norm, y_norm)); current_points.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap()); curves_changed = true; }
                                 }
                             }
                             if response.secondary_clicked() {
                                 if let Some(pos) = response.interact_pointer_pos() {
                                     let x_norm = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
                                     let mut to_remove = None;
-                                    let current_points = &mut self.curve_points[self.selected_curve_idx];
+                                    let current_points = &mut self.look.curves[self.selected_curve_idx];
                                     for (idx, p) in current_points.iter().enumerate() { if (p.x - x_norm).abs() < 0.03 && idx != 0 && idx != current_points.len() - 1 { to_remove = Some(idx); break; } }
                                     if let Some(idx) = to_remove { current_points.remove(idx); curves_changed = true; }
                                 }
                             }
-                            for p in &self.curve_points[self.selected_curve_idx] { let px = rect.left() + p.x * rect.width(); let py = rect.bottom() - p.y * rect.height(); ui.painter().circle_filled(egui::pos2(px, py), 3.0, egui::Color32::WHITE); }
+                            for p in &self.look.curves[self.selected_curve_idx] { let px = rect.left() + p.x * rect.width(); let py = rect.bottom() - p.y * rect.height(); ui.painter().circle_filled(egui::pos2(px, py), 3.0, egui::Color32::WHITE); }
                             ui.label("└─────────────────────────────────┘");
-                            if ui.button("[Reset Curves]").clicked() { self.curve_points[self.selected_curve_idx] = vec![egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)]; curves_changed = true; }
+                            if ui.button("[Reset Curves]").clicked() { self.look.curves[self.selected_curve_idx] = spline::identity_curve(); curves_changed = true; }
+
+                            ui.add_space(8.0);
+                            ui.horizontal(|ui| {
+                                ui.label("SCOPES");
+                                ui.selectable_value(&mut self.scope_output, false, "Adjusted").on_hover_text("Before the dither stage");
+                                ui.selectable_value(&mut self.scope_output, true, "Output");
+                                ui.label("|");
+                                ui.selectable_value(&mut self.show_waveform, false, "Histogram");
+                                ui.selectable_value(&mut self.show_waveform, true, "Waveform");
+                            });
+                            let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 100.0), egui::Sense::hover());
+                            let green = egui::Color32::from_rgb(0, 255, 0);
+                            ui.painter().rect_stroke(rect, 0.0, egui::Stroke::new(1.0, green));
+                            if let Some(scopes) = &self.scopes {
+                                let (hist, wave) = &scopes[self.scope_output as usize];
+                                let painter = ui.painter_at(rect.shrink(1.0));
+                                if self.show_waveform {
+                                    // Levels bucketed by 4 so each cell is a few screen pixels tall; brighter where more pixels sit.
+                                    let (peak, cw, ch) = (wave.peak() as f32, rect.width() / wave.columns as f32, rect.height() / 64.0);
+                                    for col in 0..wave.columns {
+                                        for bucket in 0..64 {
+                                            let n: u32 = wave.counts[col * 256 + bucket * 4..col * 256 + bucket * 4 + 4].iter().sum();
+                                            if n == 0 { continue; }
+                                            let a = ((n as f32 / peak).sqrt() * 255.0).clamp(40.0, 255.0) as u8;
+                                            painter.rect_filled(egui::Rect::from_min_size(egui::pos2(rect.left() + col as f32 * cw, rect.bottom() - (bucket + 1) as f32 * ch), egui::vec2(cw.max(1.0), ch.max(1.0))), 0.0, egui::Color32::from_rgba_unmultiplied(0, 255, 0, a));
+                                        }
+                                    }
+                                } else {
+                                    // Luminance filled, R, G and B as traces over it.
+                                    let peak = hist.peak() as f32;
+                                    let pos = |i: usize, n: u32| egui::pos2(rect.left() + rect.width() * (i as f32 + 0.5) / 256.0, rect.bottom() - rect.height() * (n as f32 / peak).min(1.0));
+                                    for (i, &n) in hist.luma.iter().enumerate().filter(|(_, &n)| n > 0) { painter.line_segment([egui::pos2(pos(i, n).x, rect.bottom()), pos(i, n)], egui::Stroke::new(rect.width() / 256.0 + 0.5, green.linear_multiply(0.35))); }
+                                    for (c, color) in [egui::Color32::from_rgb(255, 50, 50), egui::Color32::from_rgb(50, 255, 50), egui::Color32::from_rgb(50, 50, 255)].into_iter().enumerate() {
+                                        painter.add(egui::Shape::line(hist.rgb[c].iter().enumerate().map(|(i, &n)| pos(i, n)).collect(), egui::Stroke::new(1.0, color)));
+                                    }
+                                }
+                            } else { ui.painter().text(rect.center(), egui::Align2::CENTER_CENTER, "no image", egui::FontId::monospace(12.0), green); }
                         });
                         if curves_changed {
-                            let master_lut = spline::interpolate_spline(&self.curve_points[0]);
-                            let r_lut = spline::interpolate_spline(&self.curve_points[1]);
-                            let g_lut = spline::interpolate_spline(&self.curve_points[2]);
-                            let b_lut = spline::interpolate_spline(&self.curve_points[3]);
-                            
-                            for i in 0..256 {
-                                // Red = Master(RedCurve(i))
-                                let r_val = r_lut[i];
-                                self.curves_data[i * 4] = master_lut[r_val as usize];
-                                
-                                let g_val = g_lut[i];
-                                self.curves_data[i * 4 + 1] = master_lut[g_val as usize];
-                                
-                                let b_val = b_lut[i];
-                                self.curves_data[i * 4 + 2] = master_lut[b_val as usize];
-                            }
-                            
+                            // Red = Master(RedCurve(i)), same for G and B
+                            self.curves_data = spline::build_curves_lut(&self.look.curves);
                             if let Some(queue) = &self.queue { self.pipeline.update_curves(queue, &self.curves_data); }
                             side_changed = true;
                         }
                     },
                     Tab::Dither => {
-                        let d_type = self.settings.dither_type as usize;
-                        let d_names = ["None", "Threshold", "Random", "Bayer", "Blue Noise", "Diffusion Approx", "Stucki", "Atkinson", "Gradient Based", "Lattice Boltzmann"];
+                        let d_type = self.look.settings.dither_type as usize;
+                        let d_names = pipeline::DITHER_NAMES;
                         
                         ui.label("Dithering Algorithm [↓]");
                         egui::ComboBox::from_id_source("algo_combo").selected_text(format!("└ {}", d_names[d_type.min(d_names.len() - 1)])).show_ui(ui, |ui| {
-                            for (i, name) in d_names.iter().enumerate() { if ui.selectable_label(d_type == i, *name).clicked() { self.settings.dither_type = i as f32; self.settings.dither_enabled = if i > 0 { 1.0 } else { 0.0 }; side_changed = true; } }
+                            for (i, name) in d_names.iter().enumerate() { if ui.selectable_label(d_type == i, *name).clicked() { self.look.settings.dither_type = i as f32; self.look.settings.dither_enabled = if i > 0 { 1.0 } else { 0.0 }; side_changed = true; } }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Dither Space:");
+                            for (i, name) in pipeline::WORKING_SPACE_NAMES.iter().enumerate() { if ui.selectable_label(self.look.settings.dither_space as usize == i, *name).on_hover_text("Space thresholds, levels and palette mixes are measured in").clicked() { self.look.settings.dither_space = i as f32; side_changed = true; } }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Alpha:");
+                            let mut cut = self.look.settings.alpha_dither > 0.5;
+                            if ui.checkbox(&mut cut, "Dither").on_hover_text("Cut alpha to fully opaque or clear with the same pattern, for 1-bit sprites").changed() { self.look.settings.alpha_dither = if cut { 1.0 } else { 0.0 }; side_changed = true; }
+                            let mut pre = self.look.settings.alpha_premultiply > 0.5;
+                            if ui.checkbox(&mut pre, "Premultiply").on_hover_text("Write color multiplied by alpha instead of straight").changed() { self.look.settings.alpha_premultiply = if pre { 1.0 } else { 0.0 }; side_changed = true; }
                         });
                         ui.add_space(8.0);
 
                         ui.add_enabled_ui(d_type > 0, |ui| {
-                            let mut scale_int = self.settings.dither_scale as i32; if ui.add(egui::Slider::new(&mut scale_int, 1..=32).text("Pixel Scale")).changed() { self.settings.dither_scale = scale_int as f32; side_changed = true; }
+                            let mut scale_int = self.look.settings.dither_scale as i32; if ui.add(egui::Slider::new(&mut scale_int, 1..=32).text("Pixel Scale")).changed() { self.look.settings.dither_scale = scale_int as f32; side_changed = true; }
                             
-                            if d_type == 1 { 
-                                side_changed |= ui.add(egui::Slider::new(&mut self.settings.dither_threshold, 0.0..=1.0).text("Threshold")).changed(); 
+                            if d_type == 1 || d_type == pipeline::DITHER_ERROR_DIFFUSION { 
+                                side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.dither_threshold, 0.0..=1.0).text("Threshold")).changed(); 
                             }
 
                             ui.add_space(6.0);
                             ui.label("---------- [ Posterize ] ----------");
-                            let mut use_p = self.settings.posterize_levels > 0.0;
-                            if ui.checkbox(&mut use_p, "Enable").changed() { self.settings.posterize_levels = if use_p { 4.0 } else { 0.0 }; side_changed = true; }
-                            ui.add_enabled_ui(use_p, |ui| { side_changed |= ui.add(egui::Slider::new(&mut self.settings.posterize_levels, 2.0..=64.0).text("Levels")).changed(); });
+                            let mut use_p = self.look.settings.posterize_levels > 0.0;
+                            if ui.checkbox(&mut use_p, "Enable").changed() { self.look.settings.posterize_levels = if use_p { 4.0 } else { 0.0 }; side_changed = true; }
+                            ui.add_enabled_ui(use_p, |ui| { side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.posterize_levels, 2.0..=64.0).text("Levels")).changed(); });
+                            ui.label("-----------------------------------");
+
+                            ui.add_space(6.0);
+                            ui.label("---------- [ Palette ] ------------");
+                            let mut use_pal = self.look.settings.palette_enabled > 0.5;
+                            if ui.checkbox(&mut use_pal, "Dither to Palette").changed() { self.look.settings.palette_enabled = if use_pal { 1.0 } else { 0.0 }; side_changed = true; }
+                            ui.add_enabled_ui(use_pal, |ui| {
+                                let mut pal_ch = false;
+                                let current = self.look.palette_name.as_deref().and_then(palette::builtin::find).map_or("Custom", |b| b.name);
+                                let mut picked = None;
+                                egui::ComboBox::from_id_source("palette_combo").selected_text(format!("└ {}", current)).show_ui(ui, |ui| {
+                                    for b in &palette::builtin::BUILTINS { if ui.selectable_label(self.look.palette_name.as_deref() == Some(b.id), format!("{} ({})", b.name, b.colors.len())).clicked() { picked = Some(b.id); } }
+                                });
+                                if let Some(id) = picked { self.set_builtin_palette(id); }
+                                let metric = (self.look.settings.color_metric as usize).min(pipeline::COLOR_METRIC_NAMES.len() - 1);
+                                egui::ComboBox::from_id_source("metric_combo").selected_text(format!("Distance: {}", pipeline::COLOR_METRIC_NAMES[metric])).show_ui(ui, |ui| {
+                                    for (i, name) in pipeline::COLOR_METRIC_NAMES.iter().enumerate() { if ui.selectable_label(metric == i, *name).clicked() { self.look.settings.color_metric = i as f32; side_changed = true; } }
+                                });
+                                self.palette_locked.resize(self.look.palette.len(), false);
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.spacing_mut().item_spacing = egui::vec2(2.0, 2.0);
+                                    for (c, locked) in self.look.palette.iter_mut().zip(self.palette_locked.iter_mut()) {
+                                        let r = ui.color_edit_button_srgb(c).on_hover_text("Right-click to lock for extraction");
+                                        pal_ch |= r.changed();
+                                        if r.secondary_clicked() { *locked = !*locked; }
+                                        if *locked { ui.painter().rect_stroke(r.rect.expand(1.0), 0.0, egui::Stroke::new(1.5, egui::Color32::from_rgb(0, 255, 0))); }
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    if ui.add_enabled(self.look.palette.len() < palette::MAX_COLORS, egui::Button::new("[ + ]")).clicked() { let last = self.look.palette.last().copied().unwrap_or([160, 160, 160]); self.look.palette.push(last); pal_ch = true; }
+                                    if ui.add_enabled(self.look.palette.len() > 1, egui::Button::new("[ - ]")).clicked() { self.look.palette.pop(); pal_ch = true; }
+                                    ui.label(format!("{} colors", self.look.palette.len()));
+                                });
+                                ui.horizontal(|ui| {
+                                    if ui.button("[Import]").clicked() { self.import_palette(); }
+                                    if ui.button("[Paste]").clicked() { self.paste_palette(); }
+                                    if ui.button("[Export]").clicked() { self.export_palette(); }
+                                    if ui.button("[To Ramp]").on_hover_text("Replace the gradient remap stops with the palette, darkest to lightest").clicked() { self.palette_to_gradient(); }
+                                });
+                                ui.horizontal(|ui| {
+                                    egui::ComboBox::from_id_source("extract_combo").selected_text(self.extract_method.name()).show_ui(ui, |ui| {
+                                        for m in extract::Method::ALL { ui.selectable_value(&mut self.extract_method, m, m.name()); }
+                                    });
+                                    ui.add(egui::DragValue::new(&mut self.extract_count).clamp_range(2..=palette::MAX_COLORS).suffix(" colors"));
+                                    if ui.add_enabled(self.current_image.is_some(), egui::Button::new("[Extract]")).on_hover_text("Pick colors from the image; locked swatches are kept").clicked() { self.extract_palette(); }
+                                });
+                                ui.checkbox(&mut self.extract_adjusted, "Extract from adjusted image");
+                                if pal_ch { self.look.palette_name = None; self.palette_data = self.look.palette_lut(); if let Some(q) = &self.queue { self.pipeline.update_palette(q, &self.palette_data); } side_changed = true; }
+                            });
                             ui.label("-----------------------------------");
+
+                            if d_type == pipeline::DITHER_ERROR_DIFFUSION {
+                                ui.add_space(6.0);
+                                ui.label("---------- [ Diffusion ] ----------");
+                                ui.horizontal_wrapped(|ui| {
+                                    for (i, k) in diffusion::KERNELS.iter().enumerate() { if ui.selectable_label(self.look.settings.diffusion_kernel as usize == i, k.name).clicked() { self.look.settings.diffusion_kernel = i as f32; side_changed = true; } }
+                                });
+                                let mut serp = self.look.settings.diffusion_serpentine > 0.5;
+                                if ui.checkbox(&mut serp, "Serpentine Scan").changed() { self.look.settings.diffusion_serpentine = if serp { 1.0 } else { 0.0 }; side_changed = true; }
+                                side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.diffusion_strength, 0.0..=1.0).text("Strength").custom_formatter(|v, _| format!("{:.0}%", v * 100.0))).changed();
+                                let mut use_clamp = self.look.settings.diffusion_clamp > 0.0;
+                                if ui.checkbox(&mut use_clamp, "Clamp Error").changed() { self.look.settings.diffusion_clamp = if use_clamp { 0.5 } else { 0.0 }; side_changed = true; }
+                                ui.add_enabled_ui(use_clamp, |ui| { side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.diffusion_clamp, 0.05..=1.0).text("Max Error")).changed(); });
+                                ui.label("-----------------------------------");
+                            }
+
+                            if d_type == pipeline::DITHER_STIPPLING {
+                                ui.add_space(6.0);
+                                ui.label("---------- [ Stippling ] ----------");
+                                let mut points = self.look.settings.stipple_points as u32;
+                                if ui.add(egui::Slider::new(&mut points, stipple::MIN_POINTS as u32..=stipple::MAX_POINTS as u32).logarithmic(true).text("Points")).changed() { self.look.settings.stipple_points = points as f32; side_changed = true; }
+                                let mut iterations = self.look.settings.stipple_iterations as u32;
+                                if ui.add(egui::Slider::new(&mut iterations, 0..=stipple::MAX_ITERATIONS as u32).text("Iterations")).changed() { self.look.settings.stipple_iterations = iterations as f32; side_changed = true; }
+                                let max = self.look.settings.stipple_max_size;
+                                side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.stipple_min_size, 0.0..=max).text("Dot Size Min")).changed();
+                                let min = self.look.settings.stipple_min_size;
+                                side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.stipple_max_size, min..=1.0).text("Dot Size Max")).changed();
+                                ui.label("-----------------------------------");
+                            }
+
+                            if d_type == pipeline::DITHER_HALFTONE {
+                                ui.add_space(6.0);
+                                ui.label("---------- [ Halftone ] -----------");
+                                side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.halftone_frequency, halftone::MIN_FREQUENCY..=halftone::MAX_FREQUENCY).logarithmic(true).text("Frequency (cells)")).changed();
+                                side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.halftone_angle, 0.0..=180.0).text("Angle").suffix("°")).changed();
+                                ui.horizontal_wrapped(|ui| {
+                                    for (i, name) in halftone::SHAPE_NAMES.iter().enumerate() { if ui.selectable_label(self.look.settings.halftone_shape as usize == i, *name).clicked() { self.look.settings.halftone_shape = i as f32; side_changed = true; } }
+                                });
+                                let mut cmyk = self.look.settings.halftone_cmyk > 0.5;
+                                if ui.checkbox(&mut cmyk, "CMYK Separation").changed() { self.look.settings.halftone_cmyk = if cmyk { 1.0 } else { 0.0 }; side_changed = true; }
+                                ui.add_enabled_ui(cmyk, |ui| {
+                                    side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.cmyk_black, 0.0..=1.0).text("Black (GCR)").custom_formatter(|v, _| format!("{:.0}%", v * 100.0))).changed();
+                                    side_changed |= ui.add(egui::Slider::new(&mut self.look.settings.cmyk_black_start, 0.0..=0.95).text("Black Start (UCR)")).changed();
+                                    ui.horizontal_wrapped(|ui| {
+                                        if ui.selectable_label(self.look.settings.cmyk_plate < 0.5, "Composite").clicked() { self.look.settings.cmyk_plate = 0.0; side_changed = true; }
+                                        for (i, name) in pipeline::CMYK_PLATES.iter().enumerate() { if ui.selectable_label(self.look.settings.cmyk_plate as usize == i + 1, *name).clicked() { self.look.settings.cmyk_plate = (i + 1) as f32; side_changed = true; } }
+                                    });
+                                });
+                                ui.label("-----------------------------------");
+                            }
                             
                             ui.add_space(10.0);
-                            let mut grad_e = self.settings.grad_enabled > 0.5;
-                            if ui.checkbox(&mut grad_e, "Gradient Remap").changed() { self.settings.grad_enabled = if grad_e { 1.0 } else { 0.0 }; side_changed = true; }
+                            let mut grad_e = self.look.settings.grad_enabled > 0.5;
+                            if ui.checkbox(&mut grad_e, "Gradient Remap").changed() { self.look.settings.grad_enabled = if grad_e { 1.0 } else { 0.0 }; side_changed = true; }
                             
                             ui.add_enabled_ui(grad_e, |ui| {
                                 let mut stops_ch = false;
@@ -643,14 +1218,14 @@ impl eframe::App for VibeDitherApp {
                                     for i in 0..255 {
                                         let x0 = ramp_r.left() + (i as f32 / 255.0) * ramp_r.width();
                                         let x1 = ramp_r.left() + ((i + 1) as f32 / 255.0) * ramp_r.width();
-                                        let c = egui::Color32::from_rgba_unmultiplied(self.gradient_data[i * 4], self.gradient_data[i * 4 + 1], self.gradient_data[i * 4 + 2], 255);
+                                        let t = self.gradient_data[i * (pipeline::LUT_SIZE - 1) / 255]; let c = egui::Color32::from_rgb((t[0] * 255.0) as u8, (t[1] * 255.0) as u8, (t[2] * 255.0) as u8);
                                         ui.painter().rect_filled(egui::Rect::from_min_max(egui::pos2(x0, ramp_r.top()), egui::pos2(x1, ramp_r.bottom())), 0.0, c);
                                     }
                                     ui.painter().rect_stroke(ramp_r, 0.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 255, 0)));
                                     
                                     let mut active_id = self.selected_stop_id;
                                     let mut dragged_id = None; let mut new_pos = 0.0;
-                                    for stop in &self.gradient_stops {
+                                    for stop in &self.look.gradient {
                                         let x = ramp_r.left() + stop.pos * ramp_r.width();
                                         let is_sel = Some(stop.id) == active_id;
                                         ui.painter().line_segment([egui::pos2(x, ramp_r.top()), egui::pos2(x, ramp_r.bottom())], egui::Stroke::new(2.0, if is_sel { egui::Color32::WHITE } else { egui::Color32::from_rgba_unmultiplied(0, 255, 0, 180) }));
@@ -658,16 +1233,16 @@ impl eframe::App for VibeDitherApp {
                                         if h_res.clicked() { active_id = Some(stop.id); }
                                         if h_res.dragged() { active_id = Some(stop.id); new_pos = (stop.pos + h_res.drag_delta().x / ramp_r.width()).clamp(0.0, 1.0); dragged_id = Some(stop.id); }
                                     }
-                                    if let Some(id) = dragged_id { if let Some(s) = self.gradient_stops.iter_mut().find(|s| s.id == id) { s.pos = new_pos; stops_ch = true; } }
+                                    if let Some(id) = dragged_id { if let Some(s) = self.look.gradient.iter_mut().find(|s| s.id == id) { s.pos = new_pos; stops_ch = true; } }
                                     self.selected_stop_id = active_id;
 
                                     ui.horizontal(|ui| {
-                                        if ui.button("[ + ]").clicked() { let nid = self.next_stop_id; self.next_stop_id += 1; self.gradient_stops.push(GradientStop { id: nid, pos: 0.5, color: egui::Color32::GRAY }); self.selected_stop_id = Some(nid); stops_ch = true; }
-                                        if ui.button("[ - ]").clicked() { if let Some(id) = self.selected_stop_id { if self.gradient_stops.len() > 2 { self.gradient_stops.retain(|s| s.id != id); self.selected_stop_id = self.gradient_stops.first().map(|s| s.id); stops_ch = true; } } }
+                                        if ui.button("[ + ]").clicked() { let nid = self.next_stop_id; self.next_stop_id += 1; self.look.gradient.push(GradientStop::new(nid, 0.5, [160, 160, 160])); self.selected_stop_id = Some(nid); stops_ch = true; }
+                                        if ui.button("[ - ]").clicked() { if let Some(id) = self.selected_stop_id { if self.look.gradient.len() > 2 { self.look.gradient.retain(|s| s.id != id); self.selected_stop_id = self.look.gradient.first().map(|s| s.id); stops_ch = true; } } }
                                         ui.label("|");
                                         if let Some(id) = self.selected_stop_id {
-                                            if let Some(stop) = self.gradient_stops.iter_mut().find(|s| s.id == id) {
-                                                if ui.color_edit_button_srgba(&mut stop.color).changed() {
+                                            if let Some(stop) = self.look.gradient.iter_mut().find(|s| s.id == id) {
+                                                if ui.color_edit_button_srgb(&mut stop.color).changed() {
                                                     stops_ch = true;
                                                 }
                                                 ui.label(format!("[{:.4}]", stop.pos));
@@ -675,33 +1250,49 @@ impl eframe::App for VibeDitherApp {
                                         }
                                     });
                                     if let Some(id) = self.selected_stop_id {
-                                        if let Some(stop) = self.gradient_stops.iter().find(|s| s.id == id) {
+                                        if let Some(stop) = self.look.gradient.iter().find(|s| s.id == id) {
                                             ui.add_space(4.0);
-                                            ui.label(format!("[ Copy ]  R [{}] G [{}] B [{}]", stop.color.r(), stop.color.g(), stop.color.b()));
+                                            ui.label(format!("[ Copy ]  R [{}] G [{}] B [{}]", stop.color[0], stop.color[1], stop.color[2]));
                                         }
                                     }
                                 });
-                                if stops_ch { self.gradient_stops.sort_by(|a, b| a.pos.partial_cmp(&b.pos).unwrap()); Self::generate_gradient_data(&self.gradient_stops, &mut self.gradient_data); if let Some(q) = &self.queue { self.pipeline.update_gradient(q, &self.gradient_data); } side_changed = true; }
+                                if stops_ch { self.look.gradient.sort_by(|a, b| a.pos.partial_cmp(&b.pos).unwrap()); gradient::generate_gradient_data(&self.look.gradient, &mut self.gradient_data); if let Some(q) = &self.queue { self.pipeline.update_gradient(q, &self.gradient_data); } side_changed = true; }
                             });
                             
-                            if d_type == 3 { 
+                            if d_type == 4 {
                                 ui.add_space(8.0);
-                                ui.label("Matrix Size:"); 
+                                ui.label("Noise Size:");
                                 ui.horizontal(|ui| {
-                                    let sizes = [2, 3, 4, 8]; 
-                                    for s in sizes { if ui.selectable_label(self.settings.bayer_size as i32 == s, format!("{}x{}", s, s)).clicked() { self.settings.bayer_size = s as f32; side_changed = true; } }
+                                    for s in blue_noise::SIZES { if ui.selectable_label(self.look.settings.blue_noise_size as u32 == s, format!("{}x{}", s, s)).clicked() { self.look.settings.blue_noise_size = s as f32; side_changed = true; } }
                                 });
                             }
 
-                            if d_type >= 1 { let mut color_d = self.settings.dither_color > 0.5; if ui.checkbox(&mut color_d, "Color Dithering").changed() { self.settings.dither_color = if color_d { 1.0 } else { 0.0 }; side_changed = true; } }
+                            if d_type == 3 { 
+                                ui.add_space(8.0);
+                                ui.label("Matrix:");
+                                ui.horizontal_wrapped(|ui| {
+                                    for (i, name) in matrix::MATRIX_NAMES.iter().enumerate() { if ui.selectable_label(self.look.settings.ordered_matrix as usize == i, *name).clicked() { self.look.settings.ordered_matrix = i as f32; side_changed = true; } }
+                                });
+                                if self.look.settings.ordered_matrix as usize == matrix::CUSTOM {
+                                    ui.horizontal(|ui| {
+                                        if ui.button("[Import Matrix]").clicked() { self.import_matrix(); }
+                                        ui.label(match &self.look.custom_matrix { Some(m) => format!("{}x{}", m.width, m.height), None => "none, using Bayer".to_string() });
+                                    });
+                                } else {
+                                    ui.label("Matrix Size:"); 
+                                    ui.horizontal_wrapped(|ui| {
+                                        for s in matrix::SIZES { if ui.selectable_label(self.look.settings.bayer_size as u32 == s, format!("{}x{}", s, s)).clicked() { self.look.settings.bayer_size = s as f32; side_changed = true; } }
+                                    });
+                                }
+                            }
+
+                            if d_type >= 1 { let mut color_d = self.look.settings.dither_color > 0.5; if ui.checkbox(&mut color_d, "Color Dithering").changed() { self.look.settings.dither_color = if color_d { 1.0 } else { 0.0 }; side_changed = true; } }
                         });
                     },
                 }
 
                 if side_changed || changed {
-                    if let (Some(device), Some(queue), Some(input), Some(output)) = (&self.device, &self.queue, &self.input_texture, &self.output_texture) {
-                        self.pipeline.render(device, queue, &input.create_view(&wgpu::TextureViewDescriptor::default()), &output.create_view(&wgpu::TextureViewDescriptor::default()), &self.settings);
-                    }
+                    self.render_output();
                 }
             });
         });
@@ -722,24 +1313,112 @@ impl eframe::App for VibeDitherApp {
                     });
                 
                 if ui.button("[Fit]").clicked() { self.fit_to_screen = true; self.pan_offset = egui::Vec2::ZERO; }
+                ui.toggle_value(&mut self.inspector, "[Inspect]").on_hover_text("Show the values at the pixel under the pointer. Click picks its color into the selected gradient stop, Shift+Click adds a curve point at its level");
                 if ui.button("[100%]").clicked() { self.zoom_factor = 1.0; self.fit_to_screen = false; self.pan_offset = egui::Vec2::ZERO; }
+                ui.separator(); ui.label("Compare");
+                for (mode, name) in [(Compare::Off, "Off"), (Compare::Split, "Split"), (Compare::SideBySide, "Side by Side")] { ui.selectable_value(&mut self.compare, mode, name); }
+                if self.compare == Compare::Split && ui.button(if self.split_vertical { "[| Vertical]" } else { "[- Horizontal]" }).clicked() { self.split_vertical = !self.split_vertical; }
+                if self.noise_pending { ui.separator(); ui.spinner(); ui.label("Generating blue noise..."); }
+                else if self.cpu_pending.is_some() || self.cpu_worker.is_some() { ui.separator(); ui.spinner(); ui.label("Rendering..."); }
             });
         });
 
+        if !self.inspector { self.probe_source = None; } else if self.probe_source.is_none() { self.probe_source = self.current_image.as_ref().map(pipeline::cpu::SourceImage::from_image); }
+        let mut picked = None;
         egui::CentralPanel::default().frame(egui::Frame::none().fill(egui::Color32::BLACK)).show(ctx, |ui| {
             if let Some(tex_id) = self.egui_texture_id {
                 let img_size = self.current_image.as_ref().map(|img| egui::vec2(img.width() as f32, img.height() as f32)).unwrap_or(egui::Vec2::ZERO);
                 let (rect, resp) = ui.allocate_at_least(ui.available_size(), egui::Sense::click_and_drag());
+                let original = self.original_texture.as_ref().map_or(tex_id, |t| t.1);
+                let show_original = !ctx.wants_keyboard_input() && ui.input(|i| i.key_down(egui::Key::Backslash));
+                // Side by side, both halves share zoom and pan: the original left, the output right.
+                let views = match self.compare {
+                    Compare::SideBySide => vec![(egui::Rect::from_min_max(rect.min, egui::pos2(rect.center().x, rect.max.y)), original, "ORIGINAL"), (egui::Rect::from_min_max(egui::pos2(rect.center().x, rect.min.y), rect.max), tex_id, "OUTPUT")],
+                    _ => vec![(rect, if show_original { original } else { tex_id }, if show_original { "ORIGINAL" } else { "" })],
+                };
+                let view_at = |p: egui::Pos2| views.iter().map(|v| v.0).find(|r| r.contains(p)).unwrap_or(rect);
                 let scroll = ui.input(|i| i.smooth_scroll_delta.y);
                 if scroll != 0.0 {
                     let old_z = self.zoom_factor; self.zoom_factor = (self.zoom_factor * (1.0 + scroll * 0.002)).clamp(0.1, 32.0); self.fit_to_screen = false;
-                    if let Some(m_pos) = ui.input(|i| i.pointer.hover_pos()) { let rel = m_pos - rect.center() - self.pan_offset; self.pan_offset -= rel * (self.zoom_factor / old_z - 1.0); }
+                    if let Some(m_pos) = ui.input(|i| i.pointer.hover_pos()) { let rel = m_pos - view_at(m_pos).center() - self.pan_offset; self.pan_offset -= rel * (self.zoom_factor / old_z - 1.0); }
+                }
+                let place = |view: egui::Rect, pan: egui::Vec2| {
+                    let d_size = if self.fit_to_screen { let r = (view.width() / img_size.x).min(view.height() / img_size.y); img_size * r } else { img_size * self.zoom_factor };
+                    egui::Rect::from_center_size(view.center() + pan, d_size)
+                };
+                // Screen position of the split line through an image placed at `img_rect`.
+                let vertical = self.split_vertical;
+                let split_at = |img_rect: egui::Rect, pos: f32| if vertical { img_rect.min.x + pos * img_rect.width() } else { img_rect.min.y + pos * img_rect.height() };
+                let splitting = self.compare == Compare::Split && !show_original;
+                let on_line = |p: egui::Pos2, line: f32| splitting && (if vertical { p.x } else { p.y } - line).abs() < 6.0;
+                if resp.drag_started() { self.dragging_split = resp.interact_pointer_pos().is_some_and(|p| on_line(p, split_at(place(rect, self.pan_offset), self.split_pos))); }
+                if resp.dragged_by(egui::PointerButton::Primary) {
+                    if self.dragging_split {
+                        if let Some(p) = resp.interact_pointer_pos() { let img_rect = place(rect, self.pan_offset); self.split_pos = if self.split_vertical { (p.x - img_rect.min.x) / img_rect.width() } else { (p.y - img_rect.min.y) / img_rect.height() }.clamp(0.0, 1.0); }
+                    } else { self.pan_offset += resp.drag_delta(); }
+                }
+                if resp.drag_stopped() { self.dragging_split = false; }
+
+                let has_alpha = self.current_image.as_ref().is_some_and(|img| img.color().has_alpha());
+                let paint = |painter: &egui::Painter, img_rect: egui::Rect, tex: egui::TextureId| {
+                    if has_alpha {
+                        // Checkerboard behind transparent sources, only over the visible part.
+                        let (visible, tile) = (img_rect.intersect(painter.clip_rect()), 12.0);
+                        let (x0, y0) = (((visible.min.x - img_rect.min.x) / tile).floor() as i32, ((visible.min.y - img_rect.min.y) / tile).floor() as i32);
+                        let (x1, y1) = (((visible.max.x - img_rect.min.x) / tile).ceil() as i32, ((visible.max.y - img_rect.min.y) / tile).ceil() as i32);
+                        painter.rect_filled(visible, 0.0, egui::Color32::from_gray(40));
+                        for ty in y0..y1 { for tx in (x0..x1).filter(|tx| (tx + ty) % 2 == 0) { painter.rect_filled(egui::Rect::from_min_size(img_rect.min + egui::vec2(tx as f32, ty as f32) * tile, egui::vec2(tile, tile)).intersect(visible), 0.0, egui::Color32::from_gray(70)); } }
+                    }
+                    painter.image(tex, img_rect, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), egui::Color32::WHITE);
+                };
+                let green = egui::Color32::from_rgb(0, 255, 0);
+                let label = |painter: &egui::Painter, pos: egui::Pos2, align: egui::Align2, text: &str| { painter.text(pos, align, text, egui::FontId::monospace(12.0), green); };
+                for &(view, tex, name) in &views {
+                    let (painter, img_rect) = (ui.painter_at(view), place(view, self.pan_offset));
+                    paint(&painter, img_rect, tex);
+                    if !name.is_empty() { label(&painter, view.min + egui::vec2(8.0, 8.0), egui::Align2::LEFT_TOP, name); }
+                    if splitting {
+                        // The original covers the image up to the line: left of it, or above it.
+                        let line = split_at(img_rect, self.split_pos);
+                        let before = if self.split_vertical { egui::Rect::from_min_max(view.min, egui::pos2(line, view.max.y)) } else { egui::Rect::from_min_max(view.min, egui::pos2(view.max.x, line)) };
+                        paint(&ui.painter_at(before.intersect(view)), img_rect, original);
+                        let ends = if self.split_vertical { [egui::pos2(line, view.min.y), egui::pos2(line, view.max.y)] } else { [egui::pos2(view.min.x, line), egui::pos2(view.max.x, line)] };
+                        painter.line_segment(ends, egui::Stroke::new(1.0, green));
+                        label(&painter, view.min + egui::vec2(8.0, 8.0), egui::Align2::LEFT_TOP, "BEFORE");
+                        label(&painter, view.max - egui::vec2(8.0, 8.0), egui::Align2::RIGHT_BOTTOM, "AFTER");
+                    }
+                }
+                if views.len() > 1 { ui.painter_at(rect).line_segment([egui::pos2(rect.center().x, rect.min.y), egui::pos2(rect.center().x, rect.max.y)], egui::Stroke::new(1.0, green)); }
+                let line = split_at(place(rect, self.pan_offset), self.split_pos);
+                if self.dragging_split || resp.hover_pos().is_some_and(|p| on_line(p, line)) { ctx.set_cursor_icon(if vertical { egui::CursorIcon::ResizeHorizontal } else { egui::CursorIcon::ResizeVertical }); }
+                else if let Some(p) = resp.hover_pos().filter(|_| self.inspector) {
+                    // Every view shows the same image, so any of them maps back to a source pixel.
+                    let img_rect = place(view_at(p), self.pan_offset);
+                    let px = ((p - img_rect.min) / img_rect.size() * img_size).floor();
+                    if let Some((probe, dither_space)) = img_rect.contains(p).then(|| self.probe_pixel(px.x as u32, px.y as u32)).flatten() {
+                        ctx.set_cursor_icon(egui::CursorIcon::Crosshair);
+                        let to8 = |c: f32| (pipeline::cpu::linear_to_srgb(c.clamp(0.0, 1.0)) * 255.0 + 0.5) as u8;
+                        let rgb = |c: [f32; 3]| format!("{:3} {:3} {:3}", to8(c[0]), to8(c[1]), to8(c[2]));
+                        let adjusted = pipeline::cpu::from_space(probe.adjusted, dither_space);
+                        let out = pipeline::cpu::encode_srgb8(probe.output);
+                        let text = format!(
+                            "XY       {} {}\nSOURCE   {}  A {:3}\nADJUSTED {}\nLUMA     {:.3}\nCURVE IN {:.3}\nNOISE    {}\nOUTPUT   {:3} {:3} {:3}  A {:3}  #{:02X}{:02X}{:02X}\n{}",
+                            px.x, px.y, rgb([probe.source[0], probe.source[1], probe.source[2]]), (probe.source[3].clamp(0.0, 1.0) * 255.0 + 0.5) as u8,
+                            rgb(adjusted), probe.luminance, probe.curve_input, probe.noise.map_or("—".to_string(), |n| format!("{:.3}", n)),
+                            out[0], out[1], out[2], out[3], out[0], out[1], out[2],
+                            // Picks edit the current look, so a previewed snapshot only reports.
+                            if self.previewing.is_some() { "Snapshot preview" } else { "Click: color → stop  Shift+Click: curve point" });
+                        let painter = ui.painter_at(rect);
+                        let galley = painter.layout_no_wrap(text, egui::FontId::monospace(12.0), green);
+                        let pos = egui::pos2(rect.max.x - galley.size().x - 16.0, rect.min.y + 16.0);
+                        painter.rect_filled(egui::Rect::from_min_size(pos, galley.size()).expand(6.0), 0.0, egui::Color32::from_black_alpha(220));
+                        painter.galley(pos, galley, green);
+                        if resp.clicked() && self.previewing.is_none() { picked = Some((probe, ui.input(|i| i.modifiers.shift))); }
+                    }
                 }
-                if resp.dragged_by(egui::PointerButton::Primary) { self.pan_offset += resp.drag_delta(); }
-                let d_size = if self.fit_to_screen { let r = (rect.width() / img_size.x).min(rect.height() / img_size.y); img_size * r } else { img_size * self.zoom_factor };
-                ui.set_clip_rect(rect); ui.painter().image(tex_id, egui::Rect::from_center_size(rect.center() + self.pan_offset, d_size), egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), egui::Color32::WHITE);
             } else { ui.centered_and_justified(|ui| { ui.label("Drag and drop an image or use 'Load Image'"); }); }
         });
+        if let Some((probe, to_curve)) = picked { self.pick(&probe, to_curve); }
 
         if self.show_export_window {
             let mut close = false;
@@ -750,8 +1429,10 @@ impl eframe::App for VibeDitherApp {
                         let png_btn = ui.selectable_value(&mut self.export_settings.format, ExportFormat::Png, "PNG");
                         let jpg_btn = ui.selectable_value(&mut self.export_settings.format, ExportFormat::Jpg, "JPG");
                         let webp_btn = ui.selectable_value(&mut self.export_settings.format, ExportFormat::Webp, "WEBP");
+                        let svg_btn = ui.selectable_value(&mut self.export_settings.format, ExportFormat::Svg, "SVG");
+                        let tiff_btn = ui.selectable_value(&mut self.export_settings.format, ExportFormat::Tiff, "TIFF");
                         if self.focus == KeyboardFocus::Export && self.export_row == 0 {
-                            let r = match self.export_col { 0 => png_btn.rect, 1 => jpg_btn.rect, _ => webp_btn.rect };
+                            let r = match self.export_col { 0 => png_btn.rect, 1 => jpg_btn.rect, 2 => webp_btn.rect, 3 => svg_btn.rect, _ => tiff_btn.rect };
                             ui.painter().rect_stroke(r.expand(2.0), 0.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 255, 0)));
                         }
                     });
@@ -760,11 +1441,16 @@ impl eframe::App for VibeDitherApp {
                         let q_slider = ui.add(egui::Slider::new(&mut self.export_settings.compression, 0.0..=1.0).text("Quality"));
                         if self.focus == KeyboardFocus::Export && self.export_row == 1 { ui.painter().rect_stroke(q_slider.rect.expand(2.0), 0.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 255, 0))); }
                     } else {
-                        let c_slider = ui.add(egui::Slider::new(&mut self.export_settings.compression, 0.0..=1.0).text("Compression (File Size)"));
+                        let c_slider = ui.add_enabled(self.export_settings.format != ExportFormat::Svg, egui::Slider::new(&mut self.export_settings.compression, 0.0..=1.0).text("Compression (File Size)"));
                         if self.focus == KeyboardFocus::Export && self.export_row == 1 { ui.painter().rect_stroke(c_slider.rect.expand(2.0), 0.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 255, 0))); }
                     }
-                    let t_check = ui.add_enabled(self.export_settings.format != ExportFormat::Jpg, egui::Checkbox::new(&mut self.export_settings.transparency, "Enable Transparency"));
-                    if self.focus == KeyboardFocus::Export && self.export_row == 2 { ui.painter().rect_stroke(t_check.rect.expand(2.0), 0.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 255, 0))); }
+                    ui.horizontal(|ui| {
+                        let t_check = ui.add_enabled(self.export_settings.format.supports_alpha(), egui::Checkbox::new(&mut self.export_settings.transparency, "Enable Transparency"));
+                        let d_check = ui.add_enabled(self.export_settings.format.supports_16bit() && self.look.settings.allows_deep_output(), egui::Checkbox::new(&mut self.export_settings.deep, "16-bit")).on_disabled_hover_text("PNG or TIFF with dithering off");
+                        let flattened = !self.export_settings.transparency || !self.export_settings.format.supports_alpha();
+                        let m_btn = ui.add_enabled_ui(flattened, |ui| { ui.label("Matte"); egui::color_picker::color_edit_button_srgb(ui, &mut self.export_settings.matte).on_hover_text("Background transparent pixels are flattened onto") }).inner;
+                        if self.focus == KeyboardFocus::Export && self.export_row == 2 { let r = match self.export_col { 0 => t_check.rect, 1 => d_check.rect, _ => m_btn.rect }; ui.painter().rect_stroke(r.expand(2.0), 0.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 255, 0))); }
+                    });
                     
                     ui.separator(); ui.horizontal(|ui| { 
                         ui.label("SIZE"); 
@@ -792,19 +1478,51 @@ impl eframe::App for VibeDitherApp {
                         });
                     }
                     ui.separator();
+                    let p_check = ui.add_enabled(self.look.settings.is_cmyk(), egui::Checkbox::new(&mut self.export_settings.cmyk_plates, "CMYK Plates (4 grayscale PNGs)"));
+                    if self.focus == KeyboardFocus::Export && self.export_row == 5 { ui.painter().rect_stroke(p_check.rect.expand(2.0), 0.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 255, 0))); }
+                    ui.separator();
                     ui.horizontal(|ui| { 
                         let c_btn = ui.button("Cancel"); 
                         let e_btn = ui.button("Export"); 
-                        if self.focus == KeyboardFocus::Export && self.export_row == 5 {
+                        if self.focus == KeyboardFocus::Export && self.export_row == 6 {
                             let r = if self.export_col == 0 { c_btn.rect } else { e_btn.rect };
                             ui.painter().rect_stroke(r.expand(2.0), 0.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 255, 0)));
                         }
-                        if c_btn.clicked() { close = true; } if e_btn.clicked() { self.export_image(); close = true; } 
+                        if c_btn.clicked() { close = true; } else if e_btn.clicked() { self.export_image(); close = true; } 
                     });
                 });
             });
             if close { self.show_export_window = false; self.focus = KeyboardFocus::Main; }
         }
+
+        // Renders held back for a blue-noise map catch up once it is generated.
+        if self.noise_pending {
+            if self.look.threshold_map_ready() && self.snapshots.iter().all(|s| s.look.threshold_map_ready()) {
+                self.noise_pending = false;
+                self.render_output();
+                for i in 0..self.snapshots.len() { self.render_thumbnail(i); }
+            } else { ctx.request_repaint_after(std::time::Duration::from_millis(100)); }
+        }
+
+        // A CPU pass starts once edits pause, one at a time; the newest queued one follows when it is done.
+        match self.cpu_worker.as_ref().map(|rx| rx.try_recv()) {
+            Some(Ok(out)) => { self.cpu_worker = None; self.show_cpu_output(out); }
+            Some(Err(std::sync::mpsc::TryRecvError::Disconnected)) => self.cpu_worker = None,
+            _ => {}
+        }
+        let settled = ctx.input(|i| !i.pointer.any_down() && i.keys_down.is_empty());
+        if let Some(wait) = self.cpu_pending.as_ref().filter(|_| settled && self.cpu_worker.is_none()).map(|p| CPU_PASS_DELAY.saturating_sub(p.requested.elapsed())) {
+            if wait.is_zero() { self.start_cpu_pass(ctx); } else { ctx.request_repaint_after(wait); }
+        }
+
+        // Scopes follow edits at most ten times a second; the end of a drag still gets measured.
+        if self.scopes_dirty && self.active_tab == Tab::Adjust {
+            let now = ctx.input(|i| i.time);
+            if now - self.last_scope_time > 0.1 { self.update_scopes(); self.last_scope_time = now; } else { ctx.request_repaint_after(std::time::Duration::from_millis(100)); }
+        }
+
+        // An edit becomes one undo step once nothing is held, so drags and key repeats coalesce.
+        if settled { self.history.record(&self.look); }
     }
 }
 
@@ -0,0 +1,95 @@
+//! Hardware palettes of classic computers and consoles. Each has a stable id that presets and the
+//! CLI refer to it by, so renaming the display name never breaks a saved look.
+
+/// A built-in palette.
+pub struct Builtin {
+    /// Stable identifier, lowercase with dashes (`pico-8`).
+    pub id: &'static str,
+    pub name: &'static str,
+    pub colors: &'static [[u8; 3]],
+}
+
+const fn rgb(v: u32) -> [u8; 3] {
+    [(v >> 16) as u8, (v >> 8) as u8, v as u8]
+}
+
+pub const BUILTINS: [Builtin; 14] = [
+    Builtin { id: "1-bit", name: "1-Bit", colors: &[rgb(0x000000), rgb(0xffffff)] },
+    // CGA 320x200 four-color modes: palettes 0 and 1 in low and high intensity, and the mode 5 palette.
+    Builtin { id: "cga-0", name: "CGA Mode 4 Palette 0", colors: &[rgb(0x000000), rgb(0x00aa00), rgb(0xaa0000), rgb(0xaa5500)] },
+    Builtin { id: "cga-0-high", name: "CGA Mode 4 Palette 0 (High)", colors: &[rgb(0x000000), rgb(0x55ff55), rgb(0xff5555), rgb(0xffff55)] },
+    Builtin { id: "cga-1", name: "CGA Mode 4 Palette 1", colors: &[rgb(0x000000), rgb(0x00aaaa), rgb(0xaa00aa), rgb(0xaaaaaa)] },
+    Builtin { id: "cga-1-high", name: "CGA Mode 4 Palette 1 (High)", colors: &[rgb(0x000000), rgb(0x55ffff), rgb(0xff55ff), rgb(0xffffff)] },
+    Builtin { id: "cga-5", name: "CGA Mode 5", colors: &[rgb(0x000000), rgb(0x00aaaa), rgb(0xaa0000), rgb(0xaaaaaa)] },
+    // The 16 RGBI colors, EGA's default palette (and CGA's text mode colors).
+    Builtin {
+        id: "ega",
+        name: "EGA",
+        colors: &[
+            rgb(0x000000), rgb(0x0000aa), rgb(0x00aa00), rgb(0x00aaaa), rgb(0xaa0000), rgb(0xaa00aa), rgb(0xaa5500), rgb(0xaaaaaa),
+            rgb(0x555555), rgb(0x5555ff), rgb(0x55ff55), rgb(0x55ffff), rgb(0xff5555), rgb(0xff55ff), rgb(0xffff55), rgb(0xffffff),
+        ],
+    },
+    Builtin { id: "gameboy", name: "Game Boy (DMG)", colors: &[rgb(0x0f380f), rgb(0x306230), rgb(0x8bac0f), rgb(0x9bbc0f)] },
+    // The 2C02's distinct colors as commonly emulated, without the duplicate blacks.
+    Builtin {
+        id: "nes",
+        name: "NES",
+        colors: &[
+            rgb(0x000000), rgb(0x7c7c7c), rgb(0xbcbcbc), rgb(0xf8f8f8), rgb(0xfcfcfc),
+            rgb(0x0000fc), rgb(0x0000bc), rgb(0x4428bc), rgb(0x940084), rgb(0xa80020), rgb(0xa81000), rgb(0x881400), rgb(0x503000), rgb(0x007800), rgb(0x006800), rgb(0x005800), rgb(0x004058),
+            rgb(0x0078f8), rgb(0x0058f8), rgb(0x6844fc), rgb(0xd800cc), rgb(0xe40058), rgb(0xf83800), rgb(0xe45c10), rgb(0xac7c00), rgb(0x00b800), rgb(0x00a800), rgb(0x00a844), rgb(0x008888),
+            rgb(0x3cbcfc), rgb(0x6888fc), rgb(0x9878f8), rgb(0xf878f8), rgb(0xf85898), rgb(0xf87858), rgb(0xfca044), rgb(0xf8b800), rgb(0xb8f818), rgb(0x58d854), rgb(0x58f898), rgb(0x00e8d8), rgb(0x787878),
+            rgb(0xa4e4fc), rgb(0xb8b8f8), rgb(0xd8b8f8), rgb(0xf8b8f8), rgb(0xf8a4c0), rgb(0xf0d0b0), rgb(0xfce0a8), rgb(0xf8d878), rgb(0xd8f878), rgb(0xb8f8b8), rgb(0xb8f8d8), rgb(0x00fcfc), rgb(0xf8d8f8),
+        ],
+    },
+    // Pepto's measured VIC-II colors.
+    Builtin {
+        id: "c64",
+        name: "Commodore 64",
+        colors: &[
+            rgb(0x000000), rgb(0xffffff), rgb(0x68372b), rgb(0x70a4b2), rgb(0x6f3d86), rgb(0x588d43), rgb(0x352879), rgb(0xb8c76f),
+            rgb(0x6f4f25), rgb(0x433900), rgb(0x9a6759), rgb(0x444444), rgb(0x6c6c6c), rgb(0x9ad284), rgb(0x6c5eb5), rgb(0x959595),
+        ],
+    },
+    // Normal and bright colors; bright black is the same black, so 15 entries.
+    Builtin {
+        id: "zx-spectrum",
+        name: "ZX Spectrum",
+        colors: &[
+            rgb(0x000000), rgb(0x0000d7), rgb(0xd70000), rgb(0xd700d7), rgb(0x00d700), rgb(0x00d7d7), rgb(0xd7d700), rgb(0xd7d7d7),
+            rgb(0x0000ff), rgb(0xff0000), rgb(0xff00ff), rgb(0x00ff00), rgb(0x00ffff), rgb(0xffff00), rgb(0xffffff),
+        ],
+    },
+    Builtin {
+        id: "pico-8",
+        name: "PICO-8",
+        colors: &[
+            rgb(0x000000), rgb(0x1d2b53), rgb(0x7e2553), rgb(0x008751), rgb(0xab5236), rgb(0x5f574f), rgb(0xc2c3c7), rgb(0xfff1e8),
+            rgb(0xff004d), rgb(0xffa300), rgb(0xffec27), rgb(0x00e436), rgb(0x29adff), rgb(0x83769c), rgb(0xff77a8), rgb(0xffccaa),
+        ],
+    },
+    // Lo-res colors; the two identical grays are listed once.
+    Builtin {
+        id: "apple-ii",
+        name: "Apple II",
+        colors: &[
+            rgb(0x000000), rgb(0x722640), rgb(0x40337f), rgb(0xe434fe), rgb(0x0e5940), rgb(0x808080), rgb(0x1b9afe), rgb(0xbfb3ff),
+            rgb(0x404c00), rgb(0xe46501), rgb(0xf1a6bf), rgb(0x1bcb01), rgb(0xbfcc80), rgb(0x8dd9bf), rgb(0xffffff),
+        ],
+    },
+    // The default 16-color palette of the Macintosh II.
+    Builtin {
+        id: "mac-16",
+        name: "Macintosh 16",
+        colors: &[
+            rgb(0xffffff), rgb(0xfbf305), rgb(0xff6403), rgb(0xdd0907), rgb(0xf20884), rgb(0x4700a5), rgb(0x0000d3), rgb(0x02abea),
+            rgb(0x1fb714), rgb(0x006412), rgb(0x562c05), rgb(0x90713a), rgb(0xc0c0c0), rgb(0x808080), rgb(0x404040), rgb(0x000000),
+        ],
+    },
+];
+
+/// The built-in palette with `id`, ignoring case.
+pub fn find(id: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|b| b.id.eq_ignore_ascii_case(id.trim()))
+}
@@ -0,0 +1,30 @@
+//! Palette file writers for the two formats most tools read: GIMP `.gpl` and Lospec `.hex`.
+
+use crate::color::format_hex_color;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+/// Extensions offered by the export dialog.
+pub const EXTENSIONS: [&str; 2] = ["gpl", "hex"];
+
+pub fn to_gpl(colors: &[[u8; 3]], name: &str) -> String {
+    let mut out = format!("GIMP Palette\nName: {}\nColumns: {}\n#\n", name, colors.len().min(16));
+    for c in colors { out += &format!("{:3} {:3} {:3}\t{}\n", c[0], c[1], c[2], &format_hex_color(*c)[1..]); }
+    out
+}
+
+pub fn to_hex(colors: &[[u8; 3]]) -> String {
+    colors.iter().map(|&c| format!("{}\n", &format_hex_color(c)[1..])).collect()
+}
+
+/// Writes `colors` in the format named by the extension of `path`, using the file stem as the
+/// GIMP palette name.
+pub fn save(path: &Path, colors: &[[u8; 3]]) -> Result<()> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    let text = match ext.as_str() {
+        "gpl" => to_gpl(colors, path.file_stem().and_then(|s| s.to_str()).unwrap_or("VibeDither")),
+        "hex" => to_hex(colors),
+        _ => bail!("cannot write palettes as '.{}' (expected .gpl or .hex)", ext),
+    };
+    std::fs::write(path, text).with_context(|| format!("writing palette {:?}", path))
+}
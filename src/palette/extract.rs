@@ -0,0 +1,190 @@
+//! Picks a palette for an image: median cut, k-means in OKLab, or octree quantization.
+//!
+//! Images are sampled down to at most [`MAX_SAMPLES`] pixels first, so extraction stays
+//! interactive on large photos. Locked colors are always part of the result; the method only
+//! chooses the remaining entries. Results come back sorted from darkest to lightest.
+
+use super::MAX_COLORS;
+use crate::color::{oklab_to_srgb, srgb_to_oklab};
+use crate::pipeline::cpu::{get_luminance, srgb_to_linear};
+use anyhow::{bail, Result};
+use image::RgbaImage;
+use std::collections::HashMap;
+
+/// Pixels looked at, taken on an even grid.
+pub const MAX_SAMPLES: usize = 1 << 16;
+const KMEANS_ITERATIONS: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    MedianCut,
+    KMeans,
+    Octree,
+}
+
+impl Method {
+    pub const ALL: [Method; 3] = [Method::MedianCut, Method::KMeans, Method::Octree];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::MedianCut => "Median Cut",
+            Method::KMeans => "K-Means (OKLab)",
+            Method::Octree => "Octree",
+        }
+    }
+}
+
+/// Up to `count` colors for `img`, including every color of `locked`. Fully transparent pixels
+/// are ignored unless the whole image is.
+pub fn extract(img: &RgbaImage, count: usize, method: Method, locked: &[[u8; 3]]) -> Result<Vec<[u8; 3]>> {
+    if count == 0 || count > MAX_COLORS { bail!("palette size {} is outside 1-{}", count, MAX_COLORS); }
+    if locked.len() > count { bail!("{} locked colors do not fit in a palette of {}", locked.len(), count); }
+    let pixels = samples(img);
+    if pixels.is_empty() { bail!("image has no pixels"); }
+    let mut colors = match method {
+        Method::MedianCut => with_locked(median_cut(pixels, count), locked),
+        Method::KMeans => kmeans(&pixels, count, locked),
+        Method::Octree => with_locked(octree(&pixels, count), locked),
+    };
+    let mut seen = Vec::with_capacity(colors.len());
+    colors.retain(|c| if seen.contains(c) { false } else { seen.push(*c); true });
+    let luma = |c: &[u8; 3]| get_luminance(c.map(|v| srgb_to_linear(v as f32 / 255.0)));
+    colors.sort_by(|a, b| luma(a).total_cmp(&luma(b)));
+    Ok(colors)
+}
+
+fn samples(img: &RgbaImage) -> Vec<[u8; 3]> {
+    let step = ((img.width() as usize * img.height() as usize) as f64 / MAX_SAMPLES as f64).sqrt().ceil().max(1.0) as usize;
+    let grid = |min_alpha: u8| -> Vec<[u8; 3]> {
+        (0..img.height()).step_by(step).flat_map(|y| (0..img.width()).step_by(step).map(move |x| (x, y)))
+            .map(|(x, y)| img.get_pixel(x, y).0).filter(|p| p[3] >= min_alpha).map(|p| [p[0], p[1], p[2]]).collect()
+    };
+    let opaque = grid(1);
+    if opaque.is_empty() { grid(0) } else { opaque }
+}
+
+fn oklab_dist(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|i| (a[i] - b[i]) * (a[i] - b[i])).sum()
+}
+
+/// Puts each locked color in place of the closest candidate not already replaced, so locking a
+/// color the method found anyway does not cost a slot.
+fn with_locked(mut candidates: Vec<[u8; 3]>, locked: &[[u8; 3]]) -> Vec<[u8; 3]> {
+    let mut taken = vec![false; candidates.len()];
+    for &lock in locked {
+        let lab = srgb_to_oklab(lock);
+        let nearest = (0..candidates.len()).filter(|&i| !taken[i]).min_by(|&a, &b| oklab_dist(srgb_to_oklab(candidates[a]), lab).total_cmp(&oklab_dist(srgb_to_oklab(candidates[b]), lab)));
+        match nearest {
+            Some(i) => { candidates[i] = lock; taken[i] = true; }
+            None => { candidates.push(lock); taken.push(true); }
+        }
+    }
+    candidates
+}
+
+/// Heckbert's median cut: keep splitting the box with the widest channel at its median.
+fn median_cut(pixels: Vec<[u8; 3]>, count: usize) -> Vec<[u8; 3]> {
+    let range = |b: &[[u8; 3]]| -> (usize, u8) {
+        (0..3).map(|ch| (ch, b.iter().map(|p| p[ch]).max().unwrap() - b.iter().map(|p| p[ch]).min().unwrap())).max_by_key(|&(ch, r)| (r, std::cmp::Reverse(ch))).unwrap()
+    };
+    let mut boxes = vec![pixels];
+    while boxes.len() < count {
+        let Some((idx, ch)) = boxes.iter().enumerate().map(|(i, b)| (i, range(b))).filter(|(_, (_, r))| *r > 0).max_by_key(|(i, (_, r))| (*r, std::cmp::Reverse(*i))).map(|(i, (ch, _))| (i, ch)) else { break };
+        let mut b = boxes.swap_remove(idx);
+        b.sort_unstable_by_key(|p| p[ch]);
+        let upper = b.split_off(b.len() / 2);
+        boxes.push(b);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|b| mean(b)).collect()
+}
+
+fn mean(pixels: &[[u8; 3]]) -> [u8; 3] {
+    let n = pixels.len().max(1) as u64;
+    [0, 1, 2].map(|ch| ((pixels.iter().map(|p| p[ch] as u64).sum::<u64>() + n / 2) / n) as u8)
+}
+
+/// Lloyd's k-means on OKLab, seeded with the median cut result. Locked colors stay put as
+/// centroids, so pixels near them are not pulled into the free ones.
+fn kmeans(pixels: &[[u8; 3]], count: usize, locked: &[[u8; 3]]) -> Vec<[u8; 3]> {
+    let mut histogram: HashMap<[u8; 3], u32> = HashMap::new();
+    for p in pixels { *histogram.entry(*p).or_default() += 1; }
+    let mut points: Vec<([f32; 3], f32)> = histogram.into_iter().map(|(c, n)| (srgb_to_oklab(c), n as f32)).collect();
+    points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    let seeds = with_locked(median_cut(pixels.to_vec(), count), locked);
+    let fixed: Vec<bool> = seeds.iter().map(|c| locked.contains(c)).collect();
+    let mut centroids: Vec<[f32; 3]> = seeds.iter().map(|&c| srgb_to_oklab(c)).collect();
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut sums = vec![([0.0f32; 3], 0.0f32); centroids.len()];
+        for &(p, w) in &points {
+            let k = (0..centroids.len()).min_by(|&a, &b| oklab_dist(p, centroids[a]).total_cmp(&oklab_dist(p, centroids[b]))).unwrap();
+            for (sum, v) in sums[k].0.iter_mut().zip(p) { *sum += v * w; }
+            sums[k].1 += w;
+        }
+        let mut moved = 0.0f32;
+        for (k, (sum, w)) in sums.into_iter().enumerate() {
+            if fixed[k] || w == 0.0 { continue; }
+            let next = sum.map(|s| s / w);
+            moved = moved.max(oklab_dist(next, centroids[k]));
+            centroids[k] = next;
+        }
+        if moved < 1e-8 { break; }
+    }
+    centroids.iter().zip(&seeds).zip(&fixed).map(|((&c, &seed), &fixed)| if fixed { seed } else { oklab_to_srgb(c) }).collect()
+}
+
+#[derive(Default)]
+struct OctreeNode {
+    children: [Option<usize>; 8],
+    sum: [u64; 3],
+    count: u64,
+    leaf: bool,
+}
+
+/// Gervautz-Purgathofer octree: build the full 8-level tree, then fold the least populated
+/// deepest branches into their parents until at most `count` leaves remain.
+fn octree(pixels: &[[u8; 3]], count: usize) -> Vec<[u8; 3]> {
+    let mut nodes = vec![OctreeNode::default()];
+    let mut levels: Vec<Vec<usize>> = vec![Vec::new(); 8];
+    let mut leaves = 0usize;
+    for p in pixels {
+        let mut node = 0;
+        for depth in 0..=8 {
+            let n = &mut nodes[node];
+            for (sum, &v) in n.sum.iter_mut().zip(p) { *sum += v as u64; }
+            n.count += 1;
+            if depth == 8 { if !n.leaf { n.leaf = true; leaves += 1; } break; }
+            let bit = 7 - depth;
+            let idx = (((p[0] >> bit) & 1) << 2 | ((p[1] >> bit) & 1) << 1 | ((p[2] >> bit) & 1)) as usize;
+            node = match nodes[node].children[idx] {
+                Some(c) => c,
+                None => {
+                    nodes.push(OctreeNode::default());
+                    let c = nodes.len() - 1;
+                    nodes[node].children[idx] = Some(c);
+                    if depth < 7 { levels[depth + 1].push(c); }
+                    c
+                }
+            };
+        }
+    }
+    levels[0].push(0);
+    'reduce: for depth in (0..8).rev() {
+        let mut level = std::mem::take(&mut levels[depth]);
+        level.sort_by_key(|&i| (nodes[i].count, i));
+        for i in level {
+            if leaves <= count { break 'reduce; }
+            let children = nodes[i].children.iter().flatten().count();
+            nodes[i].leaf = true;
+            leaves = leaves + 1 - children;
+        }
+    }
+    let mut out = Vec::with_capacity(leaves);
+    let mut stack = vec![0];
+    while let Some(i) = stack.pop() {
+        let n = &nodes[i];
+        if n.leaf { out.push(n.sum.map(|s| ((s + n.count / 2) / n.count) as u8)); } else { stack.extend(n.children.iter().rev().flatten()); }
+    }
+    out
+}
@@ -0,0 +1,186 @@
+//! Palette file readers: GIMP `.gpl`, Paint.NET `.txt`, JASC `.pal`, plain `.hex` lists and the
+//! binary Adobe swatch formats `.ase` and `.aco`.
+//!
+//! Text formats are told apart by their header rather than their extension, so pasted clipboard
+//! text goes through the same path as files.
+
+use super::MAX_COLORS;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+/// Extensions offered by the import dialog and recognized on drop.
+pub const EXTENSIONS: [&str; 6] = ["gpl", "txt", "pal", "hex", "ase", "aco"];
+
+fn check_count(colors: Vec<[u8; 3]>) -> Result<Vec<[u8; 3]>> {
+    if colors.is_empty() { bail!("palette has no colors"); }
+    if colors.len() > MAX_COLORS { bail!("palette has {} colors, the most supported is {}", colors.len(), MAX_COLORS); }
+    Ok(colors)
+}
+
+/// Lines that carry data, numbered from 1, without comments (`#` or `;`) or blanks.
+fn data_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines().enumerate().map(|(i, l)| (i + 1, l.trim())).filter(|(_, l)| !l.is_empty() && !l.starts_with('#') && !l.starts_with(';'))
+}
+
+fn parse_channel(s: &str, line: usize) -> Result<u8> {
+    s.parse::<u8>().with_context(|| format!("line {}: '{}' is not a color channel (0-255)", line, s))
+}
+
+/// GIMP palette: a `GIMP Palette` header, optional `Name:`/`Columns:` fields, then `R G B name` lines.
+pub fn parse_gpl(text: &str) -> Result<Vec<[u8; 3]>> {
+    let mut lines = data_lines(text);
+    if lines.next().map(|(_, l)| l) != Some("GIMP Palette") { bail!("missing 'GIMP Palette' header"); }
+    let colors = lines.filter(|(_, l)| !l.starts_with("Name:") && !l.starts_with("Columns:")).map(|(n, l)| {
+        let rgb: Vec<&str> = l.split_whitespace().take(3).collect();
+        if rgb.len() < 3 { bail!("line {}: expected 'R G B [name]', got '{}'", n, l); }
+        Ok([parse_channel(rgb[0], n)?, parse_channel(rgb[1], n)?, parse_channel(rgb[2], n)?])
+    }).collect::<Result<Vec<_>>>()?;
+    check_count(colors)
+}
+
+/// JASC palette (Paint Shop Pro): `JASC-PAL`, a version line, the color count, then `R G B` lines.
+pub fn parse_jasc(text: &str) -> Result<Vec<[u8; 3]>> {
+    let mut lines = data_lines(text);
+    if lines.next().map(|(_, l)| l) != Some("JASC-PAL") { bail!("missing 'JASC-PAL' header"); }
+    lines.next().context("missing version line")?;
+    let (n, count) = lines.next().context("missing color count")?;
+    let count: usize = count.parse().with_context(|| format!("line {}: '{}' is not a color count", n, count))?;
+    let colors = lines.map(|(n, l)| {
+        let rgb: Vec<&str> = l.split_whitespace().collect();
+        if rgb.len() != 3 { bail!("line {}: expected 'R G B', got '{}'", n, l); }
+        Ok([parse_channel(rgb[0], n)?, parse_channel(rgb[1], n)?, parse_channel(rgb[2], n)?])
+    }).collect::<Result<Vec<_>>>()?;
+    if colors.len() != count { bail!("header says {} colors but the file has {}", count, colors.len()); }
+    check_count(colors)
+}
+
+/// Hex colors, `RRGGBB` (Lospec `.hex`, `#` optional) or `AARRGGBB` (Paint.NET `.txt`, whose alpha
+/// is dropped), one per line or separated by commas or spaces. Lines starting with `;`, or with a
+/// `#` that does not begin a color, are comments.
+pub fn parse_hex_lines(text: &str) -> Result<Vec<[u8; 3]>> {
+    let is_hex = |s: &str| matches!(s.len(), 6 | 8) && s.chars().all(|c| c.is_ascii_hexdigit());
+    let mut colors = Vec::new();
+    for (n, line) in text.lines().enumerate().map(|(i, l)| (i + 1, l.trim())) {
+        if line.is_empty() || line.starts_with(';') || (line.starts_with('#') && !line[1..].split(|c: char| c == ',' || c.is_whitespace()).next().is_some_and(is_hex)) { continue; }
+        for word in line.split(|c: char| c == ',' || c.is_whitespace()).filter(|w| !w.is_empty()) {
+            let hex = word.trim_start_matches('#');
+            if !is_hex(hex) { bail!("line {}: '{}' is not an RRGGBB or AARRGGBB color", n, word); }
+            let v = u32::from_str_radix(hex, 16)?;
+            colors.push([(v >> 16) as u8, (v >> 8) as u8, v as u8]);
+        }
+    }
+    check_count(colors)
+}
+
+/// Any of the text formats, picked by the header.
+pub fn parse_text(text: &str) -> Result<Vec<[u8; 3]>> {
+    match data_lines(text).next().map(|(_, l)| l) {
+        Some("GIMP Palette") => parse_gpl(text).context("reading GIMP palette"),
+        Some("JASC-PAL") => parse_jasc(text).context("reading JASC palette"),
+        _ => parse_hex_lines(text).context("reading hex color list"),
+    }
+}
+
+/// Big-endian cursor over a binary swatch file that names what it was reading when it runs out.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize, what: &str) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|&e| e <= self.bytes.len()).with_context(|| format!("file ends inside {} at byte {}", what, self.pos))?;
+        let out = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+    fn u16(&mut self, what: &str) -> Result<u16> { Ok(u16::from_be_bytes(self.take(2, what)?.try_into().unwrap())) }
+    fn u32(&mut self, what: &str) -> Result<u32> { Ok(u32::from_be_bytes(self.take(4, what)?.try_into().unwrap())) }
+    fn f32(&mut self, what: &str) -> Result<f32> { Ok(f32::from_be_bytes(self.take(4, what)?.try_into().unwrap())) }
+}
+
+fn unit_to_u8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn cmyk_to_rgb(c: f32, m: f32, y: f32, k: f32) -> [u8; 3] {
+    [unit_to_u8((1.0 - c) * (1.0 - k)), unit_to_u8((1.0 - m) * (1.0 - k)), unit_to_u8((1.0 - y) * (1.0 - k))]
+}
+
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [u8; 3] {
+    let h6 = h.rem_euclid(1.0) * 6.0;
+    let f = |n: f32| { let k = (n + h6) % 6.0; v - v * s * k.min(4.0 - k).clamp(0.0, 1.0) };
+    [unit_to_u8(f(5.0)), unit_to_u8(f(3.0)), unit_to_u8(f(1.0))]
+}
+
+/// Adobe Swatch Exchange: `ASEF`, a version, then blocks of which only color entries (0x0001)
+/// matter; groups are flattened. RGB, CMYK and Gray entries are read, Lab ones are rejected.
+pub fn parse_ase(bytes: &[u8]) -> Result<Vec<[u8; 3]>> {
+    let mut r = Reader { bytes, pos: 0 };
+    if r.take(4, "the signature")? != b"ASEF" { bail!("missing 'ASEF' signature"); }
+    r.take(4, "the version")?;
+    let blocks = r.u32("the block count")?;
+    let mut colors = Vec::new();
+    for i in 0..blocks {
+        let kind = r.u16("a block type")?;
+        let len = r.u32("a block length")? as usize;
+        let mut block = Reader { bytes: r.take(len, "a block")?, pos: 0 };
+        if kind != 0x0001 { continue; }
+        let name_len = block.u16("a swatch name")? as usize;
+        block.take(name_len * 2, "a swatch name")?;
+        let model = block.take(4, "a color model")?;
+        let mut ch = |what: &str| block.f32(what);
+        colors.push(match model {
+            b"RGB " => [unit_to_u8(ch("red")?), unit_to_u8(ch("green")?), unit_to_u8(ch("blue")?)],
+            b"CMYK" => cmyk_to_rgb(ch("cyan")?, ch("magenta")?, ch("yellow")?, ch("black")?),
+            b"Gray" => [unit_to_u8(ch("gray")?); 3],
+            other => bail!("swatch {} uses the {:?} color model; only RGB, CMYK and Gray are supported", i + 1, String::from_utf8_lossy(other).trim()),
+        });
+    }
+    check_count(colors)
+}
+
+/// Photoshop swatches. Only the first section is read: version 1, or version 2 with names (which
+/// usually follows a version 1 copy of the same colors). Each color is a color space id and four
+/// 16-bit channels; RGB, HSB, CMYK and Grayscale are read, the rest are rejected.
+pub fn parse_aco(bytes: &[u8]) -> Result<Vec<[u8; 3]>> {
+    let mut r = Reader { bytes, pos: 0 };
+    let version = r.u16("the version")?;
+    if version != 1 && version != 2 { bail!("unknown .aco version {} (expected 1 or 2)", version); }
+    let count = r.u16("the color count")?;
+    let mut colors = Vec::with_capacity(count as usize);
+    for i in 0..count {
+        let space = r.u16("a color space")?;
+        let w = [r.u16("a color")?, r.u16("a color")?, r.u16("a color")?, r.u16("a color")?];
+        let u = |c: u16| c as f32 / 65535.0;
+        colors.push(match space {
+            0 => [(w[0] >> 8) as u8, (w[1] >> 8) as u8, (w[2] >> 8) as u8],
+            1 => hsv_to_rgb(u(w[0]), u(w[1]), u(w[2])),
+            // Photoshop stores CMYK inverted: 0 is full ink.
+            2 => cmyk_to_rgb(1.0 - u(w[0]), 1.0 - u(w[1]), 1.0 - u(w[2]), 1.0 - u(w[3])),
+            // Grayscale is ink coverage too, 10000 being black.
+            8 => [unit_to_u8(1.0 - w[0] as f32 / 10000.0); 3],
+            other => bail!("color {} uses color space {}; only RGB (0), HSB (1), CMYK (2) and Grayscale (8) are supported", i + 1, other),
+        });
+        if version == 2 {
+            r.u16("a color name")?;
+            let chars = r.u16("a color name")? as usize;
+            r.take(chars * 2, "a color name")?;
+        }
+    }
+    check_count(colors)
+}
+
+/// Reads a palette from `bytes`: ASE by its signature, ACO when `ext` says so, otherwise text.
+pub fn parse(bytes: &[u8], ext: &str) -> Result<Vec<[u8; 3]>> {
+    if bytes.starts_with(b"ASEF") { return parse_ase(bytes).context("reading Adobe Swatch Exchange file"); }
+    if ext.eq_ignore_ascii_case("aco") { return parse_aco(bytes).context("reading Photoshop swatches"); }
+    let text = std::str::from_utf8(bytes).context("palette is neither text nor a known binary format")?;
+    parse_text(text)
+}
+
+pub fn load(path: &Path) -> Result<Vec<[u8; 3]>> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading {:?}", path))?;
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    parse(&bytes, ext).with_context(|| format!("importing palette {:?}", path))
+}
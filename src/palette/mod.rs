@@ -0,0 +1,37 @@
+//! Fixed color palettes the dither stage can quantize to instead of evenly spaced levels.
+//!
+//! The active palette is uploaded to `palette_texture` (binding 6) as a 256x1 RGBA8 texture, one
+//! entry per texel. Unused texels have zero alpha, which is where the shader stops reading.
+//! Palettes made in other tools come in through [`import`] and go back out through [`export`];
+//! classic hardware palettes ship in [`builtin`] and [`extract`] derives one from an image.
+
+pub mod builtin;
+pub mod export;
+pub mod extract;
+pub mod import;
+
+use crate::gradient::{self, GradientStop};
+use crate::pipeline::cpu::{get_luminance, srgb_to_linear};
+
+/// Largest palette the texture holds.
+pub const MAX_COLORS: usize = 256;
+
+/// Black and white, used on startup and after a reset.
+pub fn default_colors() -> Vec<[u8; 3]> {
+    vec![[0, 0, 0], [255, 255, 255]]
+}
+
+/// Bakes the first [`MAX_COLORS`] colors into the 256x1 RGBA LUT uploaded as `palette_texture`.
+pub fn generate_palette_data(colors: &[[u8; 3]], data: &mut [u8; 1024]) {
+    data.fill(0);
+    for (texel, c) in data.chunks_exact_mut(4).zip(colors) { texel.copy_from_slice(&[c[0], c[1], c[2], 255]); }
+}
+
+/// Gradient remap stops that walk the palette from darkest to lightest, evenly spaced, so the
+/// ramp follows the tone it is indexed by.
+pub fn to_gradient(colors: &[[u8; 3]]) -> Vec<GradientStop> {
+    let luma = |c: &[u8; 3]| get_luminance(c.map(|v| srgb_to_linear(v as f32 / 255.0)));
+    let mut sorted = colors.to_vec();
+    sorted.sort_by(|a, b| luma(a).total_cmp(&luma(b)));
+    gradient::stops_from_colors(&sorted)
+}
@@ -0,0 +1,553 @@
+//! Pure-Rust reference implementation of `shaders.wgsl`.
+//!
+//! Used when no GPU adapter is available and as the ground truth for tests. Function names
+//! mirror the shader so the two can be read side by side; any change to one must be made to both.
+
+use super::ColorSettings;
+use crate::threshold::ThresholdMap;
+use image::{DynamicImage, Rgba32FImage, RgbaImage};
+
+type Vec3 = [f32; 3];
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+/// A linear color in working space `space` (0 linear, 1 sRGB gamma).
+pub fn to_space(c: Vec3, space: f32) -> Vec3 {
+    if space > 0.5 { map3(c, linear_to_srgb) } else { c }
+}
+
+pub fn from_space(c: Vec3, space: f32) -> Vec3 {
+    if space > 0.5 { map3(c, srgb_to_linear) } else { c }
+}
+
+fn fract(x: f32) -> f32 { x - x.floor() }
+fn mix(a: f32, b: f32, t: f32) -> f32 { a * (1.0 - t) + b * t }
+fn smoothstep(e0: f32, e1: f32, x: f32) -> f32 { let t = ((x - e0) / (e1 - e0)).clamp(0.0, 1.0); t * t * (3.0 - 2.0 * t) }
+fn map3(c: Vec3, f: impl Fn(f32) -> f32) -> Vec3 { [f(c[0]), f(c[1]), f(c[2])] }
+
+/// The input image decoded to linear light, as sampling the half-float input texture returns it.
+pub struct SourceImage {
+    pub width: u32,
+    pub height: u32,
+    texels: Vec<[f32; 4]>,
+}
+
+impl SourceImage {
+    pub fn from_image(img: &DynamicImage) -> Self {
+        let texels = super::decode_input(img).iter().map(|t| t.map(half::f16::to_f32)).collect();
+        Self { width: img.width(), height: img.height(), texels }
+    }
+
+    /// Nearest-neighbour, clamp-to-edge lookup matching the pipeline's sampler.
+    fn texel(&self, uv: [f32; 2]) -> [f32; 4] {
+        let x = ((uv[0] * self.width as f32).floor() as i64).clamp(0, self.width as i64 - 1) as usize;
+        let y = ((uv[1] * self.height as f32).floor() as i64).clamp(0, self.height as i64 - 1) as usize;
+        self.texels[y * self.width as usize + x]
+    }
+
+    fn sample(&self, uv: [f32; 2]) -> Vec3 { let t = self.texel(uv); [t[0], t[1], t[2]] }
+
+    /// Straight coverage at `uv`.
+    pub fn alpha(&self, uv: [f32; 2]) -> f32 { self.texel(uv)[3] }
+}
+
+/// Nearest texel of a float LUT (`t_curves`, `t_gradient`) for `u` in 0..1.
+fn sample_lut(lut: &[[f32; 4]], u: f32) -> Vec3 {
+    let i = ((u * lut.len() as f32).floor() as i64).clamp(0, lut.len() as i64 - 1) as usize;
+    [lut[i][0], lut[i][1], lut[i][2]]
+}
+
+pub fn get_luminance(color: Vec3) -> f32 {
+    color[0] * 0.2126 + color[1] * 0.7152 + color[2] * 0.0722
+}
+
+fn hash22(p: [f32; 2]) -> [f32; 2] {
+    let mut p3 = [fract(p[0] * 0.1031), fract(p[1] * 0.1030), fract(p[0] * 0.0973)];
+    let d = p3[0] * (p3[1] + 33.33) + p3[1] * (p3[2] + 33.33) + p3[2] * (p3[0] + 33.33);
+    p3 = map3(p3, |v| v + d);
+    [fract((p3[0] + p3[1]) * p3[2]), fract((p3[0] + p3[2]) * p3[1])]
+}
+
+fn white_noise(p: [f32; 2]) -> f32 {
+    hash22(p)[0]
+}
+
+#[allow(clippy::excessive_precision)] // same literals as the shader
+fn interleaved_gradient_noise(p: [f32; 2]) -> f32 {
+    fract(52.9829189 * fract(p[0] * 0.06711056 + p[1] * 0.00583715))
+}
+
+/// Halftone samples per cell side; the cell's tone is the mean of `HALFTONE_SAMPLES`^2 adjusted colors.
+const HALFTONE_SAMPLES: i32 = 4;
+
+fn halftone_rot(angle: f32) -> [f32; 2] {
+    let a = angle.to_radians();
+    [a.cos(), a.sin()]
+}
+
+/// Image point `p` in the screen rotated by `rot`, where whole numbers are cell corners.
+fn to_screen(p: [f32; 2], rot: [f32; 2], cell_size: f32) -> [f32; 2] {
+    [(rot[0] * p[0] + rot[1] * p[1]) / cell_size, (rot[0] * p[1] - rot[1] * p[0]) / cell_size]
+}
+
+fn apply_dither_step(val: f32, noise: f32, levels: f32) -> f32 {
+    if levels > 1.5 {
+        let lv = levels - 1.0;
+        let scaled = val * lv;
+        let floor_v = scaled.floor();
+        let diff = scaled - floor_v;
+        if diff > noise { (floor_v + 1.0) / lv } else { floor_v / lv }
+    } else if val > noise { 1.0 } else { 0.0 }
+}
+
+fn cbrt(x: f32) -> f32 { x.signum() * x.abs().powf(1.0 / 3.0) }
+fn dot3(a: Vec3, b: Vec3) -> f32 { a[0] * b[0] + a[1] * b[1] + a[2] * b[2] }
+
+pub fn linear_to_oklab(c: Vec3) -> Vec3 {
+    let lms = [cbrt(dot3(c, [0.41222147, 0.53633254, 0.051445993])), cbrt(dot3(c, [0.2119035, 0.6806995, 0.10739696])), cbrt(dot3(c, [0.08830246, 0.28171884, 0.6299787]))];
+    [dot3(lms, [0.21045426, 0.7936178, -0.004072047]), dot3(lms, [1.9779985, -2.4285922, 0.4505937]), dot3(lms, [0.025904037, 0.78277177, -0.80867577])]
+}
+
+fn lab_f(t: f32) -> f32 {
+    if t > 0.008856452 { cbrt(t) } else { t * 7.787037 + 0.13793103 }
+}
+
+/// CIELAB relative to the D65 white point.
+pub fn linear_to_lab(c: Vec3) -> Vec3 {
+    let fx = lab_f(dot3(c, [0.4124564, 0.3575761, 0.1804375]) / 0.95047);
+    let fy = lab_f(dot3(c, [0.2126729, 0.7151522, 0.072175]));
+    let fz = lab_f(dot3(c, [0.0193339, 0.119192, 0.9503041]) / 1.08883);
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn hue_degrees(b: f32, a: f32) -> f32 {
+    if a == 0.0 && b == 0.0 { return 0.0; }
+    let h = b.atan2(a).to_degrees();
+    if h < 0.0 { h + 360.0 } else { h }
+}
+
+/// CIEDE2000 between two CIELAB colors (Sharma, Wu & Dalal 2005).
+pub fn delta_e2000(lab1: Vec3, lab2: Vec3) -> f32 {
+    let chroma = |a: f32, b: f32| (a * a + b * b).sqrt();
+    let c7 = ((chroma(lab1[1], lab1[2]) + chroma(lab2[1], lab2[2])) * 0.5).powf(7.0);
+    let g = 0.5 * (1.0 - (c7 / (c7 + 6103515625.0)).sqrt());
+    let a1 = lab1[1] * (1.0 + g);
+    let a2 = lab2[1] * (1.0 + g);
+    let c1 = chroma(a1, lab1[2]);
+    let c2 = chroma(a2, lab2[2]);
+    let h1 = hue_degrees(lab1[2], a1);
+    let h2 = hue_degrees(lab2[2], a2);
+    let (mut dh, mut h_bar) = (0.0, h1 + h2);
+    if c1 * c2 != 0.0 {
+        dh = h2 - h1;
+        if dh > 180.0 { dh -= 360.0; } else if dh < -180.0 { dh += 360.0; }
+        h_bar = if (h1 - h2).abs() <= 180.0 { (h1 + h2) * 0.5 } else if h1 + h2 < 360.0 { (h1 + h2 + 360.0) * 0.5 } else { (h1 + h2 - 360.0) * 0.5 };
+    }
+    let d_l = lab2[0] - lab1[0];
+    let d_c = c2 - c1;
+    let d_h = 2.0 * (c1 * c2).sqrt() * (dh.to_radians() * 0.5).sin();
+    let l_bar = (lab1[0] + lab2[0]) * 0.5 - 50.0;
+    let c_bar = (c1 + c2) * 0.5;
+    let cos_deg = |d: f32| d.to_radians().cos();
+    let t = 1.0 - 0.17 * cos_deg(h_bar - 30.0) + 0.24 * cos_deg(2.0 * h_bar) + 0.32 * cos_deg(3.0 * h_bar + 6.0) - 0.2 * cos_deg(4.0 * h_bar - 63.0);
+    let c_bar7 = c_bar.powf(7.0);
+    let r_t = -2.0 * (c_bar7 / (c_bar7 + 6103515625.0)).sqrt() * (60.0 * (-((h_bar - 275.0) / 25.0).powf(2.0)).exp()).to_radians().sin();
+    let s_l = 1.0 + 0.015 * l_bar * l_bar / (20.0 + l_bar * l_bar).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar;
+    let s_h = 1.0 + 0.015 * c_bar * t;
+    ((d_l / s_l).powf(2.0) + (d_c / s_c).powf(2.0) + (d_h / s_h).powf(2.0) + r_t * (d_c / s_c) * (d_h / s_h)).sqrt()
+}
+
+/// A linear color in the space `metric` (a `ColorSettings::color_metric`) measures in; see [`metric_distance`].
+pub fn to_metric_space(c: Vec3, metric: f32) -> Vec3 {
+    if metric > 2.5 { linear_to_lab(c) }
+    else if metric > 1.5 { linear_to_oklab(c) }
+    else if metric > 0.5 { [c[0] * 0.2126f32.sqrt(), c[1] * 0.7152f32.sqrt(), c[2] * 0.0722f32.sqrt()] }
+    else { c }
+}
+
+/// Distance between two colors already in [`to_metric_space`]: Euclidean, except for CIEDE2000.
+pub fn metric_distance(a: Vec3, b: Vec3, metric: f32) -> f32 {
+    if metric > 3.5 { return delta_e2000(a, b); }
+    let d = [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+    dot3(d, d).sqrt()
+}
+
+/// What `fs_main` goes through on the way to one output pixel, for inspecting it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PixelProbe {
+    /// Linear source texel with straight alpha, as sampled.
+    pub source: [f32; 4],
+    /// After sharpening, adjustments and curves, in `dither_space`.
+    pub adjusted: Vec3,
+    /// Luminance of `adjusted`, the value a monochrome dither compares.
+    pub luminance: f32,
+    /// Luminance entering the tone curves (sharpening aside), in `adjust_space`: where the pixel
+    /// sits on the curve editor's x axis.
+    pub curve_input: f32,
+    /// Threshold the dither stage compared against. `None` with dithering off, for CMYK screens
+    /// (one per plate) and for the serial passes, whose threshold moves with the diffused error.
+    pub noise: Option<f32>,
+    /// Clamped linear color and alpha written to the render target. Serial passes are not per
+    /// pixel, so for them this is the shader's output only.
+    pub output: [f32; 4],
+}
+
+/// One invocation context of `fs_main`: the bound textures plus the uniform block.
+pub struct Shader<'a> {
+    pub source: &'a SourceImage,
+    pub settings: &'a ColorSettings,
+    pub curves: &'a [[f32; 4]],
+    pub gradient: &'a [[f32; 4]],
+    /// The entries of `t_palette` up to the first zero-alpha texel, decoded to linear light.
+    pub palette: &'a [Vec3],
+    pub threshold: &'a ThresholdMap,
+}
+
+impl Shader<'_> {
+    fn apply_adjustments(&self, in_color: Vec3) -> Vec3 {
+        let color = self.tone_adjustments(in_color);
+
+        // 5. RGB Curves
+        [sample_lut(self.curves, color[0].clamp(0.0, 1.0))[0], sample_lut(self.curves, color[1].clamp(0.0, 1.0))[1], sample_lut(self.curves, color[2].clamp(0.0, 1.0))[2]]
+    }
+
+    /// Steps 1-4 of `apply_adjustments`: everything before the curves.
+    fn tone_adjustments(&self, in_color: Vec3) -> Vec3 {
+        let s = self.settings;
+        let mut color = in_color;
+
+        // 1. Exposure & White Balance
+        color = map3(color, |c| c * 2f32.powf(s.exposure));
+        color[0] *= 1.0 + s.temperature * 0.4;
+        color[2] *= 1.0 - s.temperature * 0.4;
+        color[1] *= 1.0 - s.tint * 0.25;
+        color[0] *= 1.0 + s.tint * 0.1;
+        color[2] *= 1.0 + s.tint * 0.1;
+
+        // 2. Contrast & Brightness
+        color = map3(color, |c| (c + s.brightness - 0.5) * s.contrast + 0.5);
+
+        // 3. Highlights, Shadows, Whites, Blacks
+        let lum_pre = get_luminance(color);
+        color = map3(color, |c| c + c * smoothstep(0.4, 0.8, lum_pre) * s.highlights * 0.5);
+        color = map3(color, |c| c + c * (1.0 - smoothstep(0.2, 0.6, lum_pre)) * s.shadows * 0.5);
+        color = map3(color, |c| c + (1.0 - smoothstep(0.0, 0.3, lum_pre)) * s.blacks * 0.3);
+        color = map3(color, |c| c + smoothstep(0.7, 1.0, lum_pre) * s.whites * 0.3);
+
+        // 4. Saturation & Vibrance
+        let l_pre_sat = get_luminance(color);
+        let color_sat = color[0].max(color[1].max(color[2])) - color[0].min(color[1].min(color[2]));
+        map3(color, |c| mix(l_pre_sat, c, s.saturation + s.vibrance * (1.0 - color_sat)))
+    }
+
+    /// Palette dithering is on and the palette has at least one entry.
+    pub fn palette_active(&self) -> bool {
+        self.settings.palette_enabled > 0.5 && !self.palette.is_empty()
+    }
+
+    /// Picks between the two palette entries nearest to `color` under `color_metric`: the second one wins
+    /// when the color's projection onto the segment between them lies past `noise`, so a dither pattern
+    /// mixes the two in the right proportion. `color`, the projection and the result are in
+    /// `dither_space`. A `noise` of 1 or more always gives the nearest entry.
+    pub fn palette_dither(&self, color: Vec3, noise: f32) -> Vec3 {
+        let dist2 = |a: Vec3, b: Vec3| (a[0] - b[0]) * (a[0] - b[0]) + (a[1] - b[1]) * (a[1] - b[1]) + (a[2] - b[2]) * (a[2] - b[2]);
+        let metric = self.settings.color_metric;
+        let space = self.settings.dither_space;
+        let target = to_metric_space(from_space(color, space), metric);
+        let (mut best, mut second) = ([0.0; 3], [0.0; 3]);
+        let (mut best_d, mut second_d) = (1e30, 1e30);
+        for &entry in self.palette {
+            let d = metric_distance(target, to_metric_space(entry, metric), metric);
+            if d < best_d { second = best; second_d = best_d; best = entry; best_d = d; }
+            else if d < second_d { second = entry; second_d = d; }
+        }
+        let best = to_space(best, space);
+        if second_d >= 1e30 { return best; }
+        let second = to_space(second, space);
+        let axis = [second[0] - best[0], second[1] - best[1], second[2] - best[2]];
+        let along = (color[0] - best[0]) * axis[0] + (color[1] - best[1]) * axis[1] + (color[2] - best[2]) * axis[2];
+        let t = (along / dist2(second, best).max(1e-8)).clamp(0.0, 1.0);
+        if t > noise { second } else { best }
+    }
+
+    /// The palette entry nearest to `color`.
+    pub fn palette_nearest(&self, color: Vec3) -> Vec3 {
+        self.palette_dither(color, 1.0)
+    }
+
+    fn tex_size(&self) -> [f32; 2] {
+        [self.source.width as f32, self.source.height as f32]
+    }
+
+    /// The input at `uv` in `adjust_space`.
+    fn sample_input(&self, uv: [f32; 2]) -> Vec3 {
+        to_space(self.source.sample(uv), self.settings.adjust_space)
+    }
+
+    /// Sharpening plus adjustments at `uv`, run in `adjust_space` and handed over in `dither_space`:
+    /// the color the dither stage of `fs_main` starts from.
+    pub fn adjusted_color(&self, uv: [f32; 2]) -> Vec3 {
+        let s = self.settings;
+        let tex_size = self.tex_size();
+        let mut color = self.sample_input(uv);
+
+        if s.sharpness > 0.0 {
+            let dx = 1.0 / tex_size[0];
+            let dy = 1.0 / tex_size[1];
+            let n = [self.sample_input([uv[0] - dx, uv[1]]), self.sample_input([uv[0] + dx, uv[1]]), self.sample_input([uv[0], uv[1] - dy]), self.sample_input([uv[0], uv[1] + dy])];
+            color = std::array::from_fn(|c| {
+                let laplacian = n[0][c] + n[1][c] + n[2][c] + n[3][c] - 4.0 * color[c];
+                color[c] - s.sharpness * laplacian
+            });
+        }
+
+        color = self.apply_adjustments(color);
+        if s.adjust_space == s.dither_space { return color; }
+        to_space(from_space(color, s.adjust_space), s.dither_space)
+    }
+
+    /// Mean adjusted color of screen cell `cell`, whose screen space is image space rotated by `rot`
+    /// (cos, sin) and scaled by `cell_size` pixels.
+    fn halftone_average(&self, cell: [f32; 2], rot: [f32; 2], cell_size: f32) -> Vec3 {
+        let tex_size = self.tex_size();
+        let n = HALFTONE_SAMPLES as f32;
+        let mut sum = [0.0; 3];
+        for j in 0..HALFTONE_SAMPLES {
+            for i in 0..HALFTONE_SAMPLES {
+                let q = [(cell[0] + (i as f32 + 0.5) / n) * cell_size, (cell[1] + (j as f32 + 0.5) / n) * cell_size];
+                let p = [rot[0] * q[0] - rot[1] * q[1], rot[1] * q[0] + rot[0] * q[1]];
+                let c = self.adjusted_color([p[0] / tex_size[0], p[1] / tex_size[1]]);
+                sum = std::array::from_fn(|k| sum[k] + c[k]);
+            }
+        }
+        map3(sum, |c| c / (n * n))
+    }
+
+    fn halftone_cell_size(&self) -> f32 {
+        let w = self.source.width as f32;
+        w / self.settings.halftone_frequency.clamp(1.0, w)
+    }
+
+    /// The spot map stretched over one screen cell.
+    fn halftone_threshold(&self, q: [f32; 2]) -> f32 {
+        self.threshold.at([fract(q[0]) * self.threshold.width as f32, fract(q[1]) * self.threshold.height as f32])
+    }
+
+    /// Ink coverage of the C, M, Y and K plates. Black takes `cmyk_black` of the gray component above
+    /// `cmyk_black_start` (0 is GCR over the whole range, higher confines it to the shadows like UCR)
+    /// and CMY are rescaled so the inks still multiply back to the same color.
+    fn to_cmyk(&self, rgb: Vec3) -> [f32; 4] {
+        let s = self.settings;
+        let cmy = map3(rgb, |c| 1.0 - c.clamp(0.0, 1.0));
+        let k0 = cmy[0].min(cmy[1].min(cmy[2]));
+        let k = s.cmyk_black * (k0 - s.cmyk_black_start).max(0.0) / (1.0 - s.cmyk_black_start).max(1e-5);
+        let cmy = map3(cmy, |c| (c - k) / (1.0 - k).max(1e-5));
+        [cmy[0], cmy[1], cmy[2], k]
+    }
+
+    /// CMYK separation: every plate is screened from its own cells at the classic rosette angles
+    /// (C 15, M 75, Y 0, K 45, turned with `halftone_angle` so K follows it), then the inks are
+    /// multiplied onto white paper. `cmyk_plate` 1-4 shows a single plate, ink black.
+    fn halftone_cmyk(&self, p: [f32; 2]) -> Vec3 {
+        let s = self.settings;
+        let cell_size = self.halftone_cell_size();
+        let plate = s.cmyk_plate as i32;
+        let single = (1..=4).contains(&plate);
+        let mut paper = [1.0; 4];
+        for (i, angle) in [15.0f32, 75.0, 0.0, 45.0].into_iter().enumerate() {
+            if single && i as i32 != plate - 1 { continue; }
+            let rot = halftone_rot(angle - 45.0 + s.halftone_angle);
+            let q = to_screen(p, rot, cell_size);
+            let ink = self.to_cmyk(self.halftone_average([q[0].floor(), q[1].floor()], rot, cell_size))[i];
+            paper[i] = apply_dither_step(1.0 - ink, self.halftone_threshold(q), s.posterize_levels);
+        }
+        if single { return [paper[plate as usize - 1]; 3]; }
+        [paper[0] * paper[3], paper[1] * paper[3], paper[2] * paper[3]]
+    }
+
+    /// Source alpha at `uv`, cut to fully opaque or clear against `noise` when `alpha_dither` is on.
+    pub fn dithered_alpha(&self, uv: [f32; 2], noise: f32) -> f32 {
+        let alpha = self.source.alpha(uv);
+        if self.settings.alpha_dither > 0.5 { apply_dither_step(alpha, noise, 0.0) } else { alpha }
+    }
+
+    /// Gradient remap (or the way back from `dither_space`), the final clamp and the optional
+    /// premultiply, the last stage of `fs_main`.
+    pub fn finish(&self, mut final_color: Vec3, alpha: f32) -> [f32; 4] {
+        if self.settings.grad_enabled > 0.5 {
+            let lum = get_luminance(final_color).clamp(0.0, 1.0);
+            final_color = sample_lut(self.gradient, lum);
+        } else {
+            final_color = from_space(final_color, self.settings.dither_space);
+        }
+
+        let alpha = alpha.clamp(0.0, 1.0);
+        let mut rgb = map3(final_color, |c| c.clamp(0.0, 1.0));
+        if self.settings.alpha_premultiply > 0.5 { rgb = map3(rgb, |c| c * alpha); }
+        [rgb[0], rgb[1], rgb[2], alpha]
+    }
+
+    /// Shades output pixel `(x, y)` and returns the clamped linear color and alpha written to the render target.
+    pub fn fs_main(&self, x: u32, y: u32) -> [f32; 4] {
+        self.shade(x, y).0
+    }
+
+    /// Shades output pixel `(x, y)`, keeping the intermediate values.
+    pub fn probe(&self, x: u32, y: u32) -> PixelProbe {
+        let (output, uv, adjusted, noise) = self.shade(x, y);
+        let curve_input = get_luminance(self.tone_adjustments(self.sample_input(uv))).clamp(0.0, 1.0);
+        PixelProbe { source: self.source.texel(uv), adjusted, luminance: get_luminance(adjusted), curve_input, noise, output }
+    }
+
+    /// `fs_main` proper. Also returns the sampled uv, the adjusted color and the threshold used.
+    fn shade(&self, x: u32, y: u32) -> ([f32; 4], [f32; 2], Vec3, Option<f32>) {
+        let s = self.settings;
+        let tex_size = self.tex_size();
+        let tex_coords = [(x as f32 + 0.5) / tex_size[0], (y as f32 + 0.5) / tex_size[1]];
+        let mut uv = tex_coords;
+        let scale = s.dither_scale;
+
+        if s.dither_enabled > 0.5 && scale > 1.0 {
+            uv = [((uv[0] * tex_size[0] / scale).floor() * scale + scale * 0.5) / tex_size[0], ((uv[1] * tex_size[1] / scale).floor() * scale + scale * 0.5) / tex_size[1]];
+        }
+
+        let mut color = self.adjusted_color(uv);
+        let adjusted = color;
+
+        let mut final_color = color;
+        let mut alpha_noise = s.dither_threshold;
+        let mut used_noise = None;
+
+        if s.dither_enabled < 0.5 {
+            if self.palette_active() {
+                final_color = self.palette_nearest(final_color);
+            } else if s.posterize_levels > 1.5 {
+                let lv = s.posterize_levels - 1.0;
+                final_color = map3(final_color, |c| (c * lv + 0.5).floor() / lv);
+            }
+        } else {
+            let d_scale = scale.max(1.0);
+            let screen_pos = [(tex_coords[0] * tex_size[0] / d_scale).floor(), (tex_coords[1] * tex_size[1] / d_scale).floor()];
+            let d_type = s.dither_type as i32;
+
+            let mut noise = s.dither_threshold;
+            if d_type == 2 {
+                noise = white_noise(screen_pos);
+            } else if d_type == 3 || d_type == 4 {
+                noise = self.threshold.at(screen_pos);
+            } else if d_type == 5 {
+                // 5-7 only blend noise; real error diffusion (type 10) is serial, see `diffusion`.
+                let j = hash22(screen_pos);
+                noise = (j[0] + j[1] + interleaved_gradient_noise(screen_pos)) / 3.0;
+            } else if d_type == 6 {
+                let n1 = interleaved_gradient_noise(screen_pos);
+                let n2 = interleaved_gradient_noise([screen_pos[0] + 5.0, screen_pos[1] + 3.0]);
+                noise = fract(n1 * 0.75 + n2 * 0.25);
+            } else if d_type == 7 {
+                let n = interleaved_gradient_noise(screen_pos);
+                noise = if n >= 0.5 { 0.5 } else { 0.0 } + 0.25;
+            } else if d_type == 8 {
+                let dx = get_luminance(to_space(self.source.sample([uv[0] + 1.0 / tex_size[0], uv[1]]), s.dither_space)) - get_luminance(color);
+                let dy = get_luminance(to_space(self.source.sample([uv[0], uv[1] + 1.0 / tex_size[1]]), s.dither_space)) - get_luminance(color);
+                let edge = (dx.abs() + dy.abs()).clamp(0.0, 1.0);
+                noise = mix(interleaved_gradient_noise(screen_pos), s.dither_threshold, edge * 0.8);
+            } else if d_type == 9 {
+                let p = [screen_pos[0] * 0.4, screen_pos[1] * 0.4];
+                let n = p[0].sin() * p[1].cos() + (p[1] * 0.5).sin() * (p[0] * 0.5).cos();
+                noise = fract(n * 2.0 + interleaved_gradient_noise(screen_pos) * 0.5);
+            } else if d_type == 11 && s.halftone_cmyk < 0.5 {
+                // AM halftone: the cell's mean tone decides how much of the spot map is inked,
+                // so the dot grows with darkness. The map spans the whole rotated cell.
+                let cell_size = self.halftone_cell_size();
+                let rot = halftone_rot(s.halftone_angle);
+                let q = to_screen([(screen_pos[0] + 0.5) * d_scale, (screen_pos[1] + 0.5) * d_scale], rot, cell_size);
+                color = self.halftone_average([q[0].floor(), q[1].floor()], rot, cell_size);
+                noise = self.halftone_threshold(q);
+            }
+            alpha_noise = noise;
+            if !(s.needs_cpu_pass() || d_type == 11 && s.halftone_cmyk > 0.5) { used_noise = Some(noise); }
+
+            if d_type == 11 && s.halftone_cmyk > 0.5 {
+                final_color = self.halftone_cmyk([(screen_pos[0] + 0.5) * d_scale, (screen_pos[1] + 0.5) * d_scale]);
+            } else if self.palette_active() {
+                final_color = self.palette_dither(if s.dither_color > 0.5 { color } else { [get_luminance(color); 3] }, noise);
+            } else if s.dither_color > 0.5 {
+                final_color = map3(color, |c| apply_dither_step(c, noise, s.posterize_levels));
+            } else {
+                final_color = [apply_dither_step(get_luminance(color), noise, s.posterize_levels); 3];
+            }
+        }
+
+        (self.finish(final_color, self.dithered_alpha(uv, alpha_noise)), uv, adjusted, used_noise)
+    }
+}
+
+/// Encodes a clamped linear color and alpha for an `Rgba8UnormSrgb` target, which stores alpha as is.
+pub fn encode_srgb8(c: [f32; 4]) -> [u8; 4] {
+    [(linear_to_srgb(c[0]) * 255.0 + 0.5) as u8, (linear_to_srgb(c[1]) * 255.0 + 0.5) as u8, (linear_to_srgb(c[2]) * 255.0 + 0.5) as u8, (c[3] * 255.0 + 0.5) as u8]
+}
+
+/// Renders `img` exactly as the GPU pipeline would into an `Rgba8UnormSrgb` target, including the
+/// passes only the CPU can run (see [`ColorSettings::needs_cpu_pass`]).
+pub fn render(img: &DynamicImage, settings: &ColorSettings, curves: &[[f32; 4]], gradient: &[[f32; 4]], palette: &[u8; 1024], threshold: &ThresholdMap) -> RgbaImage {
+    let source = SourceImage::from_image(img);
+    let palette = decode_palette(palette);
+    let shader = Shader { source: &source, settings, curves, gradient, palette: &palette, threshold };
+    if settings.needs_cpu_pass() {
+        return if settings.dither_type as usize == super::DITHER_STIPPLING { super::stipple::render(&shader) } else { super::diffusion::render(&shader) };
+    }
+    let mut out = RgbaImage::new(source.width, source.height);
+    shade_rows(&mut out, source.width as usize * 4, |y, row| {
+        for (x, px) in row.chunks_mut(4).enumerate() { px.copy_from_slice(&encode_srgb8(shader.fs_main(x as u32, y))); }
+    });
+    out
+}
+
+/// Like [`render`], but returns the clamped linear output as the `Rgba16Float` target of
+/// `Pipeline::render_image_linear` holds it, before any 8-bit encoding. CPU-only passes are
+/// dithered and come back decoded from their 8-bit result.
+pub fn render_linear(img: &DynamicImage, settings: &ColorSettings, curves: &[[f32; 4]], gradient: &[[f32; 4]], palette: &[u8; 1024], threshold: &ThresholdMap) -> Rgba32FImage {
+    if settings.needs_cpu_pass() {
+        let out = render(img, settings, curves, gradient, palette, threshold);
+        return Rgba32FImage::from_fn(out.width(), out.height(), |x, y| { let p = out.get_pixel(x, y).0; image::Rgba([srgb_to_linear(p[0] as f32 / 255.0), srgb_to_linear(p[1] as f32 / 255.0), srgb_to_linear(p[2] as f32 / 255.0), p[3] as f32 / 255.0]) });
+    }
+    let source = SourceImage::from_image(img);
+    let palette = decode_palette(palette);
+    let shader = Shader { source: &source, settings, curves, gradient, palette: &palette, threshold };
+    let mut out = Rgba32FImage::new(source.width, source.height);
+    shade_rows(&mut out, source.width as usize * 4, |y, row| {
+        for (x, px) in row.chunks_mut(4).enumerate() {
+            px.copy_from_slice(&shader.fs_main(x as u32, y).map(|v| half::f16::from_f32(v).to_f32()));
+        }
+    });
+    out
+}
+
+/// The entries of a palette LUT up to the first zero-alpha texel, decoded to linear light.
+pub fn decode_palette(palette: &[u8; 1024]) -> Vec<Vec3> {
+    palette.chunks_exact(4).take_while(|t| t[3] > 127).map(|t| [srgb_to_linear(t[0] as f32 / 255.0), srgb_to_linear(t[1] as f32 / 255.0), srgb_to_linear(t[2] as f32 / 255.0)]).collect()
+}
+
+/// Splits `out` into rows of `row_len` values and hands them to `shade` with their y, spread over
+/// the available cores.
+fn shade_rows<T: Send>(out: &mut [T], row_len: usize, shade: impl Fn(u32, &mut [T]) + Sync) {
+    if row_len == 0 { return; }
+    let height = out.len() / row_len;
+    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let rows_per_chunk = height.div_ceil(threads).max(1);
+    std::thread::scope(|scope| {
+        for (chunk_idx, chunk) in out.chunks_mut(rows_per_chunk * row_len).enumerate() {
+            let shade = &shade;
+            scope.spawn(move || {
+                for (row_idx, row) in chunk.chunks_mut(row_len).enumerate() { shade((chunk_idx * rows_per_chunk + row_idx) as u32, row); }
+            });
+        }
+    });
+}
@@ -0,0 +1,170 @@
+//! Serial error diffusion, the one dither family a fragment shader cannot express: every pixel's
+//! quantization error is pushed onto neighbours that have not been visited yet, so pixels must be
+//! processed in order.
+//!
+//! The pass works on the same cell grid as `fs_main` (one cell per `dither_scale` block), starts
+//! from the adjusted color the shader would threshold, and ends with the shader's gradient remap.
+//! With a palette, each cell takes the nearest entry and the error is diffused per channel.
+//! Rows run left to right, or alternate direction with `diffusion_serpentine`, which breaks up the
+//! diagonal "worms" a fixed scan direction leaves in flat areas.
+
+use super::cpu::{encode_srgb8, get_luminance, Shader};
+use super::ColorSettings;
+use image::RgbaImage;
+
+/// An error-diffusion matrix. Each tap is `(dx, dy, weight)` relative to the current pixel and
+/// receives `weight / divisor` of its error.
+pub struct Kernel {
+    pub name: &'static str,
+    pub divisor: f32,
+    pub taps: &'static [(i32, i32, f32)],
+}
+
+/// Indexed by `ColorSettings::diffusion_kernel`.
+pub const KERNELS: [Kernel; 8] = [
+    Kernel { name: "Floyd-Steinberg", divisor: 16.0, taps: &[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)] },
+    Kernel {
+        name: "Jarvis-Judice-Ninke",
+        divisor: 48.0,
+        taps: &[
+            (1, 0, 7.0), (2, 0, 5.0),
+            (-2, 1, 3.0), (-1, 1, 5.0), (0, 1, 7.0), (1, 1, 5.0), (2, 1, 3.0),
+            (-2, 2, 1.0), (-1, 2, 3.0), (0, 2, 5.0), (1, 2, 3.0), (2, 2, 1.0),
+        ],
+    },
+    Kernel {
+        name: "Stucki",
+        divisor: 42.0,
+        taps: &[
+            (1, 0, 8.0), (2, 0, 4.0),
+            (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 8.0), (1, 1, 4.0), (2, 1, 2.0),
+            (-2, 2, 1.0), (-1, 2, 2.0), (0, 2, 4.0), (1, 2, 2.0), (2, 2, 1.0),
+        ],
+    },
+    Kernel {
+        name: "Burkes",
+        divisor: 32.0,
+        taps: &[
+            (1, 0, 8.0), (2, 0, 4.0),
+            (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 8.0), (1, 1, 4.0), (2, 1, 2.0),
+        ],
+    },
+    Kernel {
+        name: "Sierra-3",
+        divisor: 32.0,
+        taps: &[
+            (1, 0, 5.0), (2, 0, 3.0),
+            (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 5.0), (1, 1, 4.0), (2, 1, 2.0),
+            (-1, 2, 2.0), (0, 2, 3.0), (1, 2, 2.0),
+        ],
+    },
+    Kernel {
+        name: "Sierra-2",
+        divisor: 16.0,
+        taps: &[
+            (1, 0, 4.0), (2, 0, 3.0),
+            (-2, 1, 1.0), (-1, 1, 2.0), (0, 1, 3.0), (1, 1, 2.0), (2, 1, 1.0),
+        ],
+    },
+    Kernel { name: "Sierra Lite", divisor: 4.0, taps: &[(1, 0, 2.0), (-1, 1, 1.0), (0, 1, 1.0)] },
+    // Only 6/8 of the error is passed on, which is what gives Atkinson its light, contrasty look.
+    Kernel {
+        name: "Atkinson",
+        divisor: 8.0,
+        taps: &[(1, 0, 1.0), (2, 0, 1.0), (-1, 1, 1.0), (0, 1, 1.0), (1, 1, 1.0), (0, 2, 1.0)],
+    },
+];
+
+pub fn kernel(settings: &ColorSettings) -> &'static Kernel {
+    &KERNELS[(settings.diffusion_kernel.max(0.0) as usize).min(KERNELS.len() - 1)]
+}
+
+/// Nearest of `posterize_levels` evenly spaced levels, or a two-level split at `dither_threshold`.
+fn quantize(v: f32, levels: f32, threshold: f32) -> f32 {
+    if levels > 1.5 {
+        let lv = levels - 1.0;
+        (v.clamp(0.0, 1.0) * lv).round() / lv
+    } else if v > threshold { 1.0 } else { 0.0 }
+}
+
+/// Adjusted color at the center of every dither cell, sampled where `fs_main` samples it.
+pub(super) fn adjusted_cells(shader: &Shader, cols: usize, rows: usize, d_scale: f32) -> Vec<[f32; 3]> {
+    let tex_size = [shader.source.width as f32, shader.source.height as f32];
+    let mut cells = vec![[0.0; 3]; cols * rows];
+    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let rows_per_chunk = rows.div_ceil(threads).max(1);
+    std::thread::scope(|scope| {
+        for (chunk_idx, chunk) in cells.chunks_mut(rows_per_chunk * cols).enumerate() {
+            scope.spawn(move || {
+                for (row_idx, row) in chunk.chunks_mut(cols).enumerate() {
+                    let cy = (chunk_idx * rows_per_chunk + row_idx) as f32;
+                    for (cx, cell) in row.iter_mut().enumerate() {
+                        let uv = [(cx as f32 * d_scale + d_scale * 0.5) / tex_size[0], (cy * d_scale + d_scale * 0.5) / tex_size[1]];
+                        *cell = shader.adjusted_color(uv);
+                    }
+                }
+            });
+        }
+    });
+    cells
+}
+
+/// Source alpha at the center of every dither cell.
+pub(super) fn cell_alphas(shader: &Shader, cols: usize, rows: usize, d_scale: f32) -> Vec<f32> {
+    let tex_size = [shader.source.width as f32, shader.source.height as f32];
+    (0..cols * rows).map(|i| shader.source.alpha([((i % cols) as f32 * d_scale + d_scale * 0.5) / tex_size[0], ((i / cols) as f32 * d_scale + d_scale * 0.5) / tex_size[1]])).collect()
+}
+
+/// Renders the whole image with serial error diffusion in place of the shader's per-pixel dither.
+pub fn render(shader: &Shader) -> RgbaImage {
+    let s = shader.settings;
+    let (width, height) = (shader.source.width, shader.source.height);
+    if width == 0 || height == 0 { return RgbaImage::new(width, height); }
+    let d_scale = s.dither_scale.max(1.0);
+    // Same cell index as `screen_pos` in `fs_main`.
+    let cell_of = |p: u32| ((p as f32 + 0.5) / d_scale).floor() as usize;
+    let (cols, rows) = (cell_of(width - 1) + 1, cell_of(height - 1) + 1);
+
+    let mut values = adjusted_cells(shader, cols, rows, d_scale);
+    // A palette takes all three channels even in monochrome, where it is fed the gray.
+    let palette = shader.palette_active();
+    let channels = if s.dither_color > 0.5 || palette { 3 } else { 1 };
+    if s.dither_color < 0.5 { for v in values.iter_mut() { *v = [get_luminance(*v); 3]; } }
+
+    let kernel = kernel(s);
+    let strength = s.diffusion_strength.clamp(0.0, 1.0);
+    // Alpha cut to 1 bit takes its own error through the same kernel.
+    let mut alphas = cell_alphas(shader, cols, rows, d_scale);
+    let cut_alpha = s.alpha_dither > 0.5;
+
+    let mut cells = vec![[0u8; 4]; cols * rows];
+    for y in 0..rows {
+        // Reversed rows mirror the kernel so error still only reaches unvisited cells.
+        let reverse = s.diffusion_serpentine > 0.5 && y % 2 == 1;
+        for i in 0..cols {
+            let (x, dir) = if reverse { (cols - 1 - i, -1) } else { (i, 1) };
+            let old = values[y * cols + x];
+            let mut q = if palette { shader.palette_nearest(old) } else { [0.0; 3] };
+            let mut err = [0.0; 3];
+            for c in 0..channels {
+                if !palette { q[c] = quantize(old[c], s.posterize_levels, s.dither_threshold); }
+                err[c] = (old[c] - q[c]) * strength;
+                if s.diffusion_clamp > 0.0 { err[c] = err[c].clamp(-s.diffusion_clamp, s.diffusion_clamp); }
+            }
+            if channels == 1 { q = [q[0]; 3]; }
+            let alpha = alphas[y * cols + x];
+            let qa = if cut_alpha { quantize(alpha, 0.0, s.dither_threshold) } else { alpha };
+            let err_a = (alpha - qa) * strength;
+            for &(dx, dy, w) in kernel.taps {
+                let (nx, ny) = (x as i32 + dx * dir, y + dy as usize);
+                if nx < 0 || nx >= cols as i32 || ny >= rows { continue; }
+                let n = &mut values[ny * cols + nx as usize];
+                for c in 0..channels { n[c] += err[c] * w / kernel.divisor; }
+                if cut_alpha { alphas[ny * cols + nx as usize] += err_a * w / kernel.divisor; }
+            }
+            cells[y * cols + x] = encode_srgb8(shader.finish(q, qa));
+        }
+    }
+
+    RgbaImage::from_fn(width, height, |x, y| image::Rgba(cells[cell_of(y) * cols + cell_of(x)]))
+}
@@ -1,9 +1,43 @@
+pub mod cpu;
+pub mod diffusion;
+pub mod stipple;
+
 use bytemuck::{Pod, Zeroable};
 use wgpu::util::DeviceExt;
 use image::GenericImageView;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use crate::threshold::ThresholdMap;
+
+/// Display names of the dither algorithms, indexed by `ColorSettings::dither_type`.
+///
+/// 5-7 are per-pixel noise blends that only resemble diffusion; real error diffusion is
+/// [`DITHER_ERROR_DIFFUSION`], which runs on the CPU.
+pub const DITHER_NAMES: [&str; 13] = ["None", "Threshold", "Random", "Bayer", "Blue Noise", "Noise Blend", "Offset Noise", "Two-Level Noise", "Gradient Based", "Lattice Boltzmann", "Error Diffusion", "Halftone", "Stippling"];
+/// `dither_type` of the serial error-diffusion pass in [`diffusion`].
+pub const DITHER_ERROR_DIFFUSION: usize = 10;
+/// `dither_type` of the amplitude-modulated halftone screen.
+pub const DITHER_HALFTONE: usize = 11;
+/// `dither_type` of weighted Voronoi stippling in [`stipple`], also CPU-only.
+pub const DITHER_STIPPLING: usize = 12;
+/// Display names of the metrics palette matching can use, indexed by `ColorSettings::color_metric`.
+/// Weighted RGB scales linear channel differences by their luminance weight; the CIELAB ones use D65.
+pub const COLOR_METRIC_NAMES: [&str; 5] = ["Linear RGB", "Weighted RGB", "OKLab", "CIELAB ΔE76", "CIELAB ΔE2000"];
+/// Display names of the working spaces, indexed by `ColorSettings::adjust_space` and `dither_space`:
+/// linear light as [`decode_input`] holds it, or the gamma-encoded sRGB values.
+pub const WORKING_SPACE_NAMES: [&str; 2] = ["Linear", "sRGB Gamma"];
+/// Plate names for `ColorSettings::cmyk_plate` 1-4 (0 shows the composite).
+pub const CMYK_PLATES: [&str; 4] = ["Cyan", "Magenta", "Yellow", "Black"];
+/// Texels in the curves and gradient LUTs, fine enough that 16-bit sources do not band through them.
+pub const LUT_SIZE: usize = 4096;
+/// A curves or gradient LUT: [`LUT_SIZE`] RGBA texels in 0..1, uploaded as an `Rgba32Float` texture.
+pub type Lut = Vec<[f32; 4]>;
+/// Format of the input texture and of [`Pipeline::render_image_linear`] targets: half floats in linear light.
+pub const FLOAT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ColorSettings {
     // Block 1: Basic Light
     pub exposure: f32,
@@ -28,13 +62,38 @@ pub struct ColorSettings {
     // Block 5: Dither Style
     pub dither_color: f32,
     pub posterize_levels: f32,
-    pub bayer_size: f32, // 2 to 8
+    pub bayer_size: f32, // ordered matrix size, 2 to 64
     pub grad_enabled: f32,
-    // Block 6: Stipple & Padding
-    pub stipple_min_size: f32,
-    pub stipple_max_size: f32,
-    pub padding1: f32,
-    pub padding2: f32,
+    // Block 6: Stipple, Diffusion & Padding
+    pub stipple_min_size: f32, // dot area as a share of its Voronoi cell, in white areas
+    pub stipple_max_size: f32, // the same in full black
+    pub diffusion_kernel: f32, // index into diffusion::KERNELS
+    pub ordered_matrix: f32, // index into threshold::matrix::MATRIX_NAMES
+    // Block 7: Error Diffusion Texture
+    pub diffusion_serpentine: f32,
+    pub diffusion_strength: f32, // 0 to 1, share of the error passed on
+    pub diffusion_clamp: f32, // max error a pixel passes on, 0 = unclamped
+    pub blue_noise_size: f32, // 16 to 256
+    // Block 8: Halftone Screen
+    pub halftone_frequency: f32, // screen cells across the image width
+    pub halftone_angle: f32, // degrees
+    pub halftone_shape: f32, // index into threshold::halftone::SHAPE_NAMES
+    pub halftone_cmyk: f32, // separate into C, M, Y and K screens
+    // Block 9: CMYK Separation
+    pub cmyk_black: f32, // 0 to 1, share of the gray component moved to K
+    pub cmyk_black_start: f32, // gray level where K starts, 0 = full-range GCR
+    pub cmyk_plate: f32, // 0 = composite, 1-4 = one plate (see CMYK_PLATES)
+    pub color_metric: f32, // how the palette's nearest entries are picked, see COLOR_METRIC_NAMES
+    // Block 10: Stippling & Palette
+    pub stipple_points: f32,
+    pub stipple_iterations: f32, // Lloyd relaxation rounds
+    pub palette_enabled: f32, // dither to Look::palette instead of posterize_levels
+    pub padding3: f32,
+    // Block 11: Working Space & Alpha
+    pub adjust_space: f32, // space adjustments and curves run in, see WORKING_SPACE_NAMES
+    pub dither_space: f32, // space dither thresholds, levels and the gradient index are measured in
+    pub alpha_dither: f32, // cut alpha to 0 or 1 with the color's dither noise, for 1-bit cutouts
+    pub alpha_premultiply: f32, // write color multiplied by alpha instead of straight
 }
 
 impl Default for ColorSettings {
@@ -62,14 +121,73 @@ impl Default for ColorSettings {
             grad_enabled: 0.0,
             stipple_min_size: 0.0,
             stipple_max_size: 1.0,
-            padding1: 0.0,
-            padding2: 0.0,
+            diffusion_kernel: 0.0,
+            ordered_matrix: 0.0,
+            diffusion_serpentine: 0.0,
+            diffusion_strength: 1.0,
+            diffusion_clamp: 0.0,
+            blue_noise_size: 64.0,
+            halftone_frequency: 80.0,
+            halftone_angle: 45.0,
+            halftone_shape: 0.0,
+            halftone_cmyk: 0.0,
+            cmyk_black: 1.0,
+            cmyk_black_start: 0.0,
+            cmyk_plate: 0.0,
+            color_metric: 0.0,
+            stipple_points: 4000.0,
+            stipple_iterations: 30.0,
+            palette_enabled: 0.0,
+            padding3: 0.0,
+            adjust_space: 0.0,
+            dither_space: 0.0,
+            alpha_dither: 0.0,
+            alpha_premultiply: 0.0,
         }
     }
 }
 
+impl ColorSettings {
+    /// True when halftone separates into CMYK plates.
+    pub fn is_cmyk(&self) -> bool {
+        self.dither_enabled > 0.5 && self.dither_type as usize == DITHER_HALFTONE && self.halftone_cmyk > 0.5
+    }
+
+    /// Settings that render CMYK plate `idx` (an index into [`CMYK_PLATES`]) alone, ink black on
+    /// white and without the gradient remap, for exporting separations.
+    pub fn with_cmyk_plate(&self, idx: usize) -> Self {
+        Self { cmyk_plate: idx as f32 + 1.0, grad_enabled: 0.0, ..*self }
+    }
+
+    /// Settings for on-screen display: egui blends textures as premultiplied, so the preview is
+    /// premultiplied whatever the export writes.
+    pub fn with_premultiplied_alpha(&self) -> Self {
+        Self { alpha_premultiply: 1.0, ..*self }
+    }
+
+    /// Settings that stop after adjustments and curves, with no dithering, posterizing, palette or
+    /// gradient remap: the adjusted image the scopes measure before the dither stage.
+    pub fn without_dither(&self) -> Self {
+        Self { dither_enabled: 0.0, posterize_levels: 0.0, palette_enabled: 0.0, grad_enabled: 0.0, alpha_dither: 0.0, ..*self }
+    }
+
+    /// True when the look needs a pass the fragment shader cannot do (serial error diffusion,
+    /// stippling), so the output has to come from [`cpu::render`] instead of [`Pipeline::render`].
+    pub fn needs_cpu_pass(&self) -> bool {
+        self.dither_enabled > 0.5 && matches!(self.dither_type as usize, DITHER_ERROR_DIFFUSION | DITHER_STIPPLING)
+    }
+
+    /// True when the output is continuous tone, so writing more than 8 bits per channel keeps
+    /// detail. Dithered output only holds a few levels and always exports at 8 bits.
+    pub fn allows_deep_output(&self) -> bool {
+        self.dither_enabled < 0.5
+    }
+}
+
 pub struct Pipeline {
     pub pipeline: Option<wgpu::RenderPipeline>,
+    /// The same shader writing to a [`FLOAT_FORMAT`] target, for 16-bit export.
+    pub linear_pipeline: Option<wgpu::RenderPipeline>,
     pub bind_group_layout: Option<wgpu::BindGroupLayout>,
     pub bind_group: Option<wgpu::BindGroup>, // New: Cached bind group
     pub uniform_buffer: Option<wgpu::Buffer>, // New: Uniform buffer
@@ -79,6 +197,12 @@ pub struct Pipeline {
     pub curves_view: Option<wgpu::TextureView>,
     pub gradient_texture: Option<wgpu::Texture>,
     pub gradient_view: Option<wgpu::TextureView>,
+    pub palette_texture: Option<wgpu::Texture>,
+    pub palette_view: Option<wgpu::TextureView>,
+    pub threshold_texture: Option<wgpu::Texture>,
+    pub threshold_view: Option<wgpu::TextureView>,
+    /// The map currently in `threshold_texture`, to skip re-uploading it every frame.
+    pub threshold_map: Option<Arc<ThresholdMap>>,
 }
 
 #[repr(C)]
@@ -95,10 +219,17 @@ const VERTICES: &[Vertex] = &[
     Vertex { position: [1.0, -1.0], tex_coords: [1.0, 1.0] },
 ];
 
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Pipeline {
     pub fn new() -> Self {
         Self {
             pipeline: None,
+            linear_pipeline: None,
             bind_group_layout: None,
             bind_group: None,
             uniform_buffer: None,
@@ -108,32 +239,55 @@ impl Pipeline {
             curves_view: None,
             gradient_texture: None,
             gradient_view: None,
+            palette_texture: None,
+            palette_view: None,
+            threshold_texture: None,
+            threshold_view: None,
+            threshold_map: None,
         }
     }
 
     pub fn init(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
         let shader = device.create_shader_module(wgpu::include_wgsl!("shaders.wgsl"));
 
-        // Create 1D Curves texture (256x1)
+        // Curves LUT (LUT_SIZE x 1), floats so 16-bit input keeps its precision through the curves.
         let curves_texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("curves_texture"),
             size: wgpu::Extent3d {
-                width: 256,
+                width: LUT_SIZE as u32,
                 height: 1,
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
+            format: wgpu::TextureFormat::Rgba32Float,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
         });
         let curves_view = curves_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-        // Create 1D Gradient texture (256x1)
+        // Gradient LUT (LUT_SIZE x 1), read with textureLoad like the curves.
         let gradient_texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("gradient_texture"),
+            size: wgpu::Extent3d {
+                width: LUT_SIZE as u32,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let gradient_view = gradient_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Palette texture (256x1). Entries stay sRGB-encoded and the shader decodes them with the same
+        // formula as the CPU, since hardware sRGB decoding is slightly off and palette picks are often near-ties.
+        let palette_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("palette_texture"),
             size: wgpu::Extent3d {
                 width: 256,
                 height: 1,
@@ -146,7 +300,11 @@ impl Pipeline {
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
         });
-        let gradient_view = gradient_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let palette_view = palette_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Threshold map, read with textureLoad. Holds zeros until update_threshold_map is called.
+        let threshold_texture = Self::create_threshold_texture(device, 1, 1);
+        let threshold_view = threshold_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("bind_group_layout"),
@@ -181,7 +339,7 @@ impl Pipeline {
                     binding: 3,
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
                         view_dimension: wgpu::TextureViewDimension::D2,
                         multisampled: false,
                     },
@@ -190,6 +348,26 @@ impl Pipeline {
                 wgpu::BindGroupLayoutEntry {
                     binding: 4,
                     visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
                         sample_type: wgpu::TextureSampleType::Float { filterable: true },
                         view_dimension: wgpu::TextureViewDimension::D2,
@@ -206,7 +384,7 @@ impl Pipeline {
             push_constant_ranges: &[],
         });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let create_render_pipeline = |format: wgpu::TextureFormat| device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("render_pipeline"),
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
@@ -246,6 +424,8 @@ impl Pipeline {
             multisample: wgpu::MultisampleState::default(),
             multiview: None,
         });
+        let render_pipeline = create_render_pipeline(format);
+        let linear_pipeline = create_render_pipeline(FLOAT_FORMAT);
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("vertex_buffer"),
@@ -277,7 +457,7 @@ impl Pipeline {
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&curves_view), // Placeholder
+                    resource: wgpu::BindingResource::TextureView(&palette_view), // Placeholder, any filterable view
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
@@ -295,12 +475,21 @@ impl Pipeline {
                     binding: 4,
                     resource: wgpu::BindingResource::TextureView(&gradient_view),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(&threshold_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(&palette_view),
+                },
             ],
             label: Some("initial_bind_group"),
         });
 
 
         self.pipeline = Some(render_pipeline);
+        self.linear_pipeline = Some(linear_pipeline);
         self.bind_group_layout = Some(bind_group_layout);
         self.sampler = Some(sampler);
         self.vertex_buffer = Some(vertex_buffer);
@@ -308,11 +497,15 @@ impl Pipeline {
         self.curves_view = Some(curves_view);
         self.gradient_texture = Some(gradient_texture);
         self.gradient_view = Some(gradient_view);
+        self.palette_texture = Some(palette_texture);
+        self.palette_view = Some(palette_view);
+        self.threshold_texture = Some(threshold_texture);
+        self.threshold_view = Some(threshold_view);
         self.uniform_buffer = Some(uniform_buffer); // Cache uniform buffer
         self.bind_group = Some(bind_group); // Cache bind group
     }
 
-    pub fn update_curves(&self, queue: &wgpu::Queue, data: &[u8; 1024]) {
+    pub fn update_curves(&self, queue: &wgpu::Queue, data: &[[f32; 4]]) {
         if let Some(texture) = &self.curves_texture {
             queue.write_texture(
                 wgpu::ImageCopyTexture {
@@ -321,14 +514,14 @@ impl Pipeline {
                     origin: wgpu::Origin3d::ZERO,
                     aspect: wgpu::TextureAspect::All,
                 },
-                data,
+                bytemuck::cast_slice(data),
                 wgpu::ImageDataLayout {
                     offset: 0,
-                    bytes_per_row: Some(1024),
+                    bytes_per_row: Some(16 * LUT_SIZE as u32),
                     rows_per_image: Some(1),
                 },
                 wgpu::Extent3d {
-                    width: 256,
+                    width: LUT_SIZE as u32,
                     height: 1,
                     depth_or_array_layers: 1,
                 },
@@ -336,8 +529,32 @@ impl Pipeline {
         }
     }
 
-    pub fn update_gradient(&self, queue: &wgpu::Queue, data: &[u8; 1024]) {
+    pub fn update_gradient(&self, queue: &wgpu::Queue, data: &[[f32; 4]]) {
         if let Some(texture) = &self.gradient_texture {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                bytemuck::cast_slice(data),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(16 * LUT_SIZE as u32),
+                    rows_per_image: Some(1),
+                },
+                wgpu::Extent3d {
+                    width: LUT_SIZE as u32,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    pub fn update_palette(&self, queue: &wgpu::Queue, data: &[u8; 1024]) {
+        if let Some(texture) = &self.palette_texture {
             queue.write_texture(
                 wgpu::ImageCopyTexture {
                     texture,
@@ -360,8 +577,42 @@ impl Pipeline {
         }
     }
 
+    fn create_threshold_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("threshold_texture"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    }
+
+    /// Uploads `map` to `threshold_texture`, resizing it when needed. Does nothing if `map` is
+    /// already the bound one.
+    pub fn update_threshold_map(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, map: &Arc<ThresholdMap>) {
+        if self.threshold_map.as_ref().is_some_and(|m| Arc::ptr_eq(m, map)) { return; }
+        let size = wgpu::Extent3d { width: map.width, height: map.height, depth_or_array_layers: 1 };
+        if self.threshold_texture.as_ref().map(|t| t.size()) != Some(size) {
+            let texture = Self::create_threshold_texture(device, map.width, map.height);
+            self.threshold_view = Some(texture.create_view(&wgpu::TextureViewDescriptor::default()));
+            self.threshold_texture = Some(texture);
+        }
+        if let Some(texture) = &self.threshold_texture {
+            queue.write_texture(
+                wgpu::ImageCopyTexture { texture, mip_level: 0, origin: wgpu::Origin3d::ZERO, aspect: wgpu::TextureAspect::All },
+                bytemuck::cast_slice(&map.values),
+                wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(4 * map.width), rows_per_image: Some(map.height) },
+                size,
+            );
+        }
+        self.threshold_map = Some(map.clone());
+    }
+
     pub fn create_texture_from_image(&self, device: &wgpu::Device, queue: &wgpu::Queue, img: &image::DynamicImage) -> wgpu::Texture {
-        let rgba = img.to_rgba8();
+        let texels: Vec<[u16; 4]> = decode_input(img).iter().map(|t| t.map(half::f16::to_bits)).collect();
         let dimensions = img.dimensions();
 
         let texture_size = wgpu::Extent3d {
@@ -376,7 +627,7 @@ impl Pipeline {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format: FLOAT_FORMAT,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
         });
@@ -388,10 +639,10 @@ impl Pipeline {
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            &rgba,
+            bytemuck::cast_slice(&texels),
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(4 * dimensions.0),
+                bytes_per_row: Some(8 * dimensions.0),
                 rows_per_image: Some(dimensions.1),
             },
             texture_size,
@@ -400,6 +651,16 @@ impl Pipeline {
         texture
     }
 
+    /// Writes a CPU-rendered frame into `texture` (which needs `COPY_DST`), in place of [`Pipeline::render`].
+    pub fn upload_image(&self, queue: &wgpu::Queue, texture: &wgpu::Texture, img: &image::RgbaImage) {
+        queue.write_texture(
+            wgpu::ImageCopyTexture { texture, mip_level: 0, origin: wgpu::Origin3d::ZERO, aspect: wgpu::TextureAspect::All },
+            img,
+            wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(4 * img.width()), rows_per_image: Some(img.height()) },
+            wgpu::Extent3d { width: img.width(), height: img.height(), depth_or_array_layers: 1 },
+        );
+    }
+
     pub fn render(
         &self,
         device: &wgpu::Device,
@@ -408,12 +669,17 @@ impl Pipeline {
         output_texture_view: &wgpu::TextureView,
         settings: &ColorSettings,
     ) {
-        let pipeline = self.pipeline.as_ref().unwrap();
+        self.render_with(self.pipeline.as_ref().unwrap(), device, queue, input_texture_view, output_texture_view, settings);
+    }
+
+    fn render_with(&self, pipeline: &wgpu::RenderPipeline, device: &wgpu::Device, queue: &wgpu::Queue, input_texture_view: &wgpu::TextureView, output_texture_view: &wgpu::TextureView, settings: &ColorSettings) {
         let bind_group_layout = self.bind_group_layout.as_ref().unwrap();
         let sampler = self.sampler.as_ref().unwrap();
         let vertex_buffer = self.vertex_buffer.as_ref().unwrap();
         let curves_view = self.curves_view.as_ref().unwrap();
         let gradient_view = self.gradient_view.as_ref().unwrap();
+        let threshold_view = self.threshold_view.as_ref().unwrap();
+        let palette_view = self.palette_view.as_ref().unwrap();
         let uniform_buffer = self.uniform_buffer.as_ref().unwrap(); // Use cached uniform buffer
 
         // Update the uniform buffer with new settings
@@ -447,6 +713,14 @@ impl Pipeline {
                     binding: 4,
                     resource: wgpu::BindingResource::TextureView(gradient_view),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(threshold_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(palette_view),
+                },
             ],
             label: Some("bind_group"),
         });
@@ -479,4 +753,96 @@ impl Pipeline {
 
         queue.submit(std::iter::once(encoder.finish()));
     }
+
+    /// Uploads `img`, renders it with `settings` into an offscreen texture and reads the result back.
+    pub fn render_image(&self, device: &wgpu::Device, queue: &wgpu::Queue, img: &image::DynamicImage, settings: &ColorSettings, format: wgpu::TextureFormat) -> Option<image::RgbaImage> {
+        let output_tex = self.render_offscreen(self.pipeline.as_ref().unwrap(), device, queue, img, settings, format);
+        read_back_texture(device, queue, &output_tex)
+    }
+
+    /// Like [`Pipeline::render_image`], but keeps the clamped linear output at half-float precision
+    /// instead of encoding it to 8-bit sRGB.
+    pub fn render_image_linear(&self, device: &wgpu::Device, queue: &wgpu::Queue, img: &image::DynamicImage, settings: &ColorSettings) -> Option<image::Rgba32FImage> {
+        let output_tex = self.render_offscreen(self.linear_pipeline.as_ref().unwrap(), device, queue, img, settings, FLOAT_FORMAT);
+        read_back_texture_linear(device, queue, &output_tex)
+    }
+
+    fn render_offscreen(&self, pipeline: &wgpu::RenderPipeline, device: &wgpu::Device, queue: &wgpu::Queue, img: &image::DynamicImage, settings: &ColorSettings, format: wgpu::TextureFormat) -> wgpu::Texture {
+        let input_tex = self.create_texture_from_image(device, queue, img);
+        let output_tex = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen_output_texture"),
+            size: input_tex.size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        self.render_with(pipeline, device, queue, &input_tex.create_view(&wgpu::TextureViewDescriptor::default()), &output_tex.create_view(&wgpu::TextureViewDescriptor::default()), settings);
+        output_tex
+    }
+}
+
+/// The input image in linear light, as the input texture holds it. 8- and 16-bit images are
+/// sRGB-decoded; float images (EXR, HDR) are already linear and only lose negative values.
+/// Alpha stays straight.
+pub fn decode_input(img: &image::DynamicImage) -> Vec<[half::f16; 4]> {
+    use image::DynamicImage::*;
+    let f16 = |v: f32| half::f16::from_f32(v.min(half::f16::MAX.to_f32()));
+    match img {
+        ImageLuma8(_) | ImageLumaA8(_) | ImageRgb8(_) | ImageRgba8(_) => {
+            let decode: Vec<half::f16> = (0..256).map(|i| f16(cpu::srgb_to_linear(i as f32 / 255.0))).collect();
+            img.to_rgba8().pixels().map(|p| [decode[p[0] as usize], decode[p[1] as usize], decode[p[2] as usize], f16(p[3] as f32 / 255.0)]).collect()
+        }
+        ImageRgb32F(_) | ImageRgba32F(_) => img.to_rgba32f().pixels().map(|p| [f16(p[0].max(0.0)), f16(p[1].max(0.0)), f16(p[2].max(0.0)), f16(p[3].clamp(0.0, 1.0))]).collect(),
+        _ => {
+            let decode: Vec<half::f16> = (0..=u16::MAX).map(|i| f16(cpu::srgb_to_linear(i as f32 / 65535.0))).collect();
+            img.to_rgba16().pixels().map(|p| [decode[p[0] as usize], decode[p[1] as usize], decode[p[2] as usize], f16(p[3] as f32 / 65535.0)]).collect()
+        }
+    }
+}
+
+/// Requests a device without a window surface, for batch rendering outside the app.
+pub fn request_headless_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        compatible_surface: None,
+        force_fallback_adapter: false,
+    }))?;
+    log::debug!("Headless adapter: {:?}", adapter.get_info());
+    pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor { label: Some("headless_device"), required_features: wgpu::Features::empty(), required_limits: wgpu::Limits::default() }, None)).ok()
+}
+
+/// Copies an RGBA8 texture into a CPU image, stripping the row padding wgpu requires.
+pub fn read_back_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Option<image::RgbaImage> {
+    image::RgbaImage::from_raw(texture.width(), texture.height(), read_back_bytes(device, queue, texture, 4)?)
+}
+
+/// Copies a [`FLOAT_FORMAT`] texture into a float CPU image.
+pub fn read_back_texture_linear(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Option<image::Rgba32FImage> {
+    let bytes = read_back_bytes(device, queue, texture, 8)?;
+    let texels = bytes.chunks_exact(2).map(|b| half::f16::from_le_bytes([b[0], b[1]]).to_f32()).collect();
+    image::Rgba32FImage::from_raw(texture.width(), texture.height(), texels)
+}
+
+fn read_back_bytes(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, bytes_per_pixel: u32) -> Option<Vec<u8>> {
+    let width = texture.width(); let height = texture.height();
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT; let unpadded = width * bytes_per_pixel; let padded = unpadded + (align - unpadded % align) % align;
+    let staging = device.create_buffer(&wgpu::BufferDescriptor { label: Some("readback_staging"), size: (padded * height) as u64, usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("readback_enc") });
+    encoder.copy_texture_to_buffer(wgpu::ImageCopyTexture { texture, mip_level: 0, origin: wgpu::Origin3d::ZERO, aspect: wgpu::TextureAspect::All }, wgpu::ImageCopyBuffer { buffer: &staging, layout: wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(padded), rows_per_image: Some(height) } }, wgpu::Extent3d { width, height, depth_or_array_layers: 1 });
+    queue.submit(Some(encoder.finish()));
+    let slice = staging.slice(..); let (tx, rx) = std::sync::mpsc::channel(); slice.map_async(wgpu::MapMode::Read, move |v| tx.send(v).unwrap()); device.poll(wgpu::Maintain::Wait);
+    if let Ok(Ok(())) = rx.recv() {
+        let data = slice.get_mapped_range(); let mut pixels = Vec::with_capacity((unpadded * height) as usize);
+        for row in 0..height {
+            let start = (row * padded) as usize;
+            pixels.extend_from_slice(&data[start..start + unpadded as usize]);
+        }
+        drop(data); staging.unmap();
+        return Some(pixels);
+    }
+    None
 }
@@ -23,7 +23,12 @@ struct ColorSettings {
     saturation: f32, vibrance: f32, sharpness: f32, brightness: f32,
     dither_enabled: f32, dither_type: f32, dither_scale: f32, dither_threshold: f32,
     dither_color: f32, posterize_levels: f32, bayer_size: f32, grad_enabled: f32,
-    stipple_min_size: f32, stipple_max_size: f32, padding1: f32, padding2: f32,
+    stipple_min_size: f32, stipple_max_size: f32, diffusion_kernel: f32, ordered_matrix: f32,
+    diffusion_serpentine: f32, diffusion_strength: f32, diffusion_clamp: f32, blue_noise_size: f32,
+    halftone_frequency: f32, halftone_angle: f32, halftone_shape: f32, halftone_cmyk: f32,
+    cmyk_black: f32, cmyk_black_start: f32, cmyk_plate: f32, color_metric: f32,
+    stipple_points: f32, stipple_iterations: f32, palette_enabled: f32, padding3: f32,
+    adjust_space: f32, dither_space: f32, alpha_dither: f32, alpha_premultiply: f32,
 };
 
 @group(0) @binding(0) var t_diffuse: texture_2d<f32>;
@@ -31,11 +36,39 @@ struct ColorSettings {
 @group(0) @binding(2) var<uniform> settings: ColorSettings;
 @group(0) @binding(3) var t_curves: texture_2d<f32>;
 @group(0) @binding(4) var t_gradient: texture_2d<f32>;
+@group(0) @binding(5) var t_threshold: texture_2d<f32>;
+@group(0) @binding(6) var t_palette: texture_2d<f32>;
+
+// Nearest texel of a float LUT (`t_curves`, `t_gradient`) for `u` in 0..1.
+fn sample_lut(lut: texture_2d<f32>, u: f32) -> vec3<f32> {
+    let n = textureDimensions(lut).x;
+    let i = u32(clamp(floor(u * f32(n)), 0.0, f32(n - 1u)));
+    return textureLoad(lut, vec2<u32>(i, 0u), 0).rgb;
+}
 
 fn get_luminance(color: vec3<f32>) -> f32 {
     return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
 }
 
+fn srgb_to_linear3(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((max(c, vec3<f32>(0.04045)) + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+fn linear_to_srgb3(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(max(c, vec3<f32>(0.0031308)), vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+// A linear color in working space `space` (0 linear, 1 sRGB gamma).
+fn to_space(c: vec3<f32>, space: f32) -> vec3<f32> {
+    if (space > 0.5) { return linear_to_srgb3(c); }
+    return c;
+}
+
+fn from_space(c: vec3<f32>, space: f32) -> vec3<f32> {
+    if (space > 0.5) { return srgb_to_linear3(c); }
+    return c;
+}
+
 fn hash22(p: vec2<f32>) -> vec2<f32> {
     var p3 = fract(vec3<f32>(p.xyx) * vec3<f32>(0.1031, 0.1030, 0.0973));
     p3 += dot(p3, p3.yzx + 33.33);
@@ -46,48 +79,17 @@ fn white_noise(p: vec2<f32>) -> f32 {
     return hash22(p).x;
 }
 
+// Threshold map tiled across dither cells (ordered matrix for type 3, blue noise for type 4).
+fn get_threshold_map(p: vec2<f32>) -> f32 {
+    let dims = textureDimensions(t_threshold);
+    return textureLoad(t_threshold, vec2<u32>(p) % dims, 0).r;
+}
+
 fn interleaved_gradient_noise(p: vec2<f32>) -> f32 {
     let magic = vec3<f32>(0.06711056, 0.00583715, 52.9829189);
     return fract(magic.z * fract(dot(p, magic.xy)));
 }
 
-// BAYER MATRICES
-var<private> bayer2: array<f32, 4> = array<f32, 4>(0.0, 0.5, 0.75, 0.25);
-var<private> bayer3: array<f32, 9> = array<f32, 9>(
-    0.0, 0.777, 0.333,
-    0.555, 0.111, 0.888,
-    0.222, 0.666, 0.444
-);
-var<private> bayer4: array<f32, 16> = array<f32, 16>(
-    0.0, 0.5, 0.125, 0.625,
-    0.75, 0.25, 0.875, 0.375,
-    0.1875, 0.6875, 0.0625, 0.5625,
-    0.9375, 0.4375, 0.8125, 0.3125
-);
-var<private> bayer8: array<u32, 64> = array<u32, 64>(
-    0u, 32u, 8u, 40u, 2u, 34u, 10u, 42u,
-    48u, 16u, 56u, 24u, 50u, 18u, 58u, 26u,
-    12u, 44u, 4u, 36u, 14u, 46u, 6u, 38u,
-    60u, 28u, 52u, 20u, 62u, 30u, 54u, 22u,
-    3u, 35u, 11u, 43u, 1u, 33u, 9u, 41u,
-    51u, 19u, 59u, 27u, 49u, 17u, 57u, 25u,
-    15u, 47u, 7u, 39u, 13u, 45u, 5u, 37u,
-    63u, 31u, 55u, 23u, 61u, 29u, 53u, 21u
-);
-
-fn get_bayer_threshold(p: vec2<f32>, size: i32) -> f32 {
-    let s = u32(size);
-    let x = u32(p.x) % s;
-    let y = u32(p.y) % s;
-    let idx = y * s + x;
-    
-    if (size == 2) { return bayer2[idx]; }
-    if (size == 3) { return bayer3[idx]; }
-    if (size == 4) { return bayer4[idx]; }
-    if (size == 8) { return f32(bayer8[idx]) / 64.0; }
-    return 0.5;
-}
-
 fn apply_dither_step(val: f32, noise: f32, levels: f32) -> f32 {
     if (levels > 1.5) {
         let lv = levels - 1.0;
@@ -101,6 +103,115 @@ fn apply_dither_step(val: f32, noise: f32, levels: f32) -> f32 {
     }
 }
 
+// Palette dithering is on and the palette has at least one entry.
+fn palette_active() -> bool {
+    return settings.palette_enabled > 0.5 && textureLoad(t_palette, vec2<u32>(0u, 0u), 0).a > 0.5;
+}
+
+fn cbrt(x: f32) -> f32 {
+    return sign(x) * pow(abs(x), 1.0 / 3.0);
+}
+
+fn linear_to_oklab(c: vec3<f32>) -> vec3<f32> {
+    let lms = vec3<f32>(cbrt(dot(c, vec3<f32>(0.41222147, 0.53633254, 0.051445993))), cbrt(dot(c, vec3<f32>(0.2119035, 0.6806995, 0.10739696))), cbrt(dot(c, vec3<f32>(0.08830246, 0.28171884, 0.6299787))));
+    return vec3<f32>(dot(lms, vec3<f32>(0.21045426, 0.7936178, -0.004072047)), dot(lms, vec3<f32>(1.9779985, -2.4285922, 0.4505937)), dot(lms, vec3<f32>(0.025904037, 0.78277177, -0.80867577)));
+}
+
+fn lab_f(t: f32) -> f32 {
+    if (t > 0.008856452) { return cbrt(t); }
+    return t * 7.787037 + 0.13793103;
+}
+
+// CIELAB relative to the D65 white point.
+fn linear_to_lab(c: vec3<f32>) -> vec3<f32> {
+    let fx = lab_f(dot(c, vec3<f32>(0.4124564, 0.3575761, 0.1804375)) / 0.95047);
+    let fy = lab_f(dot(c, vec3<f32>(0.2126729, 0.7151522, 0.072175)));
+    let fz = lab_f(dot(c, vec3<f32>(0.0193339, 0.119192, 0.9503041)) / 1.08883);
+    return vec3<f32>(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz));
+}
+
+fn hue_degrees(b: f32, a: f32) -> f32 {
+    if (a == 0.0 && b == 0.0) { return 0.0; }
+    let h = degrees(atan2(b, a));
+    if (h < 0.0) { return h + 360.0; }
+    return h;
+}
+
+// CIEDE2000 between two CIELAB colors (Sharma, Wu & Dalal 2005).
+fn delta_e2000(lab1: vec3<f32>, lab2: vec3<f32>) -> f32 {
+    let c7 = pow((length(lab1.yz) + length(lab2.yz)) * 0.5, 7.0);
+    let g = 0.5 * (1.0 - sqrt(c7 / (c7 + 6103515625.0)));
+    let a1 = lab1.y * (1.0 + g);
+    let a2 = lab2.y * (1.0 + g);
+    let c1 = length(vec2<f32>(a1, lab1.z));
+    let c2 = length(vec2<f32>(a2, lab2.z));
+    let h1 = hue_degrees(lab1.z, a1);
+    let h2 = hue_degrees(lab2.z, a2);
+    var dh = 0.0;
+    var h_bar = h1 + h2;
+    if (c1 * c2 != 0.0) {
+        dh = h2 - h1;
+        if (dh > 180.0) { dh -= 360.0; } else if (dh < -180.0) { dh += 360.0; }
+        if (abs(h1 - h2) <= 180.0) { h_bar = (h1 + h2) * 0.5; }
+        else if (h1 + h2 < 360.0) { h_bar = (h1 + h2 + 360.0) * 0.5; }
+        else { h_bar = (h1 + h2 - 360.0) * 0.5; }
+    }
+    let d_l = lab2.x - lab1.x;
+    let d_c = c2 - c1;
+    let d_h = 2.0 * sqrt(c1 * c2) * sin(radians(dh) * 0.5);
+    let l_bar = (lab1.x + lab2.x) * 0.5 - 50.0;
+    let c_bar = (c1 + c2) * 0.5;
+    let t = 1.0 - 0.17 * cos(radians(h_bar - 30.0)) + 0.24 * cos(radians(2.0 * h_bar)) + 0.32 * cos(radians(3.0 * h_bar + 6.0)) - 0.2 * cos(radians(4.0 * h_bar - 63.0));
+    let c_bar7 = pow(c_bar, 7.0);
+    let r_t = -2.0 * sqrt(c_bar7 / (c_bar7 + 6103515625.0)) * sin(radians(60.0 * exp(-pow((h_bar - 275.0) / 25.0, 2.0))));
+    let s_l = 1.0 + 0.015 * l_bar * l_bar / sqrt(20.0 + l_bar * l_bar);
+    let s_c = 1.0 + 0.045 * c_bar;
+    let s_h = 1.0 + 0.015 * c_bar * t;
+    return sqrt(pow(d_l / s_l, 2.0) + pow(d_c / s_c, 2.0) + pow(d_h / s_h, 2.0) + r_t * (d_c / s_c) * (d_h / s_h));
+}
+
+// A linear color in the space `color_metric` measures in; see `metric_distance`.
+fn to_metric_space(c: vec3<f32>) -> vec3<f32> {
+    let m = settings.color_metric;
+    if (m > 2.5) { return linear_to_lab(c); }
+    if (m > 1.5) { return linear_to_oklab(c); }
+    if (m > 0.5) { return c * sqrt(vec3<f32>(0.2126, 0.7152, 0.0722)); }
+    return c;
+}
+
+// Distance between two colors already in `to_metric_space`: Euclidean, except for CIEDE2000.
+fn metric_distance(a: vec3<f32>, b: vec3<f32>) -> f32 {
+    if (settings.color_metric > 3.5) { return delta_e2000(a, b); }
+    return distance(a, b);
+}
+
+// Picks between the two palette entries nearest to `color` under `color_metric`: the second one wins
+// when the color's projection onto the segment between them lies past `noise`, so a dither pattern
+// mixes the two in the right proportion. `color`, the projection and the result are in
+// `dither_space`. A `noise` of 1 or more always gives the nearest entry.
+fn palette_dither(color: vec3<f32>, noise: f32) -> vec3<f32> {
+    let target_m = to_metric_space(from_space(color, settings.dither_space));
+    var best = vec3<f32>(0.0);
+    var second = vec3<f32>(0.0);
+    var best_d = 1e30;
+    var second_d = 1e30;
+    for (var i = 0u; i < 256u; i++) {
+        let texel = textureLoad(t_palette, vec2<u32>(i, 0u), 0);
+        if (texel.a < 0.5) { break; }
+        let entry = srgb_to_linear3(texel.rgb);
+        let d = metric_distance(target_m, to_metric_space(entry));
+        if (d < best_d) { second = best; second_d = best_d; best = entry; best_d = d; }
+        else if (d < second_d) { second = entry; second_d = d; }
+    }
+    best = to_space(best, settings.dither_space);
+    if (second_d >= 1e30) { return best; }
+    second = to_space(second, settings.dither_space);
+    let axis = second - best;
+    let t = clamp(dot(color - best, axis) / max(dot(axis, axis), 1e-8), 0.0, 1.0);
+    if (t > noise) { return second; }
+    return best;
+}
+
 fn apply_adjustments(in_color: vec3<f32>, uv: vec2<f32>, tex_size: vec2<f32>) -> vec3<f32> {
     var color = in_color;
     
@@ -129,13 +240,101 @@ fn apply_adjustments(in_color: vec3<f32>, uv: vec2<f32>, tex_size: vec2<f32>) ->
     color = mix(vec3<f32>(l_pre_sat), color, settings.saturation + settings.vibrance * (1.0 - color_sat));
 
     // 5. RGB Curves
-    color.r = textureSampleLevel(t_curves, s_diffuse, vec2<f32>(clamp(color.r, 0.0, 1.0), 0.5), 0.0).r;
-    color.g = textureSampleLevel(t_curves, s_diffuse, vec2<f32>(clamp(color.g, 0.0, 1.0), 0.5), 0.0).g;
-    color.b = textureSampleLevel(t_curves, s_diffuse, vec2<f32>(clamp(color.b, 0.0, 1.0), 0.5), 0.0).b;
+    color.r = sample_lut(t_curves, clamp(color.r, 0.0, 1.0)).r;
+    color.g = sample_lut(t_curves, clamp(color.g, 0.0, 1.0)).g;
+    color.b = sample_lut(t_curves, clamp(color.b, 0.0, 1.0)).b;
     
     return color;
 }
 
+// The input at `uv` in `adjust_space`.
+fn sample_input(uv: vec2<f32>) -> vec3<f32> {
+    return to_space(textureSampleLevel(t_diffuse, s_diffuse, uv, 0.0).rgb, settings.adjust_space);
+}
+
+// Sharpening plus adjustments at `uv`, run in `adjust_space` and handed over in `dither_space`: the
+// color the dither stage starts from. Explicit LOD so halftone can call it from its averaging loop.
+fn adjusted_color(uv: vec2<f32>, tex_size: vec2<f32>) -> vec3<f32> {
+    var color = sample_input(uv);
+    
+    if (settings.sharpness > 0.0) {
+        let dx = 1.0 / tex_size.x;
+        let dy = 1.0 / tex_size.y;
+        let laplacian = (sample_input(uv + vec2<f32>(-dx, 0.0)) + sample_input(uv + vec2<f32>(dx, 0.0)) + sample_input(uv + vec2<f32>(0.0, -dy)) + sample_input(uv + vec2<f32>(0.0, dy)) - 4.0 * color);
+        color = color - settings.sharpness * laplacian;
+    }
+
+    color = apply_adjustments(color, uv, tex_size);
+    if (settings.adjust_space == settings.dither_space) { return color; }
+    return to_space(from_space(color, settings.adjust_space), settings.dither_space);
+}
+
+// Halftone samples per cell side; the cell's tone is the mean of HALFTONE_SAMPLES^2 adjusted colors.
+const HALFTONE_SAMPLES: i32 = 4;
+
+// Mean adjusted color of screen cell `cell`, whose screen space is image space rotated by `rot`
+// (cos, sin) and scaled by `cell_size` pixels.
+fn halftone_average(cell: vec2<f32>, rot: vec2<f32>, cell_size: f32, tex_size: vec2<f32>) -> vec3<f32> {
+    var sum = vec3<f32>(0.0);
+    for (var j = 0; j < HALFTONE_SAMPLES; j++) {
+        for (var i = 0; i < HALFTONE_SAMPLES; i++) {
+            let q = (cell + (vec2<f32>(f32(i), f32(j)) + 0.5) / f32(HALFTONE_SAMPLES)) * cell_size;
+            let p = vec2<f32>(rot.x * q.x - rot.y * q.y, rot.y * q.x + rot.x * q.y);
+            sum += adjusted_color(p / tex_size, tex_size);
+        }
+    }
+    return sum / f32(HALFTONE_SAMPLES * HALFTONE_SAMPLES);
+}
+
+fn halftone_cell_size(tex_size: vec2<f32>) -> f32 {
+    return tex_size.x / clamp(settings.halftone_frequency, 1.0, tex_size.x);
+}
+
+fn halftone_rot(angle: f32) -> vec2<f32> {
+    let a = radians(angle);
+    return vec2<f32>(cos(a), sin(a));
+}
+
+// Image point `p` in the screen rotated by `rot`, where whole numbers are cell corners.
+fn to_screen(p: vec2<f32>, rot: vec2<f32>, cell_size: f32) -> vec2<f32> {
+    return vec2<f32>(rot.x * p.x + rot.y * p.y, rot.x * p.y - rot.y * p.x) / cell_size;
+}
+
+// The spot map stretched over one screen cell.
+fn halftone_threshold(q: vec2<f32>) -> f32 {
+    return get_threshold_map(fract(q) * vec2<f32>(textureDimensions(t_threshold)));
+}
+
+// Ink coverage of the C, M, Y and K plates. Black takes `cmyk_black` of the gray component above
+// `cmyk_black_start` (0 is GCR over the whole range, higher confines it to the shadows like UCR)
+// and CMY are rescaled so the inks still multiply back to the same color.
+fn to_cmyk(rgb: vec3<f32>) -> vec4<f32> {
+    let cmy = 1.0 - clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    let k0 = min(cmy.x, min(cmy.y, cmy.z));
+    let k = settings.cmyk_black * max(k0 - settings.cmyk_black_start, 0.0) / max(1.0 - settings.cmyk_black_start, 1e-5);
+    return vec4<f32>((cmy - k) / max(1.0 - k, 1e-5), k);
+}
+
+// CMYK separation: every plate is screened from its own cells at the classic rosette angles
+// (C 15, M 75, Y 0, K 45, turned with `halftone_angle` so K follows it), then the inks are
+// multiplied onto white paper. `cmyk_plate` 1-4 shows a single plate, ink black.
+fn halftone_cmyk(p: vec2<f32>, tex_size: vec2<f32>) -> vec3<f32> {
+    var angles = array<f32, 4>(15.0, 75.0, 0.0, 45.0);
+    let cell_size = halftone_cell_size(tex_size);
+    let plate = i32(settings.cmyk_plate);
+    let single = plate >= 1 && plate <= 4;
+    var paper = vec4<f32>(1.0);
+    for (var i = 0; i < 4; i++) {
+        if (single && i != plate - 1) { continue; }
+        let rot = halftone_rot(angles[i] - 45.0 + settings.halftone_angle);
+        let q = to_screen(p, rot, cell_size);
+        let ink = to_cmyk(halftone_average(floor(q), rot, cell_size, tex_size))[i];
+        paper[i] = apply_dither_step(1.0 - ink, halftone_threshold(q), settings.posterize_levels);
+    }
+    if (single) { return vec3<f32>(paper[plate - 1]); }
+    return paper.xyz * paper.w;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let tex_size = vec2<f32>(textureDimensions(t_diffuse));
@@ -146,21 +345,15 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         uv = (floor(uv * tex_size / scale) * scale + (scale * 0.5)) / tex_size;
     }
 
-    var color = textureSample(t_diffuse, s_diffuse, uv).rgb;
-    
-    if (settings.sharpness > 0.0) {
-        let dx = 1.0 / tex_size.x;
-        let dy = 1.0 / tex_size.y;
-        let laplacian = (textureSample(t_diffuse, s_diffuse, uv + vec2<f32>(-dx, 0.0)).rgb + textureSample(t_diffuse, s_diffuse, uv + vec2<f32>(dx, 0.0)).rgb + textureSample(t_diffuse, s_diffuse, uv + vec2<f32>(0.0, -dy)).rgb + textureSample(t_diffuse, s_diffuse, uv + vec2<f32>(0.0, dy)).rgb - 4.0 * color);
-        color = color - settings.sharpness * laplacian;
-    }
-
-    color = apply_adjustments(color, uv, tex_size);
+    var color = adjusted_color(uv, tex_size);
 
     var final_color = color;
+    var alpha_noise = settings.dither_threshold;
 
     if (settings.dither_enabled < 0.5) {
-        if (settings.posterize_levels > 1.5) {
+        if (palette_active()) {
+            final_color = palette_dither(final_color, 1.0);
+        } else if (settings.posterize_levels > 1.5) {
             let lv = settings.posterize_levels - 1.0;
             final_color = floor(final_color * lv + 0.5) / lv;
         }
@@ -172,11 +365,10 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         var noise = settings.dither_threshold;
         if (d_type == 2) { 
             noise = white_noise(screen_pos); 
-        } else if (d_type == 3) {
-            noise = get_bayer_threshold(screen_pos, i32(settings.bayer_size));
-        } else if (d_type == 4) {
-            noise = interleaved_gradient_noise(screen_pos);
+        } else if (d_type == 3 || d_type == 4) {
+            noise = get_threshold_map(screen_pos);
         } else if (d_type == 5) {
+            // 5-7 only blend noise; real error diffusion (type 10) is serial and runs on the CPU, like stippling (12).
             let j = hash22(screen_pos);
             noise = (j.x + j.y + interleaved_gradient_noise(screen_pos)) / 3.0;
         } else if (d_type == 6) {
@@ -187,17 +379,32 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
             let n = interleaved_gradient_noise(screen_pos);
             noise = step(0.5, n) * 0.5 + 0.25; 
         } else if (d_type == 8) {
-            let dx = get_luminance(textureSample(t_diffuse, s_diffuse, uv + vec2<f32>(1.0/tex_size.x, 0.0)).rgb) - get_luminance(color);
-            let dy = get_luminance(textureSample(t_diffuse, s_diffuse, uv + vec2<f32>(0.0, 1.0/tex_size.y)).rgb) - get_luminance(color);
+            let dx = get_luminance(to_space(textureSample(t_diffuse, s_diffuse, uv + vec2<f32>(1.0/tex_size.x, 0.0)).rgb, settings.dither_space)) - get_luminance(color);
+            let dy = get_luminance(to_space(textureSample(t_diffuse, s_diffuse, uv + vec2<f32>(0.0, 1.0/tex_size.y)).rgb, settings.dither_space)) - get_luminance(color);
             let edge = clamp(abs(dx) + abs(dy), 0.0, 1.0);
             noise = mix(interleaved_gradient_noise(screen_pos), settings.dither_threshold, edge * 0.8);
                 } else if (d_type == 9) {
                     let p = screen_pos * 0.4;
                     let n = sin(p.x) * cos(p.y) + sin(p.y * 0.5) * cos(p.x * 0.5);
                     noise = fract(n * 2.0 + interleaved_gradient_noise(screen_pos) * 0.5);
+                } else if (d_type == 11 && settings.halftone_cmyk < 0.5) {
+                    // AM halftone: the cell's mean tone decides how much of the spot map is inked,
+                    // so the dot grows with darkness. The map spans the whole rotated cell.
+                    let cell_size = halftone_cell_size(tex_size);
+                    let rot = halftone_rot(settings.halftone_angle);
+                    let q = to_screen((screen_pos + 0.5) * d_scale, rot, cell_size);
+                    color = halftone_average(floor(q), rot, cell_size, tex_size);
+                    noise = halftone_threshold(q);
                 }
+        alpha_noise = noise;
         
-                if (settings.dither_color > 0.5) {            final_color.r = apply_dither_step(color.r, noise, settings.posterize_levels);
+        if (d_type == 11 && settings.halftone_cmyk > 0.5) {
+            final_color = halftone_cmyk((screen_pos + 0.5) * d_scale, tex_size);
+        } else if (palette_active()) {
+            if (settings.dither_color > 0.5) { final_color = palette_dither(color, noise); }
+            else { final_color = palette_dither(vec3<f32>(get_luminance(color)), noise); }
+        } else if (settings.dither_color > 0.5) {
+            final_color.r = apply_dither_step(color.r, noise, settings.posterize_levels);
             final_color.g = apply_dither_step(color.g, noise, settings.posterize_levels);
             final_color.b = apply_dither_step(color.b, noise, settings.posterize_levels);
         } else {
@@ -207,8 +414,16 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
 
     if (settings.grad_enabled > 0.5) {
         let lum = clamp(get_luminance(final_color), 0.0, 1.0);
-        final_color = textureSample(t_gradient, s_diffuse, vec2<f32>(lum, 0.5)).rgb;
+        final_color = sample_lut(t_gradient, lum);
+    } else {
+        final_color = from_space(final_color, settings.dither_space);
     }
 
-    return vec4<f32>(clamp(final_color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+    // Alpha stays straight through processing; a 1-bit cutout reuses the color's noise.
+    var alpha = textureSampleLevel(t_diffuse, s_diffuse, uv, 0.0).a;
+    if (settings.alpha_dither > 0.5) { alpha = apply_dither_step(alpha, alpha_noise, 0.0); }
+    alpha = clamp(alpha, 0.0, 1.0);
+    var rgb = clamp(final_color, vec3<f32>(0.0), vec3<f32>(1.0));
+    if (settings.alpha_premultiply > 0.5) { rgb = rgb * alpha; }
+    return vec4<f32>(rgb, alpha);
 }
\ No newline at end of file
@@ -0,0 +1,193 @@
+//! Weighted Voronoi stippling (Secord, 2002): points are scattered by darkness, then moved by a
+//! few rounds of Lloyd relaxation to the density-weighted centroids of their Voronoi cells, which
+//! spreads them evenly while keeping them packed where the image is dark. Each point is then drawn
+//! as a dot whose area is a share of its Voronoi cell, from `stipple_min_size` in highlights to
+//! `stipple_max_size` in full black, so the ink coverage follows the tone.
+//!
+//! Like [`super::diffusion`] this runs on the dither cell grid, after the adjustments and before the
+//! gradient remap, and only on the CPU.
+
+use super::cpu::{encode_srgb8, get_luminance, Shader};
+use super::diffusion::{adjusted_cells, cell_alphas};
+use crate::threshold::blue_noise::Rng;
+use image::RgbaImage;
+
+pub const MIN_POINTS: f32 = 100.0;
+pub const MAX_POINTS: f32 = 50000.0;
+pub const MAX_ITERATIONS: f32 = 100.0;
+/// Seed of the initial scatter, fixed so the same look always stipples the same way.
+const SEED: u64 = 0x5717_9e1e;
+
+/// One relaxed point, in cell coordinates, with the Voronoi cell it ended up owning.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stipple {
+    pub x: f32,
+    pub y: f32,
+    /// Cells in the point's Voronoi region.
+    pub area: f32,
+    /// Mean darkness over the region, 0 to 1.
+    pub density: f32,
+    /// Mean adjusted color over the region.
+    pub color: [f32; 3],
+}
+
+/// Uniform buckets of point indices for nearest-point queries.
+struct Grid {
+    cols: usize,
+    rows: usize,
+    size: f32,
+    buckets: Vec<Vec<u32>>,
+}
+
+impl Grid {
+    fn new(points: &[[f32; 2]], width: usize, height: usize) -> Self {
+        // About one point per bucket on average.
+        let size = ((width * height) as f32 / points.len() as f32).sqrt().max(1.0);
+        let (cols, rows) = ((width as f32 / size).ceil() as usize, (height as f32 / size).ceil() as usize);
+        let mut buckets = vec![Vec::new(); cols * rows];
+        for (i, p) in points.iter().enumerate() {
+            let (bx, by) = (((p[0] / size) as usize).min(cols - 1), ((p[1] / size) as usize).min(rows - 1));
+            buckets[by * cols + bx].push(i as u32);
+        }
+        Self { cols, rows, size, buckets }
+    }
+
+    /// Index of the point nearest to `(x, y)`, searching rings of buckets outwards until no closer
+    /// point can remain.
+    fn nearest(&self, points: &[[f32; 2]], x: f32, y: f32) -> usize {
+        let (bx, by) = (((x / self.size) as i64).min(self.cols as i64 - 1), ((y / self.size) as i64).min(self.rows as i64 - 1));
+        let (mut best, mut best_d2) = (0, f32::INFINITY);
+        for ring in 0..=self.cols.max(self.rows) as i64 {
+            for gy in (by - ring).max(0)..=(by + ring).min(self.rows as i64 - 1) {
+                for gx in (bx - ring).max(0)..=(bx + ring).min(self.cols as i64 - 1) {
+                    // Buckets inside the ring were searched on earlier rings.
+                    if (gy - by).abs() != ring && (gx - bx).abs() != ring { continue; }
+                    for &i in &self.buckets[gy as usize * self.cols + gx as usize] {
+                        let p = points[i as usize];
+                        let d2 = (p[0] - x) * (p[0] - x) + (p[1] - y) * (p[1] - y);
+                        if d2 < best_d2 { best = i as usize; best_d2 = d2; }
+                    }
+                }
+            }
+            let reach = ring as f32 * self.size;
+            if best_d2 <= reach * reach { break; }
+        }
+        best
+    }
+}
+
+/// Per-point sums over its Voronoi region: density-weighted x and y, density, cell count and color.
+type Sums = [f64; 7];
+
+/// Assigns every cell to its nearest point and sums the regions, in parallel over rows.
+fn accumulate(points: &[[f32; 2]], density: &[f32], colors: &[[f32; 3]], cols: usize, rows: usize) -> Vec<Sums> {
+    let grid = Grid::new(points, cols, rows);
+    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let rows_per_chunk = rows.div_ceil(threads).max(1);
+    let partials: Vec<Vec<Sums>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..rows).step_by(rows_per_chunk).map(|start| {
+            let grid = &grid;
+            scope.spawn(move || {
+                let mut sums = vec![[0.0; 7]; points.len()];
+                for y in start..(start + rows_per_chunk).min(rows) {
+                    for x in 0..cols {
+                        let (cx, cy) = (x as f32 + 0.5, y as f32 + 0.5);
+                        let s = &mut sums[grid.nearest(points, cx, cy)];
+                        let (d, c) = (density[y * cols + x] as f64, colors[y * cols + x]);
+                        s[0] += d * cx as f64; s[1] += d * cy as f64; s[2] += d; s[3] += 1.0;
+                        for k in 0..3 { s[4 + k] += c[k] as f64; }
+                    }
+                }
+                sums
+            })
+        }).collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    // Reduced in chunk order, so the result does not depend on thread timing.
+    let mut sums = vec![[0.0; 7]; points.len()];
+    for part in partials {
+        for (s, p) in sums.iter_mut().zip(part) { for k in 0..7 { s[k] += p[k]; } }
+    }
+    sums
+}
+
+/// Scatters `stipple_points` points by darkness and relaxes them for `stipple_iterations` rounds.
+/// `density` and `colors` are row-major over a `cols` x `rows` cell grid.
+pub fn relax(density: &[f32], colors: &[[f32; 3]], cols: usize, rows: usize, count: usize, iterations: usize) -> Vec<Stipple> {
+    let max_density = density.iter().copied().fold(0.0, f32::max);
+    if count == 0 || max_density <= 0.0 { return Vec::new(); }
+
+    // Rejection sampling against the darkest cell, so the scatter already follows the tone.
+    let mut rng = Rng(SEED);
+    let unit = |rng: &mut Rng| (rng.next() >> 40) as f32 / (1u64 << 24) as f32;
+    let mut points = Vec::with_capacity(count);
+    while points.len() < count {
+        let (x, y) = (unit(&mut rng) * cols as f32, unit(&mut rng) * rows as f32);
+        let d = density[(y as usize).min(rows - 1) * cols + (x as usize).min(cols - 1)];
+        if unit(&mut rng) * max_density < d { points.push([x, y]); }
+    }
+
+    for _ in 0..iterations {
+        let sums = accumulate(&points, density, colors, cols, rows);
+        // Points whose region is all white have no centroid and stay where they are.
+        for (p, s) in points.iter_mut().zip(&sums) { if s[2] > 0.0 { *p = [(s[0] / s[2]) as f32, (s[1] / s[2]) as f32]; } }
+    }
+
+    let sums = accumulate(&points, density, colors, cols, rows);
+    points.iter().zip(&sums).map(|(p, s)| {
+        let n = s[3].max(1.0);
+        Stipple { x: p[0], y: p[1], area: s[3] as f32, density: (s[2] / n) as f32, color: [(s[4] / n) as f32, (s[5] / n) as f32, (s[6] / n) as f32] }
+    }).collect()
+}
+
+/// Nearest of `posterize_levels` evenly spaced levels, or the color as is.
+fn quantize(v: f32, levels: f32) -> f32 {
+    if levels > 1.5 { let lv = levels - 1.0; (v.clamp(0.0, 1.0) * lv).round() / lv } else { v }
+}
+
+/// Renders the whole image as dots on white paper. Dots are black, or carry their region's mean
+/// color with color dithering on. With a palette, paper and ink both snap to their nearest entry.
+pub fn render(shader: &Shader) -> RgbaImage {
+    let s = shader.settings;
+    let (width, height) = (shader.source.width, shader.source.height);
+    if width == 0 || height == 0 { return RgbaImage::new(width, height); }
+    let d_scale = s.dither_scale.max(1.0);
+    // Same cell index as `screen_pos` in `fs_main`.
+    let cell_of = |p: u32| ((p as f32 + 0.5) / d_scale).floor() as usize;
+    let (cols, rows) = (cell_of(width - 1) + 1, cell_of(height - 1) + 1);
+
+    let colors = adjusted_cells(shader, cols, rows, d_scale);
+    let density: Vec<f32> = colors.iter().map(|&c| 1.0 - get_luminance(c).clamp(0.0, 1.0)).collect();
+    let count = s.stipple_points.clamp(MIN_POINTS, MAX_POINTS) as usize;
+    let stipples = relax(&density, &colors, cols, rows, count, s.stipple_iterations.clamp(0.0, MAX_ITERATIONS) as usize);
+
+    let palette = shader.palette_active();
+    let paper = if palette { shader.palette_nearest([1.0; 3]) } else { [1.0; 3] };
+    let mut cells = vec![paper; cols * rows];
+    let (min_size, max_size) = (s.stipple_min_size.clamp(0.0, 1.0), s.stipple_max_size.clamp(0.0, 1.0));
+    for st in &stipples {
+        // The dot covers `size` of its region, so coverage tracks darkness when the range is 0..1.
+        let size = min_size + (max_size - min_size) * st.density;
+        let r = (size * st.area / std::f32::consts::PI).sqrt();
+        if r <= 0.0 { continue; }
+        let ink = match (palette, s.dither_color > 0.5) {
+            (true, true) => shader.palette_nearest(st.color),
+            (true, false) => shader.palette_nearest([0.0; 3]),
+            (false, true) => st.color.map(|c| quantize(c, s.posterize_levels)),
+            (false, false) => [0.0; 3],
+        };
+        let (x0, x1) = ((st.x - r).floor().max(0.0) as usize, ((st.x + r).ceil() as usize).min(cols));
+        let (y0, y1) = ((st.y - r).floor().max(0.0) as usize, ((st.y + r).ceil() as usize).min(rows));
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (dx, dy) = (x as f32 + 0.5 - st.x, y as f32 + 0.5 - st.y);
+                if dx * dx + dy * dy <= r * r { cells[y * cols + x] = ink; }
+            }
+        }
+    }
+
+    // Paper and dots share the source's coverage, cut at the threshold for 1-bit alpha.
+    let alphas = cell_alphas(shader, cols, rows, d_scale);
+    let cells: Vec<[u8; 4]> = cells.into_iter().zip(alphas).map(|(c, a)| encode_srgb8(shader.finish(c, if s.alpha_dither > 0.5 { if a > s.dither_threshold { 1.0 } else { 0.0 } } else { a }))).collect();
+    RgbaImage::from_fn(width, height, |x, y| image::Rgba(cells[cell_of(y) * cols + cell_of(x)]))
+}
@@ -0,0 +1,100 @@
+//! `.vdpreset` files: a [`Look`] serialized as versioned JSON.
+//!
+//! Loading is lenient in both directions: missing fields fall back to their defaults, so presets
+//! written before a `ColorSettings` field existed still load, and unknown fields are ignored, so
+//! presets from newer versions load with whatever this version understands.
+
+use crate::color::{format_hex_color, parse_hex_color};
+use crate::gradient::{self, GradientStop};
+use crate::look::Look;
+use crate::palette;
+use crate::pipeline::ColorSettings;
+use crate::spline::{self, CurvePoint};
+use crate::threshold::matrix;
+use anyhow::{bail, Context, Result};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const PRESET_EXTENSION: &str = "vdpreset";
+/// Bumped whenever the meaning of an existing field changes. Files without a version are treated
+/// as 0, the unversioned format first accepted by `vibedither-cli --preset`.
+pub const PRESET_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct PresetFile {
+    version: u32,
+    settings: ColorSettings,
+    curves: PresetCurves,
+    gradient: Option<Vec<PresetStop>>,
+    palette: Option<Vec<String>>,
+    /// Built-in palette id; wins over `palette`, which is still written for versions without it.
+    palette_name: Option<String>,
+    custom_matrix: Option<PresetMatrix>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct PresetCurves { master: Option<Vec<[f32; 2]>>, red: Option<Vec<[f32; 2]>>, green: Option<Vec<[f32; 2]>>, blue: Option<Vec<[f32; 2]>> }
+
+#[derive(Serialize, Deserialize)]
+struct PresetStop { pos: f32, color: String }
+
+#[derive(Serialize, Deserialize)]
+struct PresetMatrix { width: u32, height: u32, values: Vec<f32> }
+
+fn to_points(pts: &[CurvePoint]) -> Option<Vec<[f32; 2]>> {
+    Some(pts.iter().map(|p| [p.x, p.y]).collect())
+}
+
+pub fn to_string(look: &Look) -> Result<String> {
+    let file = PresetFile {
+        version: PRESET_VERSION,
+        settings: look.settings,
+        curves: PresetCurves { master: to_points(&look.curves[0]), red: to_points(&look.curves[1]), green: to_points(&look.curves[2]), blue: to_points(&look.curves[3]) },
+        gradient: Some(look.gradient.iter().map(|s| PresetStop { pos: s.pos, color: format_hex_color(s.color) }).collect()),
+        palette: Some(look.palette.iter().map(|&c| format_hex_color(c)).collect()),
+        palette_name: look.palette_name.clone(),
+        custom_matrix: look.custom_matrix.as_ref().map(|m| PresetMatrix { width: m.width, height: m.height, values: m.values.clone() }),
+    };
+    Ok(serde_json::to_string_pretty(&file)?)
+}
+
+pub fn from_str(text: &str) -> Result<Look> {
+    let file: PresetFile = serde_json::from_str(text)?;
+    if file.version > PRESET_VERSION { log::warn!("Preset version {} is newer than {}; unknown fields are ignored", file.version, PRESET_VERSION); }
+    let mut look = Look { settings: file.settings, ..Look::default() };
+    for (idx, pts) in [file.curves.master, file.curves.red, file.curves.green, file.curves.blue].into_iter().enumerate() {
+        let Some(pts) = pts else { continue };
+        let mut pts: Vec<CurvePoint> = pts.iter().map(|p| CurvePoint::new(p[0].clamp(0.0, 1.0), p[1].clamp(0.0, 1.0))).collect();
+        pts.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
+        look.curves[idx] = if pts.is_empty() { spline::identity_curve() } else { pts };
+    }
+    if let Some(stops) = file.gradient {
+        let mut stops = stops.iter().enumerate().map(|(i, s)| Ok(GradientStop::new(i as u64, s.pos.clamp(0.0, 1.0), parse_hex_color(&s.color)?))).collect::<Result<Vec<_>>>()?;
+        stops.sort_by(|a, b| a.pos.partial_cmp(&b.pos).unwrap());
+        look.gradient = if stops.is_empty() { gradient::default_stops() } else { stops };
+    }
+    if let Some(colors) = file.palette {
+        if colors.len() > palette::MAX_COLORS { bail!("palette has {} colors, the most supported is {}", colors.len(), palette::MAX_COLORS); }
+        let colors = colors.iter().map(|c| parse_hex_color(c)).collect::<Result<Vec<_>>>()?;
+        look.palette = if colors.is_empty() { palette::default_colors() } else { colors };
+    }
+    if let Some(name) = file.palette_name {
+        if let Err(e) = look.set_builtin_palette(&name) { log::warn!("{}; keeping the stored colors", e); }
+    }
+    if let Some(m) = file.custom_matrix {
+        look.custom_matrix = Some(Arc::new(matrix::from_checked_values(m.width, m.height, &m.values).context("invalid custom matrix")?));
+    }
+    Ok(look)
+}
+
+pub fn save(path: &Path, look: &Look) -> Result<()> {
+    std::fs::write(path, to_string(look)?).with_context(|| format!("writing preset {:?}", path))
+}
+
+pub fn load(path: &Path) -> Result<Look> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("reading preset {:?}", path))?;
+    from_str(&text).with_context(|| format!("parsing preset {:?}", path))
+}
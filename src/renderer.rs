@@ -0,0 +1,114 @@
+use crate::look::Look;
+use crate::pipeline::{self, cpu, Pipeline};
+use anyhow::{Context, Result};
+use image::{DynamicImage, Rgba32FImage, RgbaImage};
+use parking_lot::Mutex;
+use std::sync::OnceLock;
+
+/// Which implementation of the shader pipeline a [`Renderer`] runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// The GPU when an adapter is available, otherwise the CPU.
+    #[default]
+    Auto,
+    Gpu,
+    Cpu,
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(Backend::Auto), "gpu" => Ok(Backend::Gpu), "cpu" => Ok(Backend::Cpu),
+            _ => Err(format!("unknown backend '{}' (expected auto, gpu or cpu)", s)),
+        }
+    }
+}
+
+struct GpuState {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: Mutex<Pipeline>,
+}
+
+/// Renders looks without a window, on a headless device or on the CPU reference implementation.
+pub struct Renderer {
+    gpu: Option<GpuState>,
+}
+
+impl Renderer {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    /// Equivalent to `with_backend(Backend::Auto)`, which never fails.
+    pub fn new() -> Result<Self> {
+        Self::with_backend(Backend::Auto)
+    }
+
+    pub fn with_backend(backend: Backend) -> Result<Self> {
+        let gpu = match backend {
+            Backend::Cpu => None,
+            Backend::Gpu => Some(Self::init_gpu().context("no GPU adapter available")?),
+            Backend::Auto => {
+                let gpu = Self::init_gpu();
+                if gpu.is_none() { log::info!("No GPU adapter available, rendering on the CPU"); }
+                gpu
+            }
+        };
+        Ok(Self { gpu })
+    }
+
+    fn init_gpu() -> Option<GpuState> {
+        let (device, queue) = pipeline::request_headless_device()?;
+        let mut pipeline = Pipeline::new();
+        pipeline.init(&device, Self::FORMAT);
+        Some(GpuState { device, queue, pipeline: Mutex::new(pipeline) })
+    }
+
+    /// The backend actually in use: `Gpu` or `Cpu`, never `Auto`.
+    pub fn backend(&self) -> Backend {
+        if self.gpu.is_some() { Backend::Gpu } else { Backend::Cpu }
+    }
+
+    pub fn render(&self, image: &DynamicImage, look: &Look) -> Result<RgbaImage> {
+        let (curves, gradient, palette, threshold) = (look.curves_lut(), look.gradient_lut(), look.palette_lut(), look.threshold_map());
+        match &self.gpu {
+            Some(gpu) if !look.settings.needs_cpu_pass() => {
+                let mut pipeline = gpu.pipeline.lock();
+                pipeline.update_curves(&gpu.queue, &curves);
+                pipeline.update_gradient(&gpu.queue, &gradient);
+                pipeline.update_palette(&gpu.queue, &palette);
+                pipeline.update_threshold_map(&gpu.device, &gpu.queue, &threshold);
+                pipeline.render_image(&gpu.device, &gpu.queue, image, &look.settings, Self::FORMAT).context("GPU readback failed")
+            }
+            _ => Ok(cpu::render(image, &look.settings, &curves, &gradient, &palette, &threshold)),
+        }
+    }
+
+    /// Renders `image` without the final 8-bit encoding: the clamped linear output at half-float
+    /// precision, for writing 16-bit files. See [`crate::image_io::to_srgb16`].
+    pub fn render_linear(&self, image: &DynamicImage, look: &Look) -> Result<Rgba32FImage> {
+        let (curves, gradient, palette, threshold) = (look.curves_lut(), look.gradient_lut(), look.palette_lut(), look.threshold_map());
+        match &self.gpu {
+            Some(gpu) if !look.settings.needs_cpu_pass() => {
+                let mut pipeline = gpu.pipeline.lock();
+                pipeline.update_curves(&gpu.queue, &curves);
+                pipeline.update_gradient(&gpu.queue, &gradient);
+                pipeline.update_palette(&gpu.queue, &palette);
+                pipeline.update_threshold_map(&gpu.device, &gpu.queue, &threshold);
+                pipeline.render_image_linear(&gpu.device, &gpu.queue, image, &look.settings).context("GPU readback failed")
+            }
+            _ => Ok(cpu::render_linear(image, &look.settings, &curves, &gradient, &palette, &threshold)),
+        }
+    }
+}
+
+/// Renders `image` with `look` on a shared [`Renderer`] created on first use. Runs on the GPU
+/// when one is available and falls back to the CPU reference implementation otherwise.
+pub fn render(image: &DynamicImage, look: &Look) -> RgbaImage {
+    static SHARED: OnceLock<Mutex<Renderer>> = OnceLock::new();
+    let renderer = SHARED.get_or_init(|| Mutex::new(Renderer { gpu: Renderer::init_gpu() })).lock();
+    renderer.render(image, look).unwrap_or_else(|e| {
+        log::warn!("{:#}, rendering on the CPU", e);
+        cpu::render(image, &look.settings, &look.curves_lut(), &look.gradient_lut(), &look.palette_lut(), &look.threshold_map())
+    })
+}
@@ -0,0 +1,65 @@
+//! Tonal scopes over a rendered RGBA8 image: per-channel and luminance histograms and a luminance
+//! waveform. Levels are the encoded 0-255 values, as image editors show them; fully transparent
+//! pixels are left out.
+
+use image::RgbaImage;
+
+/// Rec. 709 luma of encoded 8-bit RGB, rounded to a level.
+fn luma(p: [u8; 4]) -> usize {
+    (0.2126 * p[0] as f32 + 0.7152 * p[1] as f32 + 0.0722 * p[2] as f32 + 0.5) as usize
+}
+
+/// Pixel counts per level for luminance and each of R, G and B.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    pub luma: [u32; 256],
+    pub rgb: [[u32; 256]; 3],
+}
+
+impl Histogram {
+    pub fn of(img: &RgbaImage) -> Self {
+        let mut h = Self { luma: [0; 256], rgb: [[0; 256]; 3] };
+        for p in img.pixels().filter(|p| p[3] > 0) {
+            h.luma[luma(p.0).min(255)] += 1;
+            for c in 0..3 { h.rgb[c][p[c] as usize] += 1; }
+        }
+        h
+    }
+
+    /// Channel 0 is luminance, 1-3 are R, G and B, matching the curve channels.
+    pub fn channel(&self, idx: usize) -> &[u32; 256] {
+        if idx == 0 { &self.luma } else { &self.rgb[(idx - 1).min(2)] }
+    }
+
+    /// Highest count in any channel, what the bars are scaled against. Pure black and white are
+    /// left out, so a clipped end does not flatten everything else.
+    pub fn peak(&self) -> u32 {
+        std::iter::once(&self.luma).chain(&self.rgb).flat_map(|c| c[1..255].iter()).copied().max().unwrap_or(0).max(1)
+    }
+}
+
+/// Luminance distribution per column band: `counts[col * 256 + level]` pixels of band `col` sit at
+/// `level`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Waveform {
+    pub columns: usize,
+    pub counts: Vec<u32>,
+}
+
+impl Waveform {
+    /// Spreads the image's columns over `columns` bands.
+    pub fn of(img: &RgbaImage, columns: usize) -> Self {
+        let columns = columns.clamp(1, img.width().max(1) as usize);
+        let mut counts = vec![0; columns * 256];
+        for (x, _, p) in img.enumerate_pixels().filter(|(_, _, p)| p[3] > 0) {
+            let col = x as usize * columns / img.width() as usize;
+            counts[col * 256 + luma(p.0).min(255)] += 1;
+        }
+        Self { columns, counts }
+    }
+
+    /// Highest count of any band and level.
+    pub fn peak(&self) -> u32 {
+        self.counts.iter().copied().max().unwrap_or(0).max(1)
+    }
+}
@@ -1,62 +1,74 @@
-use eframe::egui;
+use crate::pipeline::{Lut, LUT_SIZE};
+
+/// A control point of a tone curve, both axes normalized to 0..1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CurvePoint { pub x: f32, pub y: f32 }
+
+impl CurvePoint {
+    pub const fn new(x: f32, y: f32) -> Self { Self { x, y } }
+}
 
 /// Monotone Cubic Spline interpolation (Fritsch-Carlson)
 /// This provides a very smooth curve that doesn't "overshoot" like standard cubic splines.
-pub fn interpolate_spline(points: &[egui::Pos2]) -> [u8; 256] {
-    let mut lut = [0u8; 256];
-    if points.is_empty() {
-        for i in 0..256 { lut[i] = i as u8; }
-        return lut;
-    }
+struct Spline {
+    pts: Vec<CurvePoint>,
+    /// Tangent at each point.
+    m: Vec<f32>,
+}
 
-    let mut pts = points.to_vec();
-    pts.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
+impl Spline {
+    fn new(points: &[CurvePoint]) -> Self {
+        let mut pts = points.to_vec();
+        pts.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
 
-    // 1. Calculate tangents (slopes)
-    let n = pts.len();
-    if n < 2 {
-        for i in 0..256 { lut[i] = (pts[0].y.clamp(0.0, 1.0) * 255.0) as u8; }
-        return lut;
-    }
+        // 1. Calculate tangents (slopes)
+        let n = pts.len();
+        if n < 2 { return Self { pts, m: Vec::new() }; }
 
-    let mut dx = vec![0.0; n - 1];
-    let mut dy = vec![0.0; n - 1];
-    let mut slope = vec![0.0; n - 1];
-    for i in 0..n - 1 {
-        dx[i] = pts[i + 1].x - pts[i].x;
-        dy[i] = pts[i + 1].y - pts[i].y;
-        slope[i] = dy[i] / dx[i];
-    }
+        let mut dx = vec![0.0; n - 1];
+        let mut dy = vec![0.0; n - 1];
+        let mut slope = vec![0.0; n - 1];
+        for i in 0..n - 1 {
+            dx[i] = pts[i + 1].x - pts[i].x;
+            dy[i] = pts[i + 1].y - pts[i].y;
+            slope[i] = dy[i] / dx[i];
+        }
 
-    // 2. Initialize tangents at points
-    let mut m = vec![0.0; n];
-    m[0] = slope[0];
-    for i in 1..n - 1 {
-        m[i] = (slope[i - 1] + slope[i]) / 2.0;
-    }
-    m[n - 1] = slope[n - 2];
+        // 2. Initialize tangents at points
+        let mut m = vec![0.0; n];
+        m[0] = slope[0];
+        for i in 1..n - 1 {
+            m[i] = (slope[i - 1] + slope[i]) / 2.0;
+        }
+        m[n - 1] = slope[n - 2];
 
-    // 3. Force monotonicity
-    for i in 0..n - 1 {
-        if slope[i] == 0.0 {
-            m[i] = 0.0;
-            m[i + 1] = 0.0;
-        } else {
-            let a = m[i] / slope[i];
-            let b = m[i + 1] / slope[i];
-            let h = (a * a + b * b).sqrt();
-            if h > 3.0 {
-                let t = 3.0 / h;
-                m[i] = t * a * slope[i];
-                m[i + 1] = t * b * slope[i];
+        // 3. Force monotonicity
+        for i in 0..n - 1 {
+            if slope[i] == 0.0 {
+                m[i] = 0.0;
+                m[i + 1] = 0.0;
+            } else {
+                let a = m[i] / slope[i];
+                let b = m[i + 1] / slope[i];
+                let h = (a * a + b * b).sqrt();
+                if h > 3.0 {
+                    let t = 3.0 / h;
+                    m[i] = t * a * slope[i];
+                    m[i + 1] = t * b * slope[i];
+                }
             }
         }
+
+        Self { pts, m }
     }
 
-    // 4. Interpolate
-    for x_idx in 0..256 {
-        let x = x_idx as f32 / 255.0;
-        
+    /// 4. Interpolate: the curve at `x`, clamped to 0..1. No points is the identity.
+    fn at(&self, x: f32) -> f32 {
+        let (pts, m) = (&self.pts, &self.m);
+        let n = pts.len();
+        if n == 0 { return x.clamp(0.0, 1.0); }
+        if n < 2 { return pts[0].y.clamp(0.0, 1.0); }
+
         let y = if x <= pts[0].x {
             // Linear ramp from (0,0) to the first point
             if pts[0].x > 0.0 {
@@ -93,8 +105,37 @@ pub fn interpolate_spline(points: &[egui::Pos2]) -> [u8; 256] {
             h00 * pts[i].y + h10 * h * m[i] + h01 * pts[i + 1].y + h11 * h * m[i + 1]
         };
 
-        lut[x_idx] = (y.clamp(0.0, 1.0) * 255.0) as u8;
+        y.clamp(0.0, 1.0)
     }
+}
+
+/// The curve through `points` sampled at 256 steps, for drawing it.
+pub fn interpolate_spline(points: &[CurvePoint]) -> [u8; 256] {
+    let spline = Spline::new(points);
+    std::array::from_fn(|i| (spline.at(i as f32 / 255.0) * 255.0) as u8)
+}
+
+/// The curve through `points` at `x`, unquantized, as [`build_curves_lut`] evaluates it.
+pub fn curve_at(points: &[CurvePoint], x: f32) -> f32 {
+    Spline::new(points).at(x)
+}
+
+/// Identity curve (two endpoints) for each of the Master, R, G and B channels.
+pub fn default_curves() -> [Vec<CurvePoint>; 4] {
+    std::array::from_fn(|_| identity_curve())
+}
+
+pub fn identity_curve() -> Vec<CurvePoint> {
+    vec![CurvePoint::new(0.0, 0.0), CurvePoint::new(1.0, 1.0)]
+}
 
-    lut
-}
\ No newline at end of file
+/// Bakes the four curves into the [`LUT_SIZE`]x1 float LUT uploaded as `curves_texture`.
+/// Each channel goes through its own curve first and then through the Master curve.
+pub fn build_curves_lut(curves: &[Vec<CurvePoint>; 4]) -> Lut {
+    let master = Spline::new(&curves[0]);
+    let channels = [Spline::new(&curves[1]), Spline::new(&curves[2]), Spline::new(&curves[3])];
+    (0..LUT_SIZE).map(|i| {
+        let x = i as f32 / (LUT_SIZE - 1) as f32;
+        [master.at(channels[0].at(x)), master.at(channels[1].at(x)), master.at(channels[2].at(x)), 1.0]
+    }).collect()
+}
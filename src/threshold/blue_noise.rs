@@ -0,0 +1,205 @@
+//! Blue-noise threshold maps from Ulichney's void-and-cluster method.
+//!
+//! Generating a 256x256 map takes seconds, so every map is kept in memory once built and also
+//! written to the cache directory as a 16-bit grayscale PNG of ranks (one file per size and seed).
+//! The app asks through [`try_threshold_map`], which generates missing maps on a background thread.
+
+use super::ThresholdMap;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+pub const MIN_SIZE: u32 = 16;
+pub const MAX_SIZE: u32 = 256;
+/// Sizes offered in the UI. Any size in `MIN_SIZE..=MAX_SIZE` works.
+pub const SIZES: [u32; 5] = [16, 32, 64, 128, 256];
+/// Seed of the maps the pipeline uses, fixed so renders are reproducible across machines.
+pub const SEED: u64 = 0x5eed_b1e0;
+
+/// Standard deviation of the Gaussian energy filter, the value Ulichney recommends.
+const SIGMA: f32 = 1.5;
+
+/// splitmix64, enough to scatter the initial pattern deterministically.
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// A toroidal binary pattern with the Gaussian-filtered density of its set pixels.
+///
+/// The energy is kept in two masked copies (negated energy of set pixels, energy of unset pixels,
+/// infinity elsewhere) so both searches are a plain minimum over a slice, which keeps a 256x256
+/// map down to a few seconds.
+#[derive(Clone)]
+struct Pattern {
+    size: usize,
+    bits: Vec<bool>,
+    cluster_energy: Vec<f32>,
+    void_energy: Vec<f32>,
+    kernel: Vec<(i32, i32, f32)>,
+}
+
+/// Index of the first minimum, in two passes the compiler can vectorize.
+fn argmin(values: &[f32]) -> usize {
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    values.iter().position(|&v| v == min).unwrap()
+}
+
+impl Pattern {
+    fn new(size: usize) -> Self {
+        let radius = ((3.0 * SIGMA).ceil() as i32).min(size as i32 / 2 - 1);
+        let mut kernel = Vec::new();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius { kernel.push((dx, dy, (-((dx * dx + dy * dy) as f32) / (2.0 * SIGMA * SIGMA)).exp())); }
+        }
+        let n = size * size;
+        Self { size, bits: vec![false; n], cluster_energy: vec![f32::INFINITY; n], void_energy: vec![0.0; n], kernel }
+    }
+
+    fn set(&mut self, idx: usize, on: bool) {
+        self.bits[idx] = on;
+        let sign = if on { 1.0 } else { -1.0 };
+        let (x, y, s) = ((idx % self.size) as i32, (idx / self.size) as i32, self.size as i32);
+        for &(dx, dy, w) in &self.kernel {
+            let (nx, ny) = ((x + dx).rem_euclid(s), (y + dy).rem_euclid(s));
+            let i = (ny * s + nx) as usize;
+            self.cluster_energy[i] -= sign * w;
+            self.void_energy[i] += sign * w;
+        }
+        // Swap the pixel between the masks; the other copy's value is recovered from this one's.
+        if on { self.cluster_energy[idx] = -self.void_energy[idx]; self.void_energy[idx] = f32::INFINITY; }
+        else { self.void_energy[idx] = -self.cluster_energy[idx]; self.cluster_energy[idx] = f32::INFINITY; }
+    }
+
+    /// The set pixel with the most set neighbours.
+    fn tightest_cluster(&self) -> usize {
+        argmin(&self.cluster_energy)
+    }
+
+    /// The unset pixel furthest from any set pixel.
+    fn largest_void(&self) -> usize {
+        argmin(&self.void_energy)
+    }
+}
+
+/// Ranks `0..size*size` laid out so that thresholding at any rank gives an evenly spread pattern.
+pub fn void_and_cluster(size: u32, seed: u64) -> Vec<u32> {
+    let size = size.clamp(MIN_SIZE, MAX_SIZE) as usize;
+    let n = size * size;
+    let mut pattern = Pattern::new(size);
+
+    // Initial binary pattern: ~10% of pixels at random, then moved from clusters into voids until stable.
+    let initial = n / 10;
+    let mut rng = Rng(seed);
+    let mut ones = 0;
+    while ones < initial {
+        let idx = (rng.next() % n as u64) as usize;
+        if !pattern.bits[idx] { pattern.set(idx, true); ones += 1; }
+    }
+    for _ in 0..n {
+        let cluster = pattern.tightest_cluster();
+        pattern.set(cluster, false);
+        let void = pattern.largest_void();
+        pattern.set(void, true);
+        if void == cluster { break; }
+    }
+
+    let mut ranks = vec![0u32; n];
+    let prototype = pattern.clone();
+
+    // Phase 1: remove the tightest clusters of the prototype, ranking down from `initial`.
+    for rank in (0..initial).rev() {
+        let cluster = pattern.tightest_cluster();
+        pattern.set(cluster, false);
+        ranks[cluster] = rank as u32;
+    }
+
+    // Phases 2 and 3: fill the largest voids, ranking up. With a full-sum kernel, the largest void of
+    // the ones is also the tightest cluster of the zeros, so one loop covers both halves.
+    pattern = prototype;
+    for rank in initial..n {
+        let void = pattern.largest_void();
+        pattern.set(void, true);
+        ranks[void] = rank as u32;
+    }
+    ranks
+}
+
+/// Where generated maps are cached: `$VIBEDITHER_CACHE_DIR`, else the platform cache directory.
+pub fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("VIBEDITHER_CACHE_DIR") { return Some(PathBuf::from(dir)); }
+    let base = std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))?;
+    Some(base.join("vibedither"))
+}
+
+fn cache_path(size: u32, seed: u64) -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join(format!("blue_noise_{}_{:x}.png", size, seed)))
+}
+
+fn load_cached(size: u32, seed: u64) -> Option<Vec<u32>> {
+    let img = image::open(cache_path(size, seed)?).ok()?.into_luma16();
+    if img.dimensions() != (size, size) { return None; }
+    let ranks: Vec<u32> = img.into_raw().into_iter().map(u32::from).collect();
+    // A truncated or foreign file must not poison the map, so insist on a permutation.
+    let mut seen = vec![false; ranks.len()];
+    for &r in &ranks { if r as usize >= seen.len() || std::mem::replace(&mut seen[r as usize], true) { return None; } }
+    Some(ranks)
+}
+
+fn store_cached(size: u32, seed: u64, ranks: &[u32]) {
+    let Some(path) = cache_path(size, seed) else { return };
+    let img = image::ImageBuffer::<image::Luma<u16>, _>::from_raw(size, size, ranks.iter().map(|&r| r as u16).collect::<Vec<_>>()).unwrap();
+    let result = path.parent().map_or(Ok(()), std::fs::create_dir_all).map_err(anyhow::Error::from).and_then(|_| Ok(img.save(&path)?));
+    if let Err(e) = result { log::warn!("Could not cache blue noise at {:?}: {:#}", path, e); }
+}
+
+type Slot = Arc<OnceLock<Arc<ThresholdMap>>>;
+
+/// The slot of the map for `size`. Only looking it up takes the lock; filling it does not, so
+/// generating one size leaves the others free.
+fn slot(size: u32) -> Slot {
+    static MAPS: Mutex<Option<HashMap<u32, Slot>>> = Mutex::new(None);
+    MAPS.lock().get_or_insert_with(HashMap::new).entry(size).or_default().clone()
+}
+
+fn build(size: u32) -> Arc<ThresholdMap> {
+    let ranks = load_cached(size, SEED).unwrap_or_else(|| {
+        log::info!("Generating {0}x{0} blue noise", size);
+        let ranks = void_and_cluster(size, SEED);
+        store_cached(size, SEED, &ranks);
+        ranks
+    });
+    Arc::new(ThresholdMap::from_ranks(size, size, &ranks))
+}
+
+/// The `size` x `size` blue-noise map for [`SEED`], from memory, the disk cache, or freshly
+/// generated. Waits for a generation already running in the background.
+pub fn threshold_map(size: u32) -> Arc<ThresholdMap> {
+    let size = size.clamp(MIN_SIZE, MAX_SIZE);
+    slot(size).get_or_init(|| build(size)).clone()
+}
+
+/// The map for `size` if it is in memory or the disk cache. Otherwise starts generating it on a
+/// background thread, once, and returns `None` until it is done.
+pub fn try_threshold_map(size: u32) -> Option<Arc<ThresholdMap>> {
+    static GENERATING: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+    let size = size.clamp(MIN_SIZE, MAX_SIZE);
+    let slot = slot(size);
+    if let Some(map) = slot.get() { return Some(map.clone()); }
+    let mut generating = GENERATING.lock();
+    if generating.contains(&size) { return None; }
+    if let Some(ranks) = load_cached(size, SEED) { return Some(slot.get_or_init(|| Arc::new(ThresholdMap::from_ranks(size, size, &ranks))).clone()); }
+    generating.push(size);
+    std::thread::spawn(move || { slot.get_or_init(|| build(size)); });
+    None
+}
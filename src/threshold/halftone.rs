@@ -0,0 +1,54 @@
+//! Spot functions for amplitude-modulated halftone screens.
+//!
+//! A spot function orders the texels of one screen cell from the first to be inked to the last.
+//! Normalizing it by rank makes the inked share of the cell equal to the coverage, so a cell's dot
+//! area follows its tone linearly whatever the shape.
+
+use super::{MapCache, ThresholdMap};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Display names of the dot shapes, indexed by `ColorSettings::halftone_shape`.
+pub const SHAPE_NAMES: [&str; 5] = ["Round", "Ellipse", "Square", "Line", "Diamond"];
+/// Resolution of one cell's spot map; enough levels for cells far larger than any dither cell.
+pub const SPOT_SIZE: u32 = 64;
+pub const MIN_FREQUENCY: f32 = 4.0;
+pub const MAX_FREQUENCY: f32 = 400.0;
+
+/// Spot function of `shape` at `(u, v)` in `[-1, 1]`, lower values inking first.
+fn spot(shape: usize, u: f32, v: f32) -> f32 {
+    match shape {
+        1 => u * u + 2.5 * v * v,
+        2 => u.abs().max(v.abs()),
+        3 => v.abs(),
+        4 => u.abs() + v.abs(),
+        _ => u * u + v * v,
+    }
+}
+
+/// The cell map for `shape` (an index into [`SHAPE_NAMES`]). Like any threshold map it ranks texels
+/// by the tone at which they turn to paper, so the dot center is the last to go.
+///
+/// Texels with equal spot values (a whole row of a line screen) flip together, so they get the
+/// middle of their shared range rather than its start; otherwise the slightest tone above black
+/// would open a full band of paper.
+pub fn spot_map(shape: usize) -> ThresholdMap {
+    let values: Vec<f32> = (0..SPOT_SIZE * SPOT_SIZE).map(|i| {
+        let u = ((i % SPOT_SIZE) as f32 + 0.5) / SPOT_SIZE as f32 * 2.0 - 1.0;
+        let v = ((i / SPOT_SIZE) as f32 + 0.5) / SPOT_SIZE as f32 * 2.0 - 1.0;
+        -spot(shape, u, v)
+    }).collect();
+    let mut sorted = values.clone();
+    sorted.sort_by(f32::total_cmp);
+    let n = values.len() as f32;
+    let mid = |v: &f32| (sorted.partition_point(|s| s < v) + sorted.partition_point(|s| s <= v)) as f32 / (2.0 * n);
+    ThresholdMap { width: SPOT_SIZE, height: SPOT_SIZE, values: values.iter().map(mid).collect() }
+}
+
+/// [`spot_map`], built once per shape.
+pub fn threshold_map(shape: usize) -> Arc<ThresholdMap> {
+    static MAPS: MapCache<usize> = Mutex::new(None);
+    let shape = shape.min(SHAPE_NAMES.len() - 1);
+    MAPS.lock().get_or_insert_with(HashMap::new).entry(shape).or_insert_with(|| Arc::new(spot_map(shape))).clone()
+}
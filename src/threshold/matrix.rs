@@ -0,0 +1,129 @@
+//! Ordered-dither matrices: recursive Bayer, clustered dot, line screens and user imports.
+
+use super::ThresholdMap;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+/// Display names of the ordered matrix kinds, indexed by `ColorSettings::ordered_matrix`.
+pub const MATRIX_NAMES: [&str; 5] = ["Bayer", "Clustered Dot", "Lines", "Diagonal", "Custom"];
+/// `ordered_matrix` value that dithers against the look's imported matrix.
+pub const CUSTOM: usize = 4;
+/// Sizes offered in the UI for the generated kinds.
+pub const SIZES: [u32; 7] = [2, 3, 4, 8, 16, 32, 64];
+pub const MAX_SIZE: u32 = 64;
+/// Largest imported matrix, matching the largest blue-noise tile.
+pub const MAX_CUSTOM_SIZE: u32 = 256;
+
+/// Thresholds from arbitrary values: each cell gets the share of cells with a strictly lower
+/// value, so equal values share a threshold and `0..n` ranks map to `rank / n`.
+pub fn from_values(width: u32, height: u32, values: &[f32]) -> ThresholdMap {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let n = values.len() as f32;
+    ThresholdMap { width, height, values: values.iter().map(|v| sorted.partition_point(|s| s < v) as f32 / n).collect() }
+}
+
+/// Recursive Bayer index matrix of a power-of-two `size`.
+fn bayer_ranks(size: u32) -> Vec<u32> {
+    let mut m = vec![0u32];
+    let mut n = 1;
+    while n < size {
+        let mut next = vec![0u32; (4 * n * n) as usize];
+        for y in 0..n {
+            for x in 0..n {
+                let v = 4 * m[(y * n + x) as usize];
+                for (dx, dy, offset) in [(0, 0, 0), (n, 0, 2), (0, n, 3), (n, n, 1)] { next[((y + dy) * 2 * n + x + dx) as usize] = v + offset; }
+            }
+        }
+        m = next;
+        n *= 2;
+    }
+    m
+}
+
+/// The classic 3x3 ordered matrix, which has no recursive construction.
+const BAYER3: [u32; 9] = [0, 7, 3, 5, 1, 8, 2, 6, 4];
+
+/// Bayer matrix for `size`: 3, or the nearest power of two from 2 to 64.
+pub fn bayer(size: u32) -> ThresholdMap {
+    if size == 3 { return ThresholdMap::from_ranks(3, 3, &BAYER3); }
+    let size = size.clamp(2, MAX_SIZE).next_power_of_two();
+    ThresholdMap::from_ranks(size, size, &bayer_ranks(size))
+}
+
+/// A round dot growing from the cell center, ordered in a spiral so every cell gets its own level.
+pub fn clustered_dot(size: u32) -> ThresholdMap {
+    let size = size.clamp(2, MAX_SIZE);
+    let values: Vec<f32> = (0..size * size).map(|i| {
+        let u = ((i % size) as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+        let v = ((i / size) as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+        let spot = (std::f32::consts::PI * u).cos() + (std::f32::consts::PI * v).cos();
+        -spot + v.atan2(u) * 1e-3
+    }).collect();
+    from_values(size, size, &values)
+}
+
+/// Horizontal lines thickening out from the middle row.
+pub fn lines(size: u32) -> ThresholdMap {
+    let size = size.clamp(2, MAX_SIZE);
+    let values: Vec<f32> = (0..size * size).map(|i| ((i / size) as f32 + 0.5 - size as f32 / 2.0).abs()).collect();
+    from_values(size, size, &values)
+}
+
+/// 45-degree lines, the diagonal counterpart of [`lines`]; tiles seamlessly.
+pub fn diagonal(size: u32) -> ThresholdMap {
+    let size = size.clamp(2, MAX_SIZE);
+    let values: Vec<f32> = (0..size * size).map(|i| (((i % size + i / size) % size) as f32 + 0.5 - size as f32 / 2.0).abs()).collect();
+    from_values(size, size, &values)
+}
+
+/// Generated matrix of `kind` (an index into [`MATRIX_NAMES`] other than [`CUSTOM`]).
+pub fn generate(kind: usize, size: u32) -> ThresholdMap {
+    match kind {
+        1 => clustered_dot(size),
+        2 => lines(size),
+        3 => diagonal(size),
+        _ => bayer(size),
+    }
+}
+
+/// Parses a matrix of numbers, one row per line, separated by commas, semicolons or whitespace.
+pub fn parse_csv(text: &str) -> Result<ThresholdMap> {
+    let rows = text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).enumerate().map(|(row, line)| {
+        line.split(|c: char| c == ',' || c == ';' || c.is_whitespace()).filter(|s| !s.is_empty())
+            .map(|s| s.parse::<f32>().with_context(|| format!("row {}: '{}' is not a number", row + 1, s)))
+            .collect::<Result<Vec<_>>>()
+    }).collect::<Result<Vec<_>>>()?;
+    let width = rows.first().map_or(0, Vec::len);
+    if width == 0 { bail!("matrix is empty"); }
+    if let Some(row) = rows.iter().position(|r| r.len() != width) { bail!("row {} has {} values, expected {}", row + 1, rows[row].len(), width); }
+    from_checked_values(width as u32, rows.len() as u32, &rows.concat())
+}
+
+fn check_size(width: u32, height: u32) -> Result<()> {
+    if width > MAX_CUSTOM_SIZE || height > MAX_CUSTOM_SIZE { bail!("matrix is {}x{}, the largest supported is {}x{}", width, height, MAX_CUSTOM_SIZE, MAX_CUSTOM_SIZE); }
+    Ok(())
+}
+
+/// [`from_values`] for values from a file: the size has to be within [`MAX_CUSTOM_SIZE`] and
+/// match the value count, and every value has to be finite.
+pub fn from_checked_values(width: u32, height: u32, values: &[f32]) -> Result<ThresholdMap> {
+    check_size(width, height)?;
+    if width == 0 || height == 0 || values.len() as u64 != width as u64 * height as u64 { bail!("matrix is {}x{} but has {} values", width, height, values.len()); }
+    if let Some(i) = values.iter().position(|v| !v.is_finite()) { bail!("value {} of the matrix is not a finite number", i + 1); }
+    Ok(from_values(width, height, values))
+}
+
+/// Loads a custom matrix from a CSV/text file or from a grayscale image, where brighter pixels
+/// get higher thresholds.
+pub fn load(path: &Path) -> Result<ThresholdMap> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    if matches!(ext.as_str(), "csv" | "txt" | "tsv") {
+        let text = std::fs::read_to_string(path).with_context(|| format!("reading {:?}", path))?;
+        return parse_csv(&text).with_context(|| format!("parsing matrix {:?}", path));
+    }
+    let img = image::open(path).with_context(|| format!("opening matrix image {:?}", path))?.into_luma16();
+    check_size(img.width(), img.height())?;
+    let values: Vec<f32> = img.pixels().map(|p| p[0] as f32).collect();
+    Ok(from_values(img.width(), img.height(), &values))
+}
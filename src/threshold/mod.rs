@@ -0,0 +1,73 @@
+//! Tiled threshold maps: the per-cell thresholds ordered dither modes compare against.
+//!
+//! The active map is uploaded to `threshold_texture` (binding 5) and tiled across the image in
+//! dither cells, so the shader and the CPU reference read the same value for the same cell.
+//! Halftone instead stretches one map over each rotated screen cell.
+
+pub mod blue_noise;
+pub mod halftone;
+pub mod matrix;
+
+use crate::pipeline::ColorSettings;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+/// A `width` x `height` grid of thresholds in `[0, 1)`, row-major.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThresholdMap {
+    pub width: u32,
+    pub height: u32,
+    pub values: Vec<f32>,
+}
+
+impl ThresholdMap {
+    /// Thresholds `rank / n` for a grid in which every cell has a distinct rank, the scaling of the
+    /// original hard-coded Bayer tables.
+    pub fn from_ranks(width: u32, height: u32, ranks: &[u32]) -> Self {
+        let n = ranks.len() as f32;
+        Self { width, height, values: ranks.iter().map(|&r| r as f32 / n).collect() }
+    }
+
+    /// Threshold of dither cell `p`, wrapping around the map like the shader's `textureLoad`.
+    pub fn at(&self, p: [f32; 2]) -> f32 {
+        let x = p[0] as u32 % self.width;
+        let y = p[1] as u32 % self.height;
+        self.values[(y * self.width + x) as usize]
+    }
+
+    /// Bound while no mode reads the map, since the shader always needs something at binding 5.
+    pub fn placeholder() -> Arc<Self> {
+        static FLAT: OnceLock<Arc<ThresholdMap>> = OnceLock::new();
+        FLAT.get_or_init(|| Arc::new(Self { width: 1, height: 1, values: vec![0.5] })).clone()
+    }
+}
+
+/// Maps built so far, so an unchanged setting hands back the same `Arc` and skips the upload.
+type MapCache<K> = Mutex<Option<HashMap<K, Arc<ThresholdMap>>>>;
+
+/// Generated ordered matrix, built once per kind and size.
+fn ordered_matrix(kind: usize, size: u32) -> Arc<ThresholdMap> {
+    static MAPS: MapCache<(usize, u32)> = Mutex::new(None);
+    MAPS.lock().get_or_insert_with(HashMap::new).entry((kind, size)).or_insert_with(|| Arc::new(matrix::generate(kind, size))).clone()
+}
+
+/// The map `settings` dithers against. `custom` is the look's imported matrix, used when
+/// `ordered_matrix` selects [`matrix::CUSTOM`]; without one, ordered dithering falls back to Bayer.
+pub fn for_settings(settings: &ColorSettings, custom: Option<&Arc<ThresholdMap>>) -> Arc<ThresholdMap> {
+    match settings.dither_type as i32 {
+        3 => match (settings.ordered_matrix as usize, custom) {
+            (matrix::CUSTOM, Some(m)) => m.clone(),
+            (kind, _) => ordered_matrix(if kind == matrix::CUSTOM { 0 } else { kind }, settings.bayer_size.max(2.0) as u32),
+        },
+        4 => blue_noise::threshold_map(settings.blue_noise_size as u32),
+        11 => halftone::threshold_map(settings.halftone_shape as usize),
+        _ => ThresholdMap::placeholder(),
+    }
+}
+
+/// False while the map `settings` needs is still being generated in the background, which
+/// [`for_settings`] would wait for. Starts that generation if nothing has yet.
+pub fn is_ready(settings: &ColorSettings) -> bool {
+    settings.dither_type as i32 != 4 || blue_noise::try_threshold_map(settings.blue_noise_size as u32).is_some()
+}
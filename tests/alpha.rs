@@ -0,0 +1,85 @@
+//! Alpha is carried from the source to the output, can be dithered to a 1-bit cutout or written
+//! premultiplied, and is flattened onto a matte for formats without it.
+
+mod common;
+
+use image::{DynamicImage, RgbaImage};
+use vibedither::image_io::{self, ExportFormat};
+use vibedither::{Backend, Look, Renderer};
+use common::{cpu_renderer, dither_look};
+
+/// A color ramp across, fading from opaque at the top to clear at the bottom.
+fn faded_image() -> DynamicImage {
+    let (w, h) = (64, 64);
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(w, h, |x, y| image::Rgba([(x * 255 / (w - 1)) as u8, 180, 60, 255 - (y * 255 / (h - 1)) as u8])))
+}
+
+#[test]
+fn source_alpha_reaches_the_output() {
+    let img = faded_image();
+    for look in [Look::default(), dither_look(3)] {
+        let out = cpu_renderer().render(&img, &look).unwrap();
+        assert!(out.pixels().zip(img.to_rgba8().pixels()).all(|(o, i)| o[3] == i[3]), "alpha changed for dither type {}", look.settings.dither_type);
+    }
+}
+
+#[test]
+fn dithered_alpha_is_one_bit_and_keeps_coverage() {
+    let img = faded_image();
+    let mean_alpha = |img: &RgbaImage| img.pixels().map(|p| p[3] as f32 / 255.0).sum::<f32>() / (img.width() * img.height()) as f32;
+    let mut looks = vec![dither_look(3), dither_look(3)];
+    looks[1].settings.dither_type = vibedither::pipeline::DITHER_ERROR_DIFFUSION as f32;
+    for mut look in looks {
+        look.settings.alpha_dither = 1.0;
+        let out = cpu_renderer().render(&img, &look).unwrap();
+        assert!(out.pixels().all(|p| p[3] == 0 || p[3] == 255), "dither type {} left partial alpha", look.settings.dither_type);
+        assert!((mean_alpha(&out) - mean_alpha(&img.to_rgba8())).abs() < 0.03, "dither type {} lost coverage", look.settings.dither_type);
+    }
+}
+
+#[test]
+fn premultiplied_output_clears_transparent_color() {
+    let mut look = Look::default();
+    look.settings.alpha_premultiply = 1.0;
+    let out = cpu_renderer().render(&faded_image(), &look).unwrap();
+    let bottom = out.get_pixel(40, 63);
+    assert_eq!(bottom.0, [0, 0, 0, 0]);
+    let half = out.get_pixel(40, 32);
+    assert!(half[1] < 180 && half[1] > 100, "premultiplied green {} should sit between 0 and 180", half[1]);
+}
+
+#[test]
+fn straight_and_premultiplied_flatten_to_the_same_matte_blend() {
+    let img = faded_image();
+    let straight = image_io::flatten(&DynamicImage::ImageRgba8(cpu_renderer().render(&img, &Look::default()).unwrap()), [255, 0, 255], false);
+    let mut look = Look::default();
+    look.settings.alpha_premultiply = 1.0;
+    let premultiplied = image_io::flatten(&DynamicImage::ImageRgba8(cpu_renderer().render(&img, &look).unwrap()), [255, 0, 255], true);
+    let (a, b) = (straight.to_rgb8(), premultiplied.to_rgb8());
+    // Premultiplying rounds color to 8 bits before the divide, so near-clear pixels drift a little.
+    assert!(a.pixels().zip(b.pixels()).all(|(p, q)| p.0.iter().zip(q.0.iter()).all(|(x, y)| x.abs_diff(*y) <= 2)));
+    assert_eq!(a.get_pixel(10, 63).0, [255, 0, 255], "fully clear pixels show the matte");
+    assert_eq!(a.get_pixel(10, 0).0, img.to_rgb8().get_pixel(10, 0).0, "opaque pixels keep their color");
+}
+
+#[test]
+fn jpg_export_flattens_onto_the_matte() {
+    assert!(!ExportFormat::Jpg.supports_alpha() && ExportFormat::Png.supports_alpha());
+    let path = std::env::temp_dir().join(format!("vibedither_matte_{}.jpg", std::process::id()));
+    let flat = image_io::flatten(&DynamicImage::ImageRgba8(cpu_renderer().render(&faded_image(), &Look::default()).unwrap()), [255, 255, 255], false);
+    image_io::save_image(&path, &flat, ExportFormat::Jpg, 0.95).unwrap();
+    let back = image_io::load_from_path(&path).unwrap().to_rgb8();
+    std::fs::remove_file(&path).unwrap();
+    assert!(back.get_pixel(32, 63).0.iter().all(|&c| c > 245), "clear pixels should come back white, got {:?}", back.get_pixel(32, 63));
+}
+
+#[test]
+fn gpu_alpha_matches_the_cpu() {
+    let Ok(gpu) = Renderer::with_backend(Backend::Gpu) else { eprintln!("no GPU adapter available, skipping"); return };
+    let mut look = dither_look(3);
+    look.settings.alpha_dither = 1.0; look.settings.alpha_premultiply = 1.0; look.settings.dither_color = 1.0;
+    let img = faded_image();
+    let (g, c) = (gpu.render(&img, &look).unwrap(), cpu_renderer().render(&img, &look).unwrap());
+    let bad = g.pixels().zip(c.pixels()).filter(|(p, q)| p.0.iter().zip(q.0.iter()).any(|(x, y)| x.abs_diff(*y) > 2)).count();
+    assert!(bad * 50 < (img.width() * img.height()) as usize, "{} pixels differ between GPU and CPU", bad);
+}
@@ -0,0 +1,46 @@
+//! Void-and-cluster threshold maps must be deterministic permutations with no low-frequency clumping.
+
+use vibedither::threshold::blue_noise::{self, void_and_cluster};
+
+#[test]
+fn ranks_are_a_deterministic_permutation() {
+    let ranks = void_and_cluster(32, 7);
+    let mut sorted = ranks.clone();
+    sorted.sort_unstable();
+    assert!(sorted.iter().enumerate().all(|(i, &r)| r == i as u32));
+    assert_eq!(ranks, void_and_cluster(32, 7));
+    assert_ne!(ranks, void_and_cluster(32, 8));
+}
+
+#[test]
+fn every_threshold_level_is_evenly_spread() {
+    let size = 32;
+    let ranks = void_and_cluster(size, 1);
+    // At any fill level, each 8x8 tile should hold close to its share of the set pixels; white noise
+    // routinely misses by 10 or more at this tile size.
+    for fill in [0.1, 0.25, 0.5, 0.75] {
+        let cutoff = (fill * (size * size) as f32) as u32;
+        for ty in 0..4 {
+            for tx in 0..4 {
+                let on = (0..64).filter(|i| ranks[((ty * 8 + i / 8) * size + tx * 8 + i % 8) as usize] < cutoff).count() as f32;
+                assert!((on - fill * 64.0).abs() <= 4.0, "fill {}: tile ({}, {}) has {} set pixels", fill, tx, ty, on);
+            }
+        }
+    }
+}
+
+#[test]
+fn missing_maps_generate_in_the_background() {
+    let dir = std::env::temp_dir().join(format!("vibedither_noise_{}", std::process::id()));
+    std::env::set_var("VIBEDITHER_CACHE_DIR", &dir);
+    assert!(blue_noise::try_threshold_map(48).is_none(), "an uncached map is not ready at once");
+    let start = std::time::Instant::now();
+    let map = loop {
+        if let Some(map) = blue_noise::try_threshold_map(48) { break map; }
+        assert!(start.elapsed().as_secs() < 60, "background generation never finished");
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+    assert_eq!(map, blue_noise::threshold_map(48));
+    assert!(dir.join(format!("blue_noise_48_{:x}.png", blue_noise::SEED)).is_file(), "the map is cached on disk");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,59 @@
+//! Argument parsing of `vibedither-cli`: the look it builds is checked through `--save-preset`.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use vibedither::{preset, CurvePoint, Look};
+
+/// A scratch directory holding one small input image.
+fn workdir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vibedither_cli_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    image::RgbaImage::from_pixel(4, 4, image::Rgba([90, 120, 200, 255])).save(dir.join("in.png")).unwrap();
+    dir
+}
+
+fn run_cli(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_vibedither-cli")).arg(dir.join("in.png")).args(["--backend", "cpu", "-o"]).arg(dir.join("out")).args(args).output().unwrap()
+}
+
+/// Runs the CLI and returns the look it rendered with.
+fn parsed_look(name: &str, args: &[&str]) -> Look {
+    let dir = workdir(name);
+    let preset_path = dir.join("look.vdpreset");
+    let out = run_cli(&dir, &[args, &["--save-preset", preset_path.to_str().unwrap()]].concat());
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let look = preset::load(&preset_path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    look
+}
+
+/// Runs the CLI expecting it to fail cleanly, and returns its error output.
+fn rejected(name: &str, args: &[&str]) -> String {
+    let dir = workdir(name);
+    let out = run_cli(&dir, args);
+    std::fs::remove_dir_all(&dir).unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr).into_owned();
+    assert_eq!(out.status.code(), Some(1), "{:?} should fail with an error: {}", args, stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+    stderr
+}
+
+#[test]
+fn curves_gradient_and_settings_reach_the_look() {
+    let look = parsed_look("ok", &["--curve", "red:1,1;0,0.1;0.5,0.4", "--gradient", "1:#ffcc00,0:#000000", "--set", "dither_scale=3", "--set", "exposure=-0.5"]);
+    assert_eq!(look.curves[1], vec![CurvePoint::new(0.0, 0.1), CurvePoint::new(0.5, 0.4), CurvePoint::new(1.0, 1.0)], "points are sorted by x");
+    assert_eq!(look.curves[0], Look::default().curves[0]);
+    assert_eq!(look.gradient.iter().map(|s| (s.pos, s.color)).collect::<Vec<_>>(), vec![(0.0, [0, 0, 0]), (1.0, [255, 204, 0])]);
+    assert_eq!(look.settings.grad_enabled, 1.0);
+    assert_eq!((look.settings.dither_scale, look.settings.exposure), (3.0, -0.5));
+}
+
+#[test]
+fn malformed_arguments_are_errors() {
+    assert!(rejected("nan_curve", &["--curve", "master:0,0;nan,0.5;1,1"]).contains("not a finite number"));
+    assert!(rejected("nan_stop", &["--gradient", "0:#000000,nan:#ffffff"]).contains("not a finite number"));
+    assert!(rejected("inf_set", &["--set", "exposure=inf"]).contains("not a finite number"));
+    assert!(rejected("channel", &["--curve", "alpha:0,0;1,1"]).contains("unknown curve channel"));
+    assert!(rejected("field", &["--set", "no_such_field=1"]).contains("unknown setting"));
+    assert!(rejected("pair", &["--set", "exposure"]).contains("expected FIELD=VALUE"));
+}
@@ -0,0 +1,76 @@
+//! Color distance metrics: reference CIEDE2000 values and the nearest palette entry each metric picks.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use vibedither::pipeline::cpu::{delta_e2000, linear_to_lab, metric_distance, srgb_to_linear, to_metric_space};
+use vibedither::{Backend, Look, Renderer};
+
+const LINEAR_RGB: f32 = 0.0;
+const WEIGHTED_RGB: f32 = 1.0;
+const OKLAB: f32 = 2.0;
+const DELTA_E76: f32 = 3.0;
+const DELTA_E2000: f32 = 4.0;
+
+fn linear(c: [u8; 3]) -> [f32; 3] {
+    c.map(|v| srgb_to_linear(v as f32 / 255.0))
+}
+
+fn distance(a: [u8; 3], b: [u8; 3], metric: f32) -> f32 {
+    metric_distance(to_metric_space(linear(a), metric), to_metric_space(linear(b), metric), metric)
+}
+
+#[test]
+fn delta_e2000_matches_sharma_test_data() {
+    // Pairs from Sharma, Wu & Dalal's CIEDE2000 test set.
+    let pairs = [
+        ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+        ([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669),
+        ([50.0, 2.5, 0.0], [73.0, 25.0, -18.0], 27.1492),
+        ([50.0, 2.5, 0.0], [61.0, -5.0, 29.0], 22.8977),
+        ([50.0, 2.5, 0.0], [56.0, -27.0, -3.0], 31.9030),
+        ([50.0, 2.5, 0.0], [58.0, 24.0, 15.0], 19.4535),
+        ([50.0, 2.5, 0.0], [50.0, 3.1736, 0.5854], 1.0000),
+        ([60.2574, -34.0099, 36.2677], [60.4626, -34.1751, 39.4387], 1.2644),
+    ];
+    for (a, b, expected) in pairs {
+        let d = delta_e2000(a, b);
+        assert!((d - expected).abs() < 2e-3, "{:?} vs {:?}: {} expected {}", a, b, d, expected);
+        assert!((delta_e2000(b, a) - d).abs() < 1e-4, "symmetric");
+    }
+}
+
+#[test]
+fn cielab_of_srgb_primaries() {
+    for (c, lab) in [([255, 255, 255], [100.0, 0.0, 0.0]), ([255, 0, 0], [53.24, 80.09, 67.20]), ([0, 0, 255], [32.30, 79.19, -107.86])] {
+        let got = linear_to_lab(linear(c));
+        assert!((0..3).all(|i| (got[i] - lab[i]).abs() < 0.05), "{:?}: {:?} expected {:?}", c, got, lab);
+    }
+    // ΔE76 is plain Euclidean distance in CIELAB.
+    assert!((distance([255, 0, 0], [0, 0, 255], DELTA_E76) - 176.3).abs() < 0.1);
+}
+
+#[test]
+fn metrics_disagree_where_they_should() {
+    // Dark gray sits closer to black in linear light but closer to mid gray to the eye.
+    let (gray, black, mid) = ([70, 70, 70], [0, 0, 0], [119, 119, 119]);
+    assert!(distance(gray, black, LINEAR_RGB) < distance(gray, mid, LINEAR_RGB));
+    for metric in [OKLAB, DELTA_E76, DELTA_E2000] {
+        assert!(distance(gray, mid, metric) < distance(gray, black, metric), "metric {}", metric);
+    }
+    // Weighted RGB counts a green error more than the same blue one; plain linear RGB does not.
+    let (green, blue) = ([0, 128, 0], [0, 0, 128]);
+    assert_eq!(distance(green, black, LINEAR_RGB), distance(blue, black, LINEAR_RGB));
+    assert!(distance(green, black, WEIGHTED_RGB) > 3.0 * distance(blue, black, WEIGHTED_RGB));
+}
+
+#[test]
+fn palette_snapping_follows_the_metric() {
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([70, 70, 70, 255])));
+    let cpu = Renderer::with_backend(Backend::Cpu).unwrap();
+    let mut look = Look { palette: vec![[0, 0, 0], [119, 119, 119]], ..Look::default() };
+    look.settings.palette_enabled = 1.0;
+    for (metric, expected) in [(LINEAR_RGB, [0, 0, 0]), (OKLAB, [119, 119, 119]), (DELTA_E2000, [119, 119, 119])] {
+        look.settings.color_metric = metric;
+        let out = cpu.render(&img, &look).unwrap();
+        assert!(out.pixels().all(|p| p.0[..3] == expected), "metric {} gave {:?}", metric, out.get_pixel(0, 0));
+    }
+}
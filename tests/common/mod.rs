@@ -0,0 +1,28 @@
+//! Images, looks and renderers the integration tests share. Each test crate uses only some.
+#![allow(dead_code)]
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use vibedither::{Backend, Look, Renderer};
+
+/// An opaque `w` x `h` image of one sRGB color.
+pub fn flat_color(w: u32, h: u32, c: [u8; 3]) -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_pixel(w, h, Rgba([c[0], c[1], c[2], 255])))
+}
+
+/// An opaque `w` x `h` gray image.
+pub fn flat(w: u32, h: u32, v: u8) -> DynamicImage {
+    flat_color(w, h, [v; 3])
+}
+
+/// The default look with dithering on, of `dither_type` (an index into `DITHER_NAMES`).
+pub fn dither_look(dither_type: usize) -> Look {
+    let mut look = Look::default();
+    look.settings.dither_enabled = 1.0;
+    look.settings.dither_type = dither_type as f32;
+    look
+}
+
+/// The CPU reference renderer, which is always available.
+pub fn cpu_renderer() -> Renderer {
+    Renderer::with_backend(Backend::Cpu).unwrap()
+}
@@ -0,0 +1,151 @@
+//! The CPU reference pipeline must reproduce `shaders.wgsl`. The GPU comparisons run on whatever
+//! adapter wgpu finds (a software rasterizer is enough) and are skipped when there is none.
+
+use image::{DynamicImage, RgbaImage};
+use vibedither::pipeline::{COLOR_METRIC_NAMES, DITHER_NAMES};
+use vibedither::{Backend, CurvePoint, GradientStop, Look, Renderer};
+
+/// Smooth ramps plus a hard-edged checker so both tone handling and edge-driven modes get exercised.
+fn test_image() -> DynamicImage {
+    let (w, h) = (97, 61);
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(w, h, |x, y| {
+        let checker = if (x / 8 + y / 8) % 2 == 0 { 40 } else { 0 };
+        image::Rgba([
+            (x * 255 / (w - 1)) as u8,
+            (y * 255 / (h - 1)) as u8,
+            ((x + y) * 255 / (w + h - 2)) as u8 / 2 + checker,
+            255,
+        ])
+    }))
+}
+
+fn gpu_renderer() -> Option<Renderer> {
+    let renderer = Renderer::with_backend(Backend::Gpu).ok();
+    if renderer.is_none() { eprintln!("no GPU adapter available, skipping GPU parity check"); }
+    renderer
+}
+
+/// Fraction of pixels whose channels differ by more than `tolerance`.
+fn mismatch_ratio(a: &RgbaImage, b: &RgbaImage, tolerance: u8) -> f32 {
+    assert_eq!(a.dimensions(), b.dimensions());
+    let bad = a.pixels().zip(b.pixels()).filter(|(p, q)| p.0.iter().zip(q.0.iter()).any(|(x, y)| x.abs_diff(*y) > tolerance)).count();
+    bad as f32 / (a.width() * a.height()) as f32
+}
+
+fn mean_abs_diff(a: &RgbaImage, b: &RgbaImage) -> f32 {
+    a.as_raw().iter().zip(b.as_raw().iter()).map(|(x, y)| x.abs_diff(*y) as f32).sum::<f32>() / a.as_raw().len() as f32
+}
+
+fn assert_parity(gpu: &Renderer, cpu: &Renderer, img: &DynamicImage, look: &Look, what: &str) {
+    let g = gpu.render(img, look).unwrap();
+    let c = cpu.render(img, look).unwrap();
+    // GPU pow/exp2 are a few ulps off the CPU's, which can flip a dither decision that sits right on
+    // its threshold. Those stay rare and the average error stays well below a level.
+    let ratio = mismatch_ratio(&g, &c, 2);
+    let mad = mean_abs_diff(&g, &c);
+    assert!(ratio < 0.05 && mad < 0.5, "{}: {:.2}% of pixels differ between GPU and CPU (mean diff {:.3})", what, ratio * 100.0, mad);
+}
+
+#[test]
+fn cpu_threshold_output_is_one_bit() {
+    let mut look = Look::default();
+    look.settings.dither_enabled = 1.0;
+    look.settings.dither_type = 1.0;
+    let out = Renderer::with_backend(Backend::Cpu).unwrap().render(&test_image(), &look).unwrap();
+    assert!(out.pixels().all(|p| p[0] == p[1] && p[1] == p[2] && (p[0] == 0 || p[0] == 255)));
+    assert!(out.pixels().any(|p| p[0] == 0) && out.pixels().any(|p| p[0] == 255));
+}
+
+#[test]
+fn cpu_matches_gpu_for_every_dither_type() {
+    let Some(gpu) = gpu_renderer() else { return };
+    let cpu = Renderer::with_backend(Backend::Cpu).unwrap();
+    let img = test_image();
+    for (d_type, name) in DITHER_NAMES.iter().enumerate() {
+        for (color, levels, scale) in [(0.0, 0.0, 1.0), (1.0, 4.0, 3.0)] {
+            let mut look = Look::default();
+            look.settings.dither_enabled = if d_type > 0 { 1.0 } else { 0.0 };
+            look.settings.dither_type = d_type as f32;
+            look.settings.dither_color = color;
+            look.settings.posterize_levels = levels;
+            look.settings.dither_scale = scale;
+            assert_parity(&gpu, &cpu, &img, &look, &format!("{} (color {}, levels {}, scale {})", name, color, levels, scale));
+        }
+    }
+}
+
+#[test]
+fn cpu_matches_gpu_with_adjustments_curves_and_gradient() {
+    let Some(gpu) = gpu_renderer() else { return };
+    let cpu = Renderer::with_backend(Backend::Cpu).unwrap();
+    let img = test_image();
+    let mut look = Look::default();
+    let s = &mut look.settings;
+    s.exposure = 0.4; s.contrast = 1.3; s.highlights = -0.3; s.shadows = 0.4; s.whites = 0.2; s.blacks = -0.2;
+    s.temperature = 0.3; s.tint = -0.2; s.saturation = 1.4; s.vibrance = 0.3; s.sharpness = 0.5;
+    s.dither_enabled = 1.0; s.dither_type = 3.0; s.bayer_size = 4.0; s.grad_enabled = 1.0;
+    look.curves[0] = vec![CurvePoint::new(0.0, 0.1), CurvePoint::new(0.5, 0.7), CurvePoint::new(1.0, 0.9)];
+    look.curves[2] = vec![CurvePoint::new(0.0, 0.0), CurvePoint::new(0.3, 0.5), CurvePoint::new(1.0, 1.0)];
+    look.gradient = vec![GradientStop::new(0, 0.0, [10, 20, 60]), GradientStop::new(1, 0.5, [200, 40, 90]), GradientStop::new(2, 1.0, [255, 240, 180])];
+    assert_parity(&gpu, &cpu, &img, &look, "adjusted Bayer with gradient");
+
+    look.settings.dither_enabled = 0.0; look.settings.posterize_levels = 5.0;
+    assert_parity(&gpu, &cpu, &img, &look, "posterize without dithering");
+}
+
+#[test]
+fn cpu_matches_gpu_in_gamma_working_spaces() {
+    let Some(gpu) = gpu_renderer() else { return };
+    let cpu = Renderer::with_backend(Backend::Cpu).unwrap();
+    let img = test_image();
+    let palette = vec![[20, 12, 28], [68, 36, 52], [48, 52, 109], [133, 76, 48], [52, 101, 36], [208, 70, 72], [89, 125, 206], [218, 212, 94], [222, 238, 214]];
+    let mut look = Look { palette, ..Look::default() };
+    look.settings.sharpness = 0.5; look.settings.contrast = 1.2;
+    look.curves[0] = vec![CurvePoint::new(0.0, 0.1), CurvePoint::new(0.5, 0.6), CurvePoint::new(1.0, 0.9)];
+    for (adjust, dither) in [(1.0, 1.0), (1.0, 0.0), (0.0, 1.0)] {
+        look.settings.adjust_space = adjust; look.settings.dither_space = dither;
+        for (d_type, color, palette, grad) in [(3.0, 0.0, 0.0, 0.0), (3.0, 1.0, 0.0, 1.0), (8.0, 1.0, 0.0, 0.0), (4.0, 1.0, 1.0, 0.0), (11.0, 0.0, 0.0, 0.0)] {
+            let s = &mut look.settings;
+            s.dither_enabled = 1.0; s.dither_type = d_type; s.dither_color = color; s.palette_enabled = palette; s.grad_enabled = grad;
+            assert_parity(&gpu, &cpu, &img, &look, &format!("{} in adjust space {} / dither space {} (palette {}, gradient {})", DITHER_NAMES[d_type as usize], adjust, dither, palette, grad));
+        }
+    }
+}
+
+#[test]
+fn cpu_matches_gpu_for_cmyk_halftone() {
+    let Some(gpu) = gpu_renderer() else { return };
+    let cpu = Renderer::with_backend(Backend::Cpu).unwrap();
+    let img = test_image();
+    let mut look = Look::default();
+    let s = &mut look.settings;
+    s.dither_enabled = 1.0; s.dither_type = 11.0; s.halftone_cmyk = 1.0; s.halftone_frequency = 12.0; s.cmyk_black = 0.7; s.cmyk_black_start = 0.2;
+    assert_parity(&gpu, &cpu, &img, &look, "CMYK halftone composite");
+    look.settings = look.settings.with_cmyk_plate(1);
+    assert_parity(&gpu, &cpu, &img, &look, "CMYK halftone magenta plate");
+}
+
+#[test]
+fn cpu_matches_gpu_for_palette_dithering() {
+    let Some(gpu) = gpu_renderer() else { return };
+    let cpu = Renderer::with_backend(Backend::Cpu).unwrap();
+    let img = test_image();
+    let palette = vec![[20, 12, 28], [68, 36, 52], [48, 52, 109], [133, 76, 48], [52, 101, 36], [208, 70, 72], [89, 125, 206], [218, 212, 94], [222, 238, 214]];
+    let mut look = Look { palette, ..Look::default() };
+    look.settings.palette_enabled = 1.0;
+    for (d_type, color) in [(0.0, 1.0), (3.0, 1.0), (3.0, 0.0), (4.0, 1.0), (11.0, 1.0)] {
+        look.settings.dither_enabled = if d_type > 0.0 { 1.0 } else { 0.0 };
+        look.settings.dither_type = d_type;
+        look.settings.dither_color = color;
+        assert_parity(&gpu, &cpu, &img, &look, &format!("{} to a palette (color {})", DITHER_NAMES[d_type as usize], color));
+    }
+    look.settings.dither_color = 1.0;
+    for (metric, name) in COLOR_METRIC_NAMES.iter().enumerate().skip(1) {
+        look.settings.color_metric = metric as f32;
+        for d_type in [0.0, 3.0] {
+            look.settings.dither_enabled = if d_type > 0.0 { 1.0 } else { 0.0 };
+            look.settings.dither_type = d_type;
+            assert_parity(&gpu, &cpu, &img, &look, &format!("{} to a palette by {}", DITHER_NAMES[d_type as usize], name));
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! 16-bit and float sources keep their precision through the half-float pipeline and can be
+//! written back out at 16 bits per channel.
+
+use image::{DynamicImage, ImageBuffer, Rgba, Rgba32FImage};
+use vibedither::image_io::{self, ExportFormat};
+use vibedither::{Backend, CurvePoint, Look, Renderer};
+
+/// A horizontal 16-bit gray ramp far finer than 256 levels.
+fn ramp16(width: u32) -> DynamicImage {
+    DynamicImage::ImageRgba16(ImageBuffer::from_fn(width, 1, |x, _| { let v = (x * 65535 / (width - 1)) as u16; Rgba([v, v, v, u16::MAX]) }))
+}
+
+fn distinct_levels(img: &ImageBuffer<Rgba<u16>, Vec<u16>>) -> usize {
+    let mut levels: Vec<u16> = img.pixels().map(|p| p[0]).collect();
+    levels.sort_unstable();
+    levels.dedup();
+    levels.len()
+}
+
+#[test]
+fn sixteen_bit_ramps_survive_curves_without_banding() {
+    let mut look = Look::default();
+    look.curves[0] = vec![CurvePoint::new(0.0, 0.0), CurvePoint::new(0.5, 0.6), CurvePoint::new(1.0, 1.0)];
+    let out = image_io::to_srgb16(&Renderer::with_backend(Backend::Cpu).unwrap().render_linear(&ramp16(4096), &look).unwrap());
+    // An 8-bit path collapses the ramp to at most 256 levels.
+    assert!(distinct_levels(&out) > 1500, "only {} levels left", distinct_levels(&out));
+    assert!(out.pixels().zip(out.pixels().skip(1)).all(|(a, b)| b[0] >= a[0]), "the ramp has to stay monotonic");
+}
+
+#[test]
+fn float_sources_are_taken_as_linear_light() {
+    let img = DynamicImage::ImageRgba32F(Rgba32FImage::from_pixel(4, 4, Rgba([0.18, 0.5, 2.0, 1.0])));
+    let out = Renderer::with_backend(Backend::Cpu).unwrap().render_linear(&img, &Look::default()).unwrap();
+    let p = out.get_pixel(0, 0);
+    assert!((p[0] - 0.18).abs() < 1e-3 && (p[1] - 0.5).abs() < 1e-3 && p[2] == 1.0, "{:?}", p);
+}
+
+#[test]
+fn gpu_linear_output_matches_the_cpu() {
+    let Ok(gpu) = Renderer::with_backend(Backend::Gpu) else { eprintln!("no GPU adapter available, skipping"); return };
+    let cpu = Renderer::with_backend(Backend::Cpu).unwrap();
+    let mut look = Look::default();
+    look.settings.contrast = 1.3; look.settings.saturation = 1.2;
+    look.curves[1] = vec![CurvePoint::new(0.0, 0.1), CurvePoint::new(0.4, 0.5), CurvePoint::new(1.0, 0.95)];
+    let img = ramp16(1024);
+    let (g, c) = (gpu.render_linear(&img, &look).unwrap(), cpu.render_linear(&img, &look).unwrap());
+    let worst = g.as_raw().iter().zip(c.as_raw()).map(|(a, b)| (a - b).abs()).fold(0.0f32, f32::max);
+    assert!(worst < 2e-3, "GPU and CPU differ by up to {}", worst);
+}
+
+#[test]
+fn sixteen_bit_png_and_tiff_round_trip() {
+    let img = ramp16(300);
+    let dir = std::env::temp_dir().join(format!("vibedither_deep_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for format in [ExportFormat::Png, ExportFormat::Tiff] {
+        assert!(format.supports_16bit());
+        let path = dir.join(format!("ramp.{}", format.extension().0));
+        image_io::save_image(&path, &img, format, 0.5).unwrap();
+        let back = image_io::load_from_path(&path).unwrap();
+        assert_eq!(back.to_rgba16(), img.to_rgba16(), "{:?} lost precision", format);
+    }
+    assert!(!ExportFormat::Jpg.supports_16bit());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,55 @@
+//! The serial error-diffusion pass (`dither_type` 10) against properties every kernel must keep.
+
+mod common;
+
+use image::{DynamicImage, RgbaImage};
+use vibedither::pipeline::{cpu, diffusion, DITHER_ERROR_DIFFUSION};
+use vibedither::{Backend, Look, Renderer};
+use common::{cpu_renderer, dither_look, flat};
+
+fn diffusion_look(kernel: usize) -> Look {
+    let mut look = dither_look(DITHER_ERROR_DIFFUSION);
+    look.settings.diffusion_kernel = kernel as f32;
+    look
+}
+
+#[test]
+fn every_kernel_preserves_mean_tone() {
+    let cpu = cpu_renderer();
+    let img = flat(64, 64, 150);
+    let target = cpu::srgb_to_linear(150.0 / 255.0);
+    for (k, kernel) in diffusion::KERNELS.iter().enumerate() {
+        let out = cpu.render(&img, &diffusion_look(k)).unwrap();
+        assert!(out.pixels().all(|p| p[0] == 0 || p[0] == 255), "{} is not one-bit", kernel.name);
+        let white = out.pixels().filter(|p| p[0] == 255).count() as f32 / (64.0 * 64.0);
+        // Atkinson drops a quarter of the error by design, so it is only held to a looser bound.
+        let tolerance = if kernel.name == "Atkinson" { 0.08 } else { 0.02 };
+        assert!((white - target).abs() < tolerance, "{}: {:.3} white, expected {:.3}", kernel.name, white, target);
+    }
+}
+
+#[test]
+fn gpu_backend_and_pixel_scale_use_the_cpu_pass() {
+    let img = flat(30, 20, 128);
+    let mut look = diffusion_look(0);
+    look.settings.dither_scale = 3.0;
+    let out = cpu_renderer().render(&img, &look).unwrap();
+    assert!(out.enumerate_pixels().all(|(x, y, p)| p == out.get_pixel(x / 3 * 3, y / 3 * 3)));
+    if let Ok(gpu) = Renderer::with_backend(Backend::Gpu) { assert_eq!(gpu.render(&img, &look).unwrap(), out); }
+}
+
+#[test]
+fn zero_strength_is_a_plain_threshold_and_serpentine_changes_the_pattern() {
+    let cpu = cpu_renderer();
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(48, 32, |x, _| image::Rgba([(x * 5) as u8, (x * 5) as u8, (x * 5) as u8, 255])));
+    let mut look = diffusion_look(0);
+    look.settings.diffusion_strength = 0.0;
+    let mut threshold = look.clone();
+    threshold.settings.dither_type = 1.0;
+    assert_eq!(cpu.render(&img, &look).unwrap(), cpu.render(&img, &threshold).unwrap());
+
+    look.settings.diffusion_strength = 1.0;
+    let raster = cpu.render(&img, &look).unwrap();
+    look.settings.diffusion_serpentine = 1.0;
+    assert_ne!(cpu.render(&img, &look).unwrap(), raster);
+}
@@ -0,0 +1,95 @@
+//! The AM halftone screen (`dither_type` 11): dot area has to follow tone for every shape and plate.
+
+mod common;
+
+use image::{DynamicImage, RgbaImage};
+use vibedither::pipeline::{cpu, DITHER_HALFTONE};
+use vibedither::threshold::halftone;
+use vibedither::Look;
+use common::{cpu_renderer, dither_look, flat};
+
+fn halftone_look(shape: usize, angle: f32) -> Look {
+    let mut look = dither_look(DITHER_HALFTONE);
+    look.settings.halftone_shape = shape as f32;
+    look.settings.halftone_angle = angle;
+    look.settings.halftone_frequency = 2.0;
+    look
+}
+
+#[test]
+fn every_shape_covers_the_cell_in_proportion_to_tone() {
+    let cpu = cpu_renderer();
+    for v in [60, 180] {
+        let img = flat(128, 128, v);
+        let target = cpu::srgb_to_linear(v as f32 / 255.0);
+        for (shape, name) in halftone::SHAPE_NAMES.iter().enumerate() {
+            for angle in [0.0, 30.0] {
+                let out = cpu.render(&img, &halftone_look(shape, angle)).unwrap();
+                assert!(out.pixels().all(|p| p[0] == 0 || p[0] == 255), "{} is not one-bit", name);
+                let white = out.pixels().filter(|p| p[0] == 255).count() as f32 / (128.0 * 128.0);
+                // Square and line dots grow a whole ring or row of pixels at a time, a few percent of a 64 px cell.
+                assert!((white - target).abs() < 0.035, "{} at {} deg, tone {}: {:.3} white, expected {:.3}", name, angle, v, white, target);
+            }
+        }
+    }
+}
+
+#[test]
+fn black_and_white_stay_solid() {
+    let cpu = cpu_renderer();
+    for shape in 0..halftone::SHAPE_NAMES.len() {
+        let look = halftone_look(shape, 15.0);
+        assert!(cpu.render(&flat(64, 64, 0), &look).unwrap().pixels().all(|p| p[0] == 0));
+        assert!(cpu.render(&flat(64, 64, 255), &look).unwrap().pixels().all(|p| p[0] == 255));
+    }
+}
+
+#[test]
+fn dots_grow_from_the_cell_center() {
+    // One cell spanning the whole image at 0 degrees: a dark tone leaves a single dot in the middle.
+    let mut look = halftone_look(0, 0.0);
+    look.settings.halftone_frequency = 1.0;
+    let out = cpu_renderer().render(&flat(64, 64, 200), &look).unwrap();
+    assert_eq!(out.get_pixel(32, 32)[0], 0);
+    assert_eq!(out.get_pixel(0, 0)[0], 255);
+    assert_eq!(out.get_pixel(63, 63)[0], 255);
+}
+
+fn cmyk_look(black: f32, plate: Option<usize>) -> Look {
+    let mut look = halftone_look(0, 45.0);
+    look.settings.halftone_cmyk = 1.0;
+    look.settings.cmyk_black = black;
+    if let Some(i) = plate { look.settings = look.settings.with_cmyk_plate(i); }
+    look
+}
+
+fn ink(out: &RgbaImage) -> f32 {
+    out.pixels().filter(|p| p[0] == 0).count() as f32 / (out.width() * out.height()) as f32
+}
+
+#[test]
+fn gray_goes_to_black_with_full_gcr_and_to_cmy_without() {
+    let cpu = cpu_renderer();
+    let img = flat(128, 128, 150);
+    let target = 1.0 - cpu::srgb_to_linear(150.0 / 255.0);
+    let plates = |black: f32| -> Vec<f32> { (0..4).map(|i| ink(&cpu.render(&img, &cmyk_look(black, Some(i))).unwrap())).collect() };
+
+    let gcr = plates(1.0);
+    assert!(gcr[..3].iter().all(|&c| c == 0.0), "full GCR left color ink in a gray: {:?}", gcr);
+    assert!((gcr[3] - target).abs() < 0.035, "K covers {:.3}, expected {:.3}", gcr[3], target);
+
+    let cmy = plates(0.0);
+    assert_eq!(cmy[3], 0.0, "no black generation, yet K has ink");
+    assert!(cmy[..3].iter().all(|&c| (c - target).abs() < 0.035), "CMY coverage {:?}, expected {:.3}", cmy, target);
+}
+
+#[test]
+fn cmyk_composite_keeps_the_color() {
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(128, 128, image::Rgba([230, 120, 40, 255])));
+    let out = cpu_renderer().render(&img, &cmyk_look(0.5, None)).unwrap();
+    for (c, v) in [230u8, 120, 40].into_iter().enumerate() {
+        let mean = out.pixels().map(|p| cpu::srgb_to_linear(p[c] as f32 / 255.0)).sum::<f32>() / (128.0 * 128.0);
+        let target = cpu::srgb_to_linear(v as f32 / 255.0);
+        assert!((mean - target).abs() < 0.05, "channel {}: mean {:.3}, expected {:.3}", c, mean, target);
+    }
+}
@@ -0,0 +1,57 @@
+//! Undo/redo steps over whole looks.
+
+use vibedither::history::{History, MAX_STEPS};
+use vibedither::{CurvePoint, Look};
+
+fn with_exposure(ev: f32) -> Look {
+    let mut look = Look::default();
+    look.settings.exposure = ev;
+    look
+}
+
+#[test]
+fn undo_and_redo_walk_the_recorded_steps() {
+    let mut history = History::new(&Look::default());
+    assert!(!history.can_undo());
+    let mut look = with_exposure(0.5);
+    assert!(history.record(&look));
+    look.curves[0].insert(1, CurvePoint::new(0.5, 0.7));
+    assert!(history.record(&look));
+    assert!(!history.record(&look), "an unchanged look is not a step");
+
+    assert_eq!(history.undo().unwrap(), with_exposure(0.5));
+    assert_eq!(history.undo().unwrap(), Look::default());
+    assert!(history.undo().is_none());
+    assert_eq!(history.redo().unwrap(), with_exposure(0.5));
+    assert_eq!(history.redo().unwrap(), look);
+    assert!(!history.can_redo());
+}
+
+#[test]
+fn a_new_edit_after_undo_drops_the_redo_steps() {
+    let mut history = History::new(&Look::default());
+    history.record(&with_exposure(1.0));
+    history.undo();
+    assert!(history.can_redo());
+    history.record(&with_exposure(-1.0));
+    assert!(!history.can_redo());
+    assert_eq!(history.undo().unwrap(), Look::default());
+}
+
+#[test]
+fn only_the_newest_steps_are_kept() {
+    let mut history = History::new(&Look::default());
+    for i in 1..=MAX_STEPS + 10 { history.record(&with_exposure(i as f32)); }
+    let mut steps = 0;
+    while history.undo().is_some() { steps += 1; }
+    assert_eq!(steps, MAX_STEPS);
+}
+
+#[test]
+fn steps_share_the_custom_matrix() {
+    let look = Look { custom_matrix: Some(std::sync::Arc::new(vibedither::threshold::matrix::bayer(64))), ..Look::default() };
+    let mut history = History::new(&look);
+    history.record(&with_exposure(1.0));
+    let back = history.undo().unwrap();
+    assert!(std::sync::Arc::ptr_eq(back.custom_matrix.as_ref().unwrap(), look.custom_matrix.as_ref().unwrap()));
+}
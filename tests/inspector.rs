@@ -0,0 +1,57 @@
+//! The pixel inspector's probe reports what the shader did to one pixel on the way to the output.
+
+mod common;
+
+use image::{DynamicImage, RgbaImage};
+use vibedither::pipeline::cpu::{self, Shader, SourceImage};
+use vibedither::pipeline::LUT_SIZE;
+use vibedither::{spline, CurvePoint, Look};
+use common::dither_look;
+
+fn ramp() -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 16, |x, _| image::Rgba([(x * 4) as u8, 128, 255 - (x * 4) as u8, 255])))
+}
+
+fn probes(img: &DynamicImage, look: &Look) -> (RgbaImage, Vec<cpu::PixelProbe>) {
+    let (curves, gradient, palette, threshold) = (look.curves_lut(), look.gradient_lut(), look.palette_lut(), look.threshold_map());
+    let out = cpu::render(img, &look.settings, &curves, &gradient, &palette, &threshold);
+    let (source, palette) = (SourceImage::from_image(img), cpu::decode_palette(&palette));
+    let shader = Shader { source: &source, settings: &look.settings, curves: &curves, gradient: &gradient, palette: &palette, threshold: &threshold };
+    (out, (0..img.width()).map(|x| shader.probe(x, 5)).collect())
+}
+
+#[test]
+fn probe_matches_the_rendered_pixel() {
+    let img = ramp();
+    let mut look = Look::default();
+    look.settings.exposure = 0.5;
+    let (out, probes) = probes(&img, &look);
+    for (x, probe) in probes.iter().enumerate() {
+        assert_eq!(cpu::encode_srgb8(probe.output), out.get_pixel(x as u32, 5).0);
+        assert_eq!(probe.source[3], 1.0);
+        assert!(probe.noise.is_none(), "nothing is dithered");
+    }
+}
+
+#[test]
+fn ordered_dither_reports_the_threshold_it_compared() {
+    let img = ramp();
+    let look = dither_look(3);
+    let (out, probes) = probes(&img, &look);
+    for (x, probe) in probes.iter().enumerate() {
+        let noise = probe.noise.expect("an ordered dither has a threshold per pixel");
+        assert!((0.0..=1.0).contains(&noise));
+        assert_eq!(cpu::encode_srgb8(probe.output), out.get_pixel(x as u32, 5).0);
+    }
+}
+
+#[test]
+fn a_picked_curve_point_lands_on_the_baked_curve() {
+    let mut curves = spline::default_curves();
+    curves[1] = vec![CurvePoint::new(0.0, 0.0), CurvePoint::new(0.3, 0.55), CurvePoint::new(1.0, 1.0)];
+    let lut = spline::build_curves_lut(&curves);
+    for i in [37, 1000, 2500, LUT_SIZE - 300] {
+        let x = i as f32 / (LUT_SIZE - 1) as f32;
+        assert!((spline::curve_at(&curves[1], x) - lut[i][0]).abs() < 1e-5, "curve at {} is off the LUT", x);
+    }
+}
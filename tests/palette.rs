@@ -0,0 +1,83 @@
+//! Palette dithering: every mode has to pick only palette entries, mixed in the right proportion.
+
+mod common;
+
+use image::{DynamicImage, RgbaImage};
+use vibedither::pipeline::{cpu, DITHER_ERROR_DIFFUSION, DITHER_STIPPLING};
+use vibedither::{palette, preset, Look};
+use common::{cpu_renderer, dither_look, flat_color};
+
+const RED: [u8; 3] = [255, 0, 0];
+const BLUE: [u8; 3] = [0, 0, 255];
+
+fn palette_look(d_type: usize, palette: &[[u8; 3]]) -> Look {
+    let mut look = dither_look(d_type);
+    look.settings.dither_color = 1.0;
+    look.settings.palette_enabled = 1.0;
+    look.palette = palette.to_vec();
+    look
+}
+
+fn share_of(out: &RgbaImage, c: [u8; 3]) -> f32 {
+    out.pixels().filter(|p| p.0[..3] == c).count() as f32 / (out.width() * out.height()) as f32
+}
+
+#[test]
+fn two_color_palette_mixes_in_proportion() {
+    let cpu = cpu_renderer();
+    // A quarter of the way from red to blue in linear light.
+    let purple = [(cpu::linear_to_srgb(0.75) * 255.0).round() as u8, 0, (cpu::linear_to_srgb(0.25) * 255.0).round() as u8];
+    for d_type in [3, 4, DITHER_ERROR_DIFFUSION] {
+        let out = cpu.render(&flat_color(64, 64, purple), &palette_look(d_type, &[RED, BLUE])).unwrap();
+        assert_eq!(share_of(&out, RED) + share_of(&out, BLUE), 1.0, "type {} left the palette", d_type);
+        assert!((share_of(&out, BLUE) - 0.25).abs() < 0.03, "type {}: {:.3} blue, expected 0.25", d_type, share_of(&out, BLUE));
+    }
+}
+
+#[test]
+fn every_mode_stays_inside_the_palette() {
+    let cpu = cpu_renderer();
+    let palette = [[0, 0, 0], [255, 255, 255], [200, 40, 40], [40, 160, 60], [50, 60, 200], [240, 220, 60]];
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(48, 32, |x, y| image::Rgba([(x * 5) as u8, (y * 8) as u8, ((x + y) * 3) as u8, 255])));
+    for d_type in [0, 1, 2, 3, 4, 5, 8, DITHER_ERROR_DIFFUSION, 11, DITHER_STIPPLING] {
+        for color in [0.0, 1.0] {
+            let mut look = palette_look(d_type, &palette);
+            look.settings.dither_enabled = if d_type > 0 { 1.0 } else { 0.0 };
+            look.settings.dither_color = color;
+            look.settings.stipple_points = 200.0;
+            let out = cpu.render(&img, &look).unwrap();
+            assert!(out.pixels().all(|p| palette.iter().any(|c| p.0[..3] == *c)), "type {} (color {}) left the palette", d_type, color);
+        }
+    }
+}
+
+#[test]
+fn a_single_color_palette_fills_the_image() {
+    let out = cpu_renderer().render(&flat_color(16, 16, [90, 90, 90]), &palette_look(3, &[BLUE])).unwrap();
+    assert_eq!(share_of(&out, BLUE), 1.0);
+}
+
+#[test]
+fn builtin_palettes_are_addressable_by_id() {
+    let mut ids: Vec<&str> = palette::builtin::BUILTINS.iter().map(|b| b.id).collect();
+    for b in &palette::builtin::BUILTINS {
+        assert!(!b.colors.is_empty() && b.colors.len() <= palette::MAX_COLORS, "{}", b.id);
+        assert!(b.colors.iter().enumerate().all(|(i, c)| !b.colors[..i].contains(c)), "{} repeats a color", b.id);
+        assert_eq!(palette::builtin::find(&b.id.to_uppercase()).unwrap().id, b.id);
+    }
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), palette::builtin::BUILTINS.len(), "ids are unique");
+
+    // Presets store the id, which wins over the color list written next to it.
+    let mut look = palette_look(3, &[RED, BLUE]);
+    look.set_builtin_palette("gameboy").unwrap();
+    let text = preset::to_string(&look).unwrap();
+    assert!(text.contains("\"palette_name\": \"gameboy\""));
+    assert_eq!(preset::from_str(&text).unwrap(), look);
+    let edited = text.replace("\"palette_name\": \"gameboy\"", "\"palette_name\": \"pico-8\"");
+    assert_eq!(preset::from_str(&edited).unwrap().palette, palette::builtin::find("pico-8").unwrap().colors);
+    let unknown = text.replace("\"palette_name\": \"gameboy\"", "\"palette_name\": \"vectrex\"");
+    let fallback = preset::from_str(&unknown).unwrap();
+    assert_eq!((fallback.palette, fallback.palette_name), (look.palette, None));
+}
@@ -0,0 +1,49 @@
+//! Palette extraction: every method recovers the colors of a flat image and keeps locked ones,
+//! and extracted palettes survive the trip through the exporters.
+
+use image::{Rgba, RgbaImage};
+use vibedither::palette::{export, extract::{extract, Method}, import};
+
+const QUADRANTS: [[u8; 3]; 4] = [[20, 20, 20], [200, 40, 30], [30, 90, 220], [240, 240, 200]];
+
+/// Four equal flat quadrants, with a little noise on one of them.
+fn quadrants() -> RgbaImage {
+    RgbaImage::from_fn(120, 80, |x, y| {
+        let c = QUADRANTS[(x >= 60) as usize + 2 * (y >= 40) as usize];
+        let jitter = if x < 60 && y < 40 { ((x * 7 + y * 13) % 5) as u8 } else { 2 };
+        Rgba([c[0] + jitter - 2, c[1] + jitter - 2, c[2] + jitter - 2, 255])
+    })
+}
+
+#[test]
+fn every_method_finds_the_quadrant_colors() {
+    for method in Method::ALL {
+        let colors = extract(&quadrants(), 4, method, &[]).unwrap();
+        assert_eq!(colors.len(), 4, "{}", method.name());
+        for want in QUADRANTS {
+            assert!(colors.iter().any(|got| (0..3).all(|ch| got[ch].abs_diff(want[ch]) <= 2)), "{}: no match for {:?} in {:?}", method.name(), want, colors);
+        }
+    }
+}
+
+#[test]
+fn locked_colors_are_kept_and_fill_a_slot() {
+    let red = [255, 0, 0];
+    for method in Method::ALL {
+        let colors = extract(&quadrants(), 4, method, &[red]).unwrap();
+        assert!(colors.contains(&red), "{}", method.name());
+        assert!(colors.len() <= 4, "{}", method.name());
+    }
+    assert!(extract(&quadrants(), 1, Method::KMeans, &[[0; 3], [255; 3]]).is_err());
+    // Flat images have fewer colors than asked for, not duplicates.
+    let flat = RgbaImage::from_pixel(8, 8, Rgba([9, 99, 199, 255]));
+    assert_eq!(extract(&flat, 8, Method::MedianCut, &[]).unwrap(), vec![[9, 99, 199]]);
+}
+
+#[test]
+fn extracted_palettes_export_and_import() {
+    let colors = extract(&quadrants(), 4, Method::Octree, &[]).unwrap();
+    assert_eq!(import::parse_text(&export::to_gpl(&colors, "Quadrants")).unwrap(), colors);
+    assert_eq!(import::parse_text(&export::to_hex(&colors)).unwrap(), colors);
+    assert!(export::to_gpl(&colors, "Quadrants").starts_with("GIMP Palette\nName: Quadrants\n"));
+}
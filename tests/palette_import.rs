@@ -0,0 +1,85 @@
+//! Palette importers: each format reads back the colors it was written with and malformed files
+//! name what is wrong.
+
+use vibedither::palette::{self, import};
+
+const COLORS: [[u8; 3]; 3] = [[0, 0, 0], [255, 128, 0], [18, 52, 86]];
+
+fn error_of<T: std::fmt::Debug>(r: anyhow::Result<T>) -> String {
+    format!("{:#}", r.unwrap_err())
+}
+
+#[test]
+fn text_formats_read_back() {
+    let gpl = "GIMP Palette\nName: Test\nColumns: 3\n#\n  0   0   0\tBlack\n255 128   0\tOrange\n 18  52  86\n";
+    let jasc = "JASC-PAL\r\n0100\r\n3\r\n0 0 0\r\n255 128 0\r\n18 52 86\r\n";
+    let paint_net = "; paint.net Palette File\n; Colors: 3\nFF000000\nFFFF8000\nFF123456\n";
+    let hex = "000000\nff8000\n123456\n";
+    let pasted = "#000000, #FF8000 #123456";
+    for (name, text) in [("gpl", gpl), ("jasc", jasc), ("paint.net", paint_net), ("hex", hex), ("pasted", pasted)] {
+        assert_eq!(import::parse_text(text).unwrap(), COLORS, "{}", name);
+    }
+}
+
+/// An ASE file with one group around RGB, CMYK and Gray swatches.
+fn ase() -> Vec<u8> {
+    let mut blocks: Vec<(u16, Vec<u8>)> = vec![(0xC001, vec![0, 2, 0, b'G', 0, 0])];
+    for (model, values) in [(b"RGB ", vec![1.0f32, 0.5, 0.0]), (b"CMYK", vec![0.0, 0.0, 0.0, 1.0]), (b"Gray", vec![1.0])] {
+        let mut b = vec![0, 2, 0, b'x', 0, 0];
+        b.extend_from_slice(model);
+        for v in values { b.extend_from_slice(&v.to_be_bytes()); }
+        b.extend_from_slice(&2u16.to_be_bytes());
+        blocks.push((0x0001, b));
+    }
+    blocks.push((0xC002, vec![]));
+    let mut out = b"ASEF\0\x01\0\0".to_vec();
+    out.extend_from_slice(&(blocks.len() as u32).to_be_bytes());
+    for (kind, b) in blocks {
+        out.extend_from_slice(&kind.to_be_bytes());
+        out.extend_from_slice(&(b.len() as u32).to_be_bytes());
+        out.extend_from_slice(&b);
+    }
+    out
+}
+
+#[test]
+fn ase_reads_rgb_cmyk_and_gray() {
+    assert_eq!(import::parse(&ase(), "ase").unwrap(), vec![[255, 128, 0], [0, 0, 0], [255, 255, 255]]);
+    let truncated = &ase()[..40];
+    assert!(error_of(import::parse(truncated, "ase")).contains("file ends inside"));
+}
+
+#[test]
+fn aco_reads_version_1_and_2() {
+    let color = |space: u16, w: [u16; 4]| -> Vec<u8> { std::iter::once(space).chain(w).flat_map(u16::to_be_bytes).collect() };
+    let entries = [color(0, [0xffff, 0x8000, 0, 0]), color(1, [0, 0xffff, 0xffff, 0]), color(2, [0xffff, 0xffff, 0xffff, 0]), color(8, [10000, 0, 0, 0])];
+    let mut v1 = [1u16, entries.len() as u16].iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<u8>>();
+    for e in &entries { v1.extend_from_slice(e); }
+    let expected = vec![[255, 128, 0], [255, 0, 0], [0, 0, 0], [0, 0, 0]];
+    assert_eq!(import::parse(&v1, "aco").unwrap(), expected);
+
+    let mut v2 = [2u16, entries.len() as u16].iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<u8>>();
+    for e in &entries { v2.extend_from_slice(e); v2.extend_from_slice(&[0, 0, 0, 2, 0, b'n', 0, 0]); }
+    assert_eq!(import::parse(&v2, "aco").unwrap(), expected);
+
+    let lab = [1u16, 1, 7, 5000, 0, 0, 0].iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<u8>>();
+    assert!(error_of(import::parse(&lab, "aco")).contains("color space 7"));
+}
+
+#[test]
+fn malformed_files_say_where() {
+    assert!(error_of(import::parse_text("GIMP Palette\n255 0\n")).contains("line 2"));
+    assert!(error_of(import::parse_text("GIMP Palette\n300 0 0 Red\n")).contains("'300' is not a color channel"));
+    assert!(error_of(import::parse_text("JASC-PAL\n0100\n3\n0 0 0\n")).contains("header says 3 colors but the file has 1"));
+    assert!(error_of(import::parse_text("ff0000\nnope\n")).contains("line 2: 'nope'"));
+    assert!(error_of(import::parse_text("; empty\n")).contains("no colors"));
+    let too_many = (0..300).map(|i| format!("{:06x}\n", i)).collect::<String>();
+    assert!(error_of(import::parse_text(&too_many)).contains("300 colors"));
+}
+
+#[test]
+fn palette_becomes_a_dark_to_light_ramp() {
+    let stops = palette::to_gradient(&COLORS);
+    assert_eq!(stops.iter().map(|s| s.color).collect::<Vec<_>>(), vec![[0, 0, 0], [18, 52, 86], [255, 128, 0]]);
+    assert_eq!(stops.iter().map(|s| s.pos).collect::<Vec<_>>(), vec![0.0, 0.5, 1.0]);
+}
@@ -0,0 +1,50 @@
+//! `.vdpreset` files: what a look saves is what loads back, and damaged or foreign files load
+//! leniently or fail with an error instead of crashing later.
+
+use std::sync::Arc;
+use vibedither::threshold::matrix;
+use vibedither::{preset, CurvePoint, GradientStop, Look};
+
+#[test]
+fn a_full_look_round_trips() {
+    let mut look = Look::default();
+    look.settings.exposure = 0.75; look.settings.dither_enabled = 1.0; look.settings.dither_type = 3.0; look.settings.ordered_matrix = matrix::CUSTOM as f32;
+    look.curves[0] = vec![CurvePoint::new(0.0, 0.1), CurvePoint::new(0.4, 0.6), CurvePoint::new(1.0, 0.9)];
+    look.curves[3] = vec![CurvePoint::new(0.0, 0.0), CurvePoint::new(1.0, 0.5)];
+    look.gradient = vec![GradientStop::new(0, 0.0, [10, 20, 30]), GradientStop::new(1, 0.3, [200, 100, 0]), GradientStop::new(2, 1.0, [255, 255, 240])];
+    look.palette = vec![[0, 0, 0], [255, 0, 77], [41, 173, 255]];
+    look.custom_matrix = Some(Arc::new(matrix::from_values(3, 2, &[5.0, 0.0, 2.0, 4.0, 1.0, 3.0])));
+    assert_eq!(preset::from_str(&preset::to_string(&look).unwrap()).unwrap(), look);
+}
+
+#[test]
+fn missing_fields_fall_back_to_defaults() {
+    let look = preset::from_str(r#"{"settings":{"exposure":0.5},"curves":{"red":[[0,0],[1,0.8]]}}"#).unwrap();
+    let mut expected = Look::default();
+    expected.settings.exposure = 0.5;
+    expected.curves[1] = vec![CurvePoint::new(0.0, 0.0), CurvePoint::new(1.0, 0.8)];
+    assert_eq!(look, expected);
+    assert_eq!(preset::from_str("{}").unwrap(), Look::default());
+}
+
+#[test]
+fn newer_versions_and_unknown_fields_still_load() {
+    let look = preset::from_str(r#"{"version":99,"layers":[1,2],"settings":{"dither_scale":2,"future_knob":3},"curves":{"alpha":[[0,1]]}}"#).unwrap();
+    assert_eq!(look.settings.dither_scale, 2.0);
+    assert_eq!(look.curves, Look::default().curves);
+}
+
+#[test]
+fn bad_custom_matrices_are_rejected() {
+    let with_matrix = |m: &str| preset::from_str(&format!(r#"{{"custom_matrix":{}}}"#, m));
+    for bad in [
+        r#"{"width":65536,"height":65536,"values":[]}"#,
+        r#"{"width":0,"height":4,"values":[]}"#,
+        r#"{"width":2,"height":2,"values":[0,1,2]}"#,
+        r#"{"width":300,"height":1,"values":[0]}"#,
+        r#"{"width":2,"height":1,"values":[0,1e39]}"#,
+    ] {
+        assert!(with_matrix(bad).is_err(), "{} loaded", bad);
+    }
+    assert_eq!(with_matrix(r#"{"width":2,"height":1,"values":[7,3]}"#).unwrap().custom_matrix.unwrap().values, vec![0.5, 0.0]);
+}
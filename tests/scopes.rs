@@ -0,0 +1,30 @@
+//! Histogram and waveform scopes over rendered images.
+
+use image::{Rgba, RgbaImage};
+use vibedither::scopes::{Histogram, Waveform};
+
+#[test]
+fn histogram_counts_levels_per_channel() {
+    let mut img = RgbaImage::from_pixel(4, 2, Rgba([255, 0, 0, 255]));
+    img.put_pixel(0, 0, Rgba([128, 128, 128, 255]));
+    img.put_pixel(1, 0, Rgba([10, 20, 30, 0]));
+    let h = Histogram::of(&img);
+    assert_eq!(h.rgb[0][255], 6);
+    assert_eq!(h.rgb[1][0], 6);
+    assert_eq!(h.channel(0)[128], 1, "gray sits at its own level in luminance");
+    assert_eq!(h.channel(0)[54], 6, "pure red has a Rec. 709 luma of 54");
+    assert_eq!(h.luma.iter().sum::<u32>(), 7, "transparent pixels are left out");
+}
+
+#[test]
+fn waveform_follows_a_horizontal_ramp() {
+    let img = RgbaImage::from_fn(256, 8, |x, _| Rgba([x as u8, x as u8, x as u8, 255]));
+    let w = Waveform::of(&img, 16);
+    assert_eq!(w.columns, 16);
+    for col in 0..16 {
+        let levels: Vec<usize> = (0..256).filter(|&l| w.counts[col * 256 + l] > 0).collect();
+        assert_eq!(levels.first(), Some(&(col * 16)));
+        assert_eq!(levels.last(), Some(&(col * 16 + 15)));
+    }
+    assert_eq!(w.peak(), 8);
+}
@@ -0,0 +1,57 @@
+//! Weighted Voronoi stippling (`dither_type` 12): points follow darkness and dots follow tone.
+
+mod common;
+
+use image::{DynamicImage, RgbaImage};
+use vibedither::pipeline::{cpu, stipple, DITHER_STIPPLING};
+use vibedither::Look;
+use common::{cpu_renderer, dither_look};
+
+fn stipple_look(points: f32) -> Look {
+    let mut look = dither_look(DITHER_STIPPLING);
+    look.settings.stipple_points = points;
+    look.settings.stipple_iterations = 20.0;
+    look
+}
+
+/// Coefficient of variation of the dark mass each point owns.
+fn spread(stipples: &[stipple::Stipple]) -> f32 {
+    let mass: Vec<f32> = stipples.iter().map(|s| s.density * s.area).collect();
+    let mean = mass.iter().sum::<f32>() / mass.len() as f32;
+    (mass.iter().map(|m| (m - mean) * (m - mean)).sum::<f32>() / mass.len() as f32).sqrt() / mean
+}
+
+#[test]
+fn points_settle_in_the_dark_and_are_reproducible() {
+    let (cols, rows) = (160, 80);
+    // Dark on the left, paper on the right.
+    let density: Vec<f32> = (0..cols * rows).map(|i| if i % cols < cols / 2 { 0.8 } else { 0.0 }).collect();
+    let colors = vec![[0.2; 3]; cols * rows];
+    let relaxed = stipple::relax(&density, &colors, cols, rows, 200, 30);
+    assert_eq!(relaxed.len(), 200);
+    assert!(relaxed.iter().all(|s| s.x < cols as f32 / 2.0), "a point drifted into the white half");
+    assert_eq!(relaxed, stipple::relax(&density, &colors, cols, rows, 200, 30));
+    // Relaxation evens out how much of the dark half each point stands for.
+    let scattered = stipple::relax(&density, &colors, cols, rows, 200, 0);
+    assert!(spread(&relaxed) < spread(&scattered) * 0.6, "relaxation did not even out the regions: {:.3} vs {:.3}", spread(&relaxed), spread(&scattered));
+}
+
+#[test]
+fn ink_coverage_follows_tone() {
+    let cpu = cpu_renderer();
+    for v in [90u8, 180] {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(96, 96, image::Rgba([v, v, v, 255])));
+        let out = cpu.render(&img, &stipple_look(300.0)).unwrap();
+        assert!(out.pixels().all(|p| p[0] == 0 || p[0] == 255), "stipples are not one-bit");
+        let ink = out.pixels().filter(|p| p[0] == 0).count() as f32 / (96.0 * 96.0);
+        let target = 1.0 - cpu::srgb_to_linear(v as f32 / 255.0);
+        assert!((ink - target).abs() < 0.06, "tone {}: {:.3} ink, expected {:.3}", v, ink, target);
+    }
+}
+
+#[test]
+fn white_stays_blank() {
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(40, 30, image::Rgba([255, 255, 255, 255])));
+    let out = cpu_renderer().render(&img, &stipple_look(500.0)).unwrap();
+    assert!(out.pixels().all(|p| p[0] == 255));
+}
@@ -0,0 +1,66 @@
+//! SVG export: the traced rectangles have to redraw the image exactly, one group per color.
+
+mod common;
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use vibedither::image_io::{encode_svg, resize_for_export, ExportFormat};
+use common::{cpu_renderer, dither_look};
+
+/// Paints the background rect and every `M x y h w v h h -w z` of each group back onto a canvas.
+fn rasterize(svg: &str, width: u32, height: u32) -> RgbaImage {
+    let mut img = RgbaImage::new(width, height);
+    let hex = |s: &str| Rgba([0, 2, 4].map(|i| u8::from_str_radix(&s[1 + i..3 + i], 16).unwrap()).into_iter().chain([255]).collect::<Vec<_>>().try_into().unwrap());
+    let attr = |line: &str, name: &str| line.split(&format!("{}=\"", name)).nth(1).map(|s| s.split('"').next().unwrap().to_string());
+    for line in svg.lines() {
+        if line.starts_with("<rect") { for p in img.pixels_mut() { *p = hex(&attr(line, "fill").unwrap()); } }
+        if !line.starts_with("<g") { continue; }
+        let color = hex(&attr(line, "fill").unwrap());
+        for rect in attr(line, "d").unwrap().split('z').filter(|r| !r.is_empty()) {
+            let n: Vec<u32> = rect.split(|c: char| !c.is_ascii_digit()).filter(|s| !s.is_empty()).map(|s| s.parse().unwrap()).collect();
+            for y in n[1]..n[1] + n[3] { for x in n[0]..n[0] + n[2] { img.put_pixel(x, y, color); } }
+        }
+    }
+    img
+}
+
+#[test]
+fn rectangles_redraw_the_dithered_image() {
+    let src = DynamicImage::ImageRgba8(RgbaImage::from_fn(96, 64, |x, y| { let v = ((x * 255 / 95 + y) % 256) as u8; Rgba([v, 255 - v, v / 2, 255]) }));
+    let mut look = dither_look(3);
+    look.settings.dither_color = 1.0;
+    look.settings.dither_scale = 4.0;
+    let out = cpu_renderer().render(&src, &look).unwrap();
+    let svg = encode_svg(&out, 192, 128);
+    assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="192" height="128" viewBox="0 0 96 64""#));
+    assert_eq!(rasterize(&svg, 96, 64), RgbaImage::from_fn(96, 64, |x, y| { let p = out.get_pixel(x, y); Rgba([p[0], p[1], p[2], 255]) }));
+
+    let mut colors: Vec<[u8; 3]> = out.pixels().map(|p| [p[0], p[1], p[2]]).collect();
+    colors.sort();
+    colors.dedup();
+    assert_eq!(svg.matches("<g ").count() + 1, colors.len(), "one group per color besides the background");
+}
+
+#[test]
+fn pixel_scaled_cells_merge_into_single_rects() {
+    // A 4x4 checkerboard of 8x8-pixel cells: every cell is one rectangle and black is the only group.
+    let img = RgbaImage::from_fn(32, 32, |x, y| if (x / 8 + y / 8) % 2 == 0 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) });
+    let svg = encode_svg(&img, 32, 32);
+    assert_eq!(svg.matches("<g ").count(), 1);
+    assert_eq!(svg.matches('M').count(), 8);
+    // Vertical bars extend across the whole image instead of one rect per row.
+    let bars = RgbaImage::from_fn(16, 40, |x, _| if x % 4 == 0 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) });
+    assert_eq!(encode_svg(&bars, 16, 40).matches('M').count(), 4);
+}
+
+#[test]
+fn svg_export_at_another_size_keeps_the_rendered_cells() {
+    let checker = RgbaImage::from_fn(32, 32, |x, y| if (x / 8 + y / 8) % 2 == 0 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) });
+    for (w, h) in [(48, 48), (32, 64)] {
+        let svg_img = resize_for_export(DynamicImage::ImageRgba8(checker.clone()), ExportFormat::Svg, w, h).to_rgba8();
+        assert_eq!(svg_img, checker, "SVG at {}x{} was resampled", w, h);
+        let svg = encode_svg(&svg_img, w, h);
+        assert!(svg.contains(&format!(r#"width="{}" height="{}" viewBox="0 0 32 32""#, w, h)));
+        assert_eq!(svg.matches('M').count(), 8);
+        assert_eq!(resize_for_export(DynamicImage::ImageRgba8(checker.clone()), ExportFormat::Png, w, h).to_rgba8().dimensions(), (w, h));
+    }
+}
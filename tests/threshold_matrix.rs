@@ -0,0 +1,38 @@
+//! Generated and imported ordered-dither matrices.
+
+use vibedither::threshold::matrix;
+
+#[test]
+fn recursive_bayer_matches_the_classic_tables() {
+    assert_eq!(matrix::bayer(2).values, [0.0, 0.5, 0.75, 0.25]);
+    let b4: Vec<f32> = [0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5].iter().map(|&r| r as f32 / 16.0).collect();
+    assert_eq!(matrix::bayer(4).values, b4);
+    let b64 = matrix::bayer(64);
+    assert_eq!((b64.width, b64.height), (64, 64));
+    let mut sorted = b64.values.clone();
+    sorted.sort_by(f32::total_cmp);
+    assert!(sorted.iter().enumerate().all(|(i, &v)| v == i as f32 / 4096.0));
+    // Sizes without a Bayer construction round up instead of falling back to a flat 0.5.
+    assert_eq!(matrix::bayer(6).width, 8);
+}
+
+#[test]
+fn line_screens_share_thresholds_along_each_line() {
+    let lines = matrix::lines(8);
+    for y in 0..8 { assert!(lines.values[y * 8..y * 8 + 8].iter().all(|&v| v == lines.values[y * 8])); }
+    let diag = matrix::diagonal(8);
+    assert_eq!(diag.at([1.0, 0.0]), diag.at([0.0, 1.0]));
+    let dot = matrix::clustered_dot(8);
+    assert!(dot.at([3.0, 3.0]) < dot.at([0.0, 0.0]));
+}
+
+#[test]
+fn csv_import_normalizes_ranks_and_reports_errors() {
+    let m = matrix::parse_csv("# 2x2 Bayer\n0, 2\n3; 1\n").unwrap();
+    assert_eq!((m.width, m.height), (2, 2));
+    assert_eq!(m.values, [0.0, 0.5, 0.75, 0.25]);
+    assert!(matrix::parse_csv("1 2 3\n4 5\n").unwrap_err().to_string().contains("row 2"));
+    assert!(matrix::parse_csv("1, x\n").is_err());
+    assert!(matrix::parse_csv("1, nan\n").is_err());
+    assert!(matrix::parse_csv("\n").is_err());
+}
@@ -0,0 +1,62 @@
+//! Working spaces: adjustments and dither decisions follow `adjust_space` and `dither_space`.
+
+mod common;
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use vibedither::{CurvePoint, Look};
+use common::{cpu_renderer, flat};
+
+const LINEAR: f32 = 0.0;
+const GAMMA: f32 = 1.0;
+
+fn white_share(out: &RgbaImage) -> f32 {
+    out.pixels().filter(|p| p[0] == 255).count() as f32 / (out.width() * out.height()) as f32
+}
+
+#[test]
+fn dither_decision_follows_the_dither_space() {
+    // sRGB 128 is 50% in gamma-encoded values but only 21.6% in linear light.
+    let cpu = cpu_renderer();
+    let mut look = Look::default();
+    look.settings.dither_enabled = 1.0;
+    for d_type in [3.0, 10.0] {
+        look.settings.dither_type = d_type;
+        for (space, expected) in [(LINEAR, 0.216), (GAMMA, 0.502)] {
+            look.settings.dither_space = space;
+            let share = white_share(&cpu.render(&flat(64, 64, 128), &look).unwrap());
+            assert!((share - expected).abs() < 0.02, "type {} in space {}: {:.3} white, expected {:.3}", d_type, space, share, expected);
+        }
+    }
+}
+
+#[test]
+fn neutral_look_keeps_shadow_detail_in_every_space() {
+    // The curves LUT is indexed by the adjust-space value. It has to be fine enough that even in
+    // linear light, where the darkest sRGB levels lie closest together, each keeps its own entry.
+    let cpu = cpu_renderer();
+    let src = DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 4, |x, _| Rgba([x as u8 * 4, 255 - x as u8 * 4, 128, 255])));
+    let worst_diff = |adjust: f32, dither: f32| {
+        let mut look = Look::default();
+        look.settings.adjust_space = adjust;
+        look.settings.dither_space = dither;
+        let out = cpu.render(&src, &look).unwrap();
+        out.pixels().zip(src.to_rgba8().pixels()).map(|(a, b)| (0..3).map(|c| a[c].abs_diff(b[c])).max().unwrap()).max().unwrap()
+    };
+    for (adjust, dither) in [(GAMMA, GAMMA), (GAMMA, LINEAR), (LINEAR, LINEAR), (LINEAR, GAMMA)] {
+        let diff = worst_diff(adjust, dither);
+        assert!(diff <= 2, "adjust space {} / dither space {} moves a level by {}", adjust, dither, diff);
+    }
+}
+
+#[test]
+fn curves_apply_to_the_adjust_space() {
+    // An inverting curve maps sRGB 64 to 191 on encoded values, but to almost white on linear ones.
+    let cpu = cpu_renderer();
+    let mut look = Look::default();
+    look.curves[0] = vec![CurvePoint::new(0.0, 1.0), CurvePoint::new(1.0, 0.0)];
+    for (space, expected) in [(GAMMA, 191), (LINEAR, 250)] {
+        look.settings.adjust_space = space;
+        let v = cpu.render(&flat(64, 64, 64), &look).unwrap().get_pixel(0, 0)[0];
+        assert!(v.abs_diff(expected) <= 2, "space {}: {} expected {}", space, v, expected);
+    }
+}